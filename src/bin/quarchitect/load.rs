@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use quarchitect::game_data;
+use quarchitect::wad;
+use quarchitect::{Texture, TextureInfo};
+
+// Deserialize a data file as JSON or RON depending on its extension
+pub fn data_file<T: serde::de::DeserializeOwned>(file: &str) -> Result<T, Box<dyn Error>> {
+    let contents = fs::read_to_string(file)?;
+
+    let extension = Path::new(file)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("json") => Ok(serde_json::from_str(&contents)?),
+        Some("ron") => Ok(ron::from_str(&contents)?),
+        _ => Err(format!("Unsupported data file {}, expected .json or .ron", file).into()),
+    }
+}
+
+pub fn forge_game_data(
+    file: &Option<String>,
+) -> Result<game_data::forge::GameData, Box<dyn Error>> {
+    match file {
+        Some(file) => data_file(file),
+        None => Ok(game_data::forge::GameData::default()),
+    }
+}
+
+pub fn quarchitect_game_data(file: &Option<String>) -> Result<game_data::GameData, Box<dyn Error>> {
+    match file {
+        Some(file) => data_file(file),
+        None => Ok(game_data::GameData::default()),
+    }
+}
+
+// Texture sizes from every WAD, with later WADs taking precedence
+pub fn texture_info(wad_files: &[String]) -> Result<TextureInfo, Box<dyn Error>> {
+    let mut textures = HashMap::new();
+
+    for wad_file in wad_files {
+        for mip_texture in quarchitect::wad::read_texture_headers(wad_file, None)? {
+            textures.insert(
+                mip_texture.name,
+                Texture::new(mip_texture.width, mip_texture.height),
+            );
+        }
+    }
+
+    Ok(TextureInfo(textures))
+}
+
+// Decode a PNG into a WAD source image named after the file stem
+pub fn png_image(file: &str) -> Result<wad::Image, Box<dyn Error>> {
+    let name = match Path::new(file).file_stem() {
+        Some(stem) => stem.to_string_lossy().into_owned(),
+        None => return Err(format!("No texture name in {}", file).into()),
+    };
+
+    let mut decoder = png::Decoder::new(fs::File::open(file)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let data = &buf[..info.buffer_size()];
+
+    let image = match info.color_type {
+        png::ColorType::Rgb => wad::Image::from_rgb(name, info.width, info.height, data)?,
+        png::ColorType::Rgba => wad::Image::from_rgba(name, info.width, info.height, data)?,
+        png::ColorType::Grayscale => {
+            let pixels = data.iter().map(|v| [*v, *v, *v, 255]).collect();
+            wad::Image::new(name, info.width, info.height, pixels)
+        }
+        png::ColorType::GrayscaleAlpha => {
+            let pixels = data
+                .chunks_exact(2)
+                .map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+                .collect();
+            wad::Image::new(name, info.width, info.height, pixels)
+        }
+        png::ColorType::Indexed => {
+            return Err(format!("Unexpanded indexed PNG {}", file).into());
+        }
+    };
+
+    Ok(image)
+}
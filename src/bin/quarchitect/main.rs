@@ -0,0 +1,603 @@
+use std::error::Error;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use quarchitect::diagnostics::{Level, StdoutSink};
+use quarchitect::export;
+use quarchitect::lint::{LintCheck, LintSettings};
+use quarchitect::scene_tree::SceneTreeNode;
+use quarchitect::wad::{ExportSettings, ImageFileFormat, WadFormat};
+use quarchitect::watch::{WatchControl, WatchSettings};
+use quarchitect::{
+    BakeSettings, BuildReport, Config, Diagnostics, TextureBlacklist, TexturePreset, UnwrapSettings,
+};
+
+mod load;
+
+#[derive(Parser)]
+#[command(name = "quarchitect", version, about = "Quake map processing")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build a map into a scene file
+    Build(Box<BuildArgs>),
+    /// Print token differences between two maps
+    Diff { map_a: String, map_b: String },
+    /// Inspect or unpack WAD files
+    Wad {
+        #[command(subcommand)]
+        command: WadCommand,
+    },
+    /// Work with Forge game data definitions
+    Fgd {
+        #[command(subcommand)]
+        command: FgdCommand,
+    },
+    /// Print entity, brush and texture counts for a map
+    Stats { map: String },
+    /// Check a map for common level design errors
+    Lint(LintArgs),
+}
+
+#[derive(Subcommand)]
+enum WadCommand {
+    /// List every lump in a WAD
+    List { wad: String },
+    /// Write raw lumps out as .lmp files
+    Extract {
+        wad: String,
+        /// Output directory
+        #[arg(short, long, default_value = ".")]
+        output: String,
+        /// Only extract lumps with these names
+        #[arg(long = "name")]
+        names: Vec<String>,
+    },
+    /// Build a WAD from PNG images, each named after its file stem
+    Build {
+        /// Output WAD file
+        output: String,
+        #[arg(required = true)]
+        images: Vec<String>,
+        /// Quake palette.lmp, writes WAD2 quantized to it instead of WAD3 with per-texture palettes
+        #[arg(long)]
+        palette: Option<String>,
+    },
+    /// Write textures out as images, '{' textures with alpha
+    Export {
+        wad: String,
+        /// Output directory
+        #[arg(short, long, default_value = ".")]
+        output: String,
+        /// Only export textures with these names
+        #[arg(long = "name")]
+        names: Vec<String>,
+        #[arg(short, long, value_enum, default_value = "png")]
+        format: ImageFormat,
+        /// Quake palette.lmp, required for WAD2 textures
+        #[arg(long)]
+        palette: Option<String>,
+        /// Also write mip levels 1 to 3
+        #[arg(long)]
+        mips: bool,
+        /// Also write fullbright pixels as an emissive mask
+        #[arg(long)]
+        emissive: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FgdCommand {
+    /// Check a Forge game data file for errors
+    Validate {
+        forge_game_data: String,
+        /// Quarchitect game data whose classnames must be defined
+        #[arg(long)]
+        game_data: Option<String>,
+        /// Write the generated FGD here if validation passes
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(clap::Args)]
+struct LintArgs {
+    map: String,
+    /// Forge game data file (.json or .ron), enables class definition checks
+    #[arg(long)]
+    forge_game_data: Option<String>,
+    /// WAD files to check textures against, enables missing texture checks
+    #[arg(long = "wad")]
+    wads: Vec<String>,
+    /// Grid size vertices are expected to snap to
+    #[arg(long, default_value_t = 1.0)]
+    grid: f32,
+    /// Checks to skip, e.g. off-grid-vertex
+    #[arg(long = "disable")]
+    disabled: Vec<LintCheck>,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum Preset {
+    Quake,
+    Quake2,
+    HalfLife,
+    Trenchbroom,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum ImageFormat {
+    Png,
+    Tga,
+}
+
+#[derive(Copy, Clone, PartialEq, ValueEnum)]
+enum Format {
+    Gltf,
+    Glb,
+    Obj,
+    Tscn,
+    Json,
+}
+
+#[derive(clap::Args)]
+struct BuildArgs {
+    map: String,
+    /// Output file
+    #[arg(short, long)]
+    output: String,
+    /// Output format, inferred from the output extension by default
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+    /// Quarchitect game data file (.json or .ron)
+    #[arg(long)]
+    game_data: Option<String>,
+    /// Forge game data file (.json or .ron)
+    #[arg(long)]
+    forge_game_data: Option<String>,
+    /// WAD files to read texture sizes from
+    #[arg(long = "wad")]
+    wads: Vec<String>,
+    /// Directories or archives to find the WADs named by worldspawn in, after the map's directory
+    #[arg(long = "wad-path")]
+    wad_paths: Vec<String>,
+    /// Directories of loose image textures, named by their path relative to the directory
+    #[arg(long = "texture-root")]
+    texture_roots: Vec<String>,
+    /// Textures whose brushes get no visual mesh; pair with --no-collision to also drop collision.
+    /// '*' matches any characters, e.g. clip*
+    #[arg(long = "blacklist-brush")]
+    blacklist_brush: Vec<String>,
+    /// Textures whose faces get no visual mesh; pair with --no-collision to also drop collision
+    #[arg(long = "blacklist-plane")]
+    blacklist_plane: Vec<String>,
+    /// Blacklist the special textures of a game or editor
+    #[arg(long = "blacklist-preset", value_enum)]
+    blacklist_presets: Vec<Preset>,
+    /// Blacklisted textures that lose their collision too, rather than only their visuals
+    #[arg(long = "no-collision")]
+    no_collision: Vec<String>,
+    /// Generate lightmap UVs
+    #[arg(long)]
+    unwrap: bool,
+    /// Bake ambient occlusion and lights into vertex colors
+    #[arg(long)]
+    bake: bool,
+    /// Include collision geometry in OBJ output
+    #[arg(long)]
+    collision: bool,
+    /// Image extension OBJ materials reference textures with, or empty for untextured materials
+    #[arg(long, default_value = "png")]
+    texture_extension: String,
+    /// Directory for the build cache
+    #[cfg(feature = "cache")]
+    #[arg(long)]
+    cache: Option<String>,
+    /// Print debug output, twice for trace output
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Print per-class counts, stage timings and missing data after building
+    #[arg(long, conflicts_with = "watch")]
+    report: bool,
+    /// Rebuild whenever the map, WADs or game data change
+    #[arg(long)]
+    watch: bool,
+    /// Loopback address to push watch builds to, e.g. 127.0.0.1:9876
+    #[arg(long, requires = "watch")]
+    socket: Option<SocketAddr>,
+}
+
+fn output_format(args: &BuildArgs) -> Result<Format, Box<dyn Error>> {
+    if let Some(format) = args.format {
+        return Ok(format);
+    }
+
+    let extension = Path::new(&args.output)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("gltf") => Ok(Format::Gltf),
+        Some("glb") => Ok(Format::Glb),
+        Some("obj") => Ok(Format::Obj),
+        Some("tscn") | Some("escn") => Ok(Format::Tscn),
+        Some("json") => Ok(Format::Json),
+        _ => Err(format!("Cannot infer output format from {}", args.output).into()),
+    }
+}
+
+fn build_config(args: &BuildArgs) -> Result<Config, Box<dyn Error>> {
+    let texture_info = load::texture_info(&args.wads)?;
+    let mut texture_blacklist =
+        TextureBlacklist::new(args.blacklist_brush.clone(), args.blacklist_plane.clone());
+    for preset in &args.blacklist_presets {
+        texture_blacklist = texture_blacklist.with_preset(match preset {
+            Preset::Quake => TexturePreset::Quake,
+            Preset::Quake2 => TexturePreset::Quake2,
+            Preset::HalfLife => TexturePreset::HalfLife,
+            Preset::Trenchbroom => TexturePreset::TrenchBroom,
+        });
+    }
+    texture_blacklist
+        .no_collision
+        .extend(args.no_collision.iter().cloned());
+    let forge_game_data = load::forge_game_data(&args.forge_game_data)?;
+    let quarchitect_game_data = load::quarchitect_game_data(&args.game_data)?;
+
+    let mut config = Config::new(
+        &args.map,
+        texture_info,
+        texture_blacklist,
+        forge_game_data,
+        quarchitect_game_data,
+    );
+
+    let level = match args.verbose {
+        0 => Level::Info,
+        1 => Level::Debug,
+        _ => Level::Trace,
+    };
+    config = config.with_diagnostics(Arc::new(StdoutSink::new(level)));
+
+    if !args.wad_paths.is_empty() {
+        config = config.with_wad_search_paths(args.wad_paths.clone());
+    }
+
+    if !args.texture_roots.is_empty() {
+        config = config.with_texture_roots(args.texture_roots.clone());
+    }
+
+    if args.unwrap {
+        config = config.with_uv_unwrap(UnwrapSettings::default());
+    }
+
+    if args.bake {
+        config = config.with_vertex_bake(BakeSettings::default());
+    }
+
+    #[cfg(feature = "cache")]
+    if let Some(cache) = &args.cache {
+        config = config.with_cache(quarchitect::BuildCache::new(cache));
+    }
+
+    Ok(config)
+}
+
+fn write_output(
+    args: &BuildArgs,
+    format: Format,
+    scene_tree: &[SceneTreeNode],
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        Format::Gltf => export::gltf::write_gltf(scene_tree, &args.output)?,
+        Format::Glb => export::gltf::write_glb(scene_tree, &args.output)?,
+        Format::Obj => {
+            let texture_extension =
+                Some(args.texture_extension.as_str()).filter(|ext| !ext.is_empty());
+            let settings = export::obj::ObjSettings::new(args.collision, texture_extension);
+            export::obj::write_obj(scene_tree, &args.output, &settings)?
+        }
+        Format::Tscn => export::tscn::write_tscn(scene_tree, &args.output, &Default::default())?,
+        Format::Json => fs::write(&args.output, serde_json::to_string(scene_tree)?)?,
+    }
+
+    Ok(())
+}
+
+fn build(args: BuildArgs) -> Result<(), Box<dyn Error>> {
+    let format = output_format(&args)?;
+
+    if !args.watch {
+        let (scene_tree, report) = quarchitect::run_with_report(build_config(&args)?)?;
+        if args.report {
+            print_report(&report);
+        }
+        return write_output(&args, format, &scene_tree);
+    }
+
+    let files = args
+        .wads
+        .iter()
+        .chain(args.game_data.iter())
+        .chain(args.forge_game_data.iter())
+        .map(PathBuf::from)
+        .collect();
+
+    let settings = WatchSettings {
+        files,
+        socket: args.socket,
+        ..WatchSettings::default()
+    };
+
+    quarchitect::watch::run(
+        &settings,
+        || build_config(&args),
+        |result| {
+            match result.and_then(|scene_tree| write_output(&args, format, &scene_tree)) {
+                Ok(()) => println!("Built {}", args.output),
+                Err(err) => eprintln!("Error: {}", err),
+            }
+            WatchControl::Continue
+        },
+    )
+}
+
+fn print_report(report: &BuildReport) {
+    if report.from_cache {
+        println!("Loaded from build cache");
+    }
+
+    println!(
+        "{:<32} {:>8} {:>8} {:>8} {:>10} {:>10}",
+        "Class", "Entities", "Brushes", "Faces", "Triangles", "Collision"
+    );
+    for (classname, stats) in &report.classes {
+        println!(
+            "{:<32} {:>8} {:>8} {:>8} {:>10} {:>10}",
+            classname,
+            stats.entities,
+            stats.brushes,
+            stats.faces,
+            stats.triangles,
+            stats.collision_shapes
+        );
+    }
+
+    println!("Timings:");
+    for timing in &report.timings {
+        println!("  {:<16} {:?}", timing.stage, timing.duration);
+    }
+    println!("  {:<16} {:?}", "total", report.total_duration());
+
+    for wad in &report.missing_wads {
+        println!("Missing WAD: {}", wad);
+    }
+
+    for texture in &report.missing_textures {
+        println!("Missing texture: {}", texture);
+    }
+
+    for classname in &report.unknown_classnames {
+        println!("Unknown classname: {}", classname);
+    }
+
+    for brush in &report.degenerate_brushes {
+        println!(
+            "Degenerate brush: entity {} ({}) brush {}",
+            brush.entity_idx,
+            brush.classname.as_deref().unwrap_or("no classname"),
+            brush.brush_idx
+        );
+    }
+
+    for fallback in &report.property_fallbacks {
+        println!(
+            "Property fallback: {} {} = {:?}",
+            fallback.classname, fallback.property, fallback.value
+        );
+    }
+}
+
+fn wad(command: WadCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        WadCommand::List { wad } => {
+            for entry in quarchitect::wad::read_entries(&wad)? {
+                println!(
+                    "{:<16} {} {:>8}{}",
+                    entry.name,
+                    entry.entry_type,
+                    entry.dsize,
+                    if entry.cmprs != 0 {
+                        " (compressed)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        WadCommand::Extract { wad, output, names } => {
+            fs::create_dir_all(&output)?;
+
+            for entry in quarchitect::wad::read_entries(&wad)? {
+                if !names.is_empty() && !names.iter().any(|name| name == &entry.name) {
+                    continue;
+                }
+
+                let file_name = format!("{}.lmp", quarchitect::wad::file_stem(&entry.name));
+                let data = quarchitect::wad::read_entry_data(&wad, &entry)?;
+                fs::write(PathBuf::from(&output).join(file_name), data)?;
+            }
+        }
+        WadCommand::Build {
+            output,
+            images,
+            palette,
+        } => {
+            let images = images
+                .iter()
+                .map(|image| load::png_image(image))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let format = match palette {
+                Some(palette) => {
+                    WadFormat::WAD2(Box::new(quarchitect::wad::palette::read_palette(&palette)?))
+                }
+                None => WadFormat::WAD3,
+            };
+
+            quarchitect::wad::write_wad(&output, &images, &format)?;
+        }
+        WadCommand::Export {
+            wad,
+            output,
+            names,
+            format,
+            palette,
+            mips,
+            emissive,
+        } => {
+            let palette = match palette {
+                Some(palette) => Some(quarchitect::wad::palette::read_palette(&palette)?),
+                None => None,
+            };
+
+            let settings = ExportSettings {
+                format: match format {
+                    ImageFormat::Png => ImageFileFormat::Png,
+                    ImageFormat::Tga => ImageFileFormat::Tga,
+                },
+                all_mips: mips,
+                emissive_mask: emissive,
+            };
+
+            let whitelist = if names.is_empty() { None } else { Some(names) };
+
+            let files = quarchitect::wad::export_textures(
+                &wad,
+                whitelist,
+                &output,
+                palette.as_ref(),
+                &settings,
+                &Diagnostics::default(),
+            )?;
+            println!("Wrote {} images to {}", files.len(), output);
+        }
+    }
+
+    Ok(())
+}
+
+fn fgd(command: FgdCommand) -> Result<(), Box<dyn Error>> {
+    match command {
+        FgdCommand::Validate {
+            forge_game_data,
+            game_data,
+            output,
+        } => {
+            let forge_game_data: quarchitect::game_data::forge::GameData =
+                load::data_file(&forge_game_data)?;
+
+            let mut errors = forge_game_data.validate();
+
+            if game_data.is_some() {
+                for entity in load::quarchitect_game_data(&game_data)?.entities {
+                    if !forge_game_data
+                        .definitions
+                        .iter()
+                        .any(|definition| definition.class_name == entity.classname)
+                    {
+                        errors.push(format!(
+                            "{}: No Forge definition for game data entity",
+                            entity.classname
+                        ));
+                    }
+                }
+            }
+
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+
+            if !errors.is_empty() {
+                return Err(format!("{} validation errors", errors.len()).into());
+            }
+
+            if let Some(output) = output {
+                forge_game_data.save(output)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn stats(map: &str) -> Result<(), Box<dyn Error>> {
+    let stats = quarchitect::run_stats(map)?;
+
+    println!("Entities: {}", stats.entity_count);
+    println!("Brushes: {}", stats.brush_count);
+    println!("Faces: {}", stats.plane_count);
+
+    println!("Classnames:");
+    for (classname, count) in &stats.classnames {
+        println!("  {:<32} {}", classname, count);
+    }
+
+    println!("Textures:");
+    for (texture, count) in &stats.textures {
+        println!("  {:<32} {}", texture, count);
+    }
+
+    Ok(())
+}
+
+fn lint(args: LintArgs) -> Result<(), Box<dyn Error>> {
+    let forge_game_data = load::forge_game_data(&args.forge_game_data)?;
+    let texture_info = load::texture_info(&args.wads)?;
+
+    let mut settings = LintSettings::new(args.disabled.into_iter().collect(), args.grid);
+    if args.forge_game_data.is_none() {
+        settings = settings.with_check(LintCheck::ClassDefinition, false);
+    }
+    if args.wads.is_empty() {
+        settings = settings.with_check(LintCheck::MissingTexture, false);
+    }
+
+    let findings = quarchitect::run_lint(&args.map, &settings, &forge_game_data, &texture_info)?;
+
+    for finding in &findings {
+        println!("{}:{}", args.map, finding);
+    }
+
+    if !findings.is_empty() {
+        return Err(format!("{} lint findings", findings.len()).into());
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Build(args) => build(*args),
+        Command::Diff { map_a, map_b } => quarchitect::run_diff(&map_a, &map_b),
+        Command::Wad { command } => wad(command),
+        Command::Fgd { command } => fgd(command),
+        Command::Stats { map } => stats(&map),
+        Command::Lint(args) => lint(args),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
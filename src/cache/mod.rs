@@ -0,0 +1,276 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
+
+use crate::game_data;
+use crate::scene_tree::SceneTreeNode;
+use crate::{BakeSettings, TextureBlacklist, TextureInfo, UnwrapSettings};
+
+const CACHE_MAGIC: &[u8; 4] = b"QRCH";
+
+// Bump whenever the scene tree layout or the pipeline output changes
+const CACHE_VERSION: u32 = 6;
+
+const HEADER_SIZE: usize = 4 + 4 + 32;
+
+pub type CacheKey = [u8; 32];
+
+#[derive(Debug, Clone)]
+pub struct BuildCache {
+    directory: PathBuf,
+}
+
+// Everything other than the map text that influences the built scene tree
+#[derive(serde::Serialize)]
+pub(crate) struct CacheInputs<'a> {
+    pub texture_info: &'a TextureInfo,
+    pub texture_blacklist: &'a TextureBlacklist,
+    pub forge_game_data: &'a game_data::forge::GameData,
+    pub quarchitect_game_data: &'a game_data::GameData,
+    pub uv_unwrap: &'a Option<UnwrapSettings>,
+    pub vertex_bake: &'a Option<BakeSettings>,
+}
+
+impl BuildCache {
+    pub fn new(directory: &str) -> BuildCache {
+        let directory = directory.into();
+        BuildCache { directory }
+    }
+
+    // Content hash of the map and its build inputs. Hash maps among the inputs serialize in
+    // sorted key order, so equal inputs always give the same bytes.
+    pub(crate) fn key(map: &str, inputs: &CacheInputs) -> Result<CacheKey, String> {
+        let inputs = match serde_json::to_vec(inputs) {
+            Ok(inputs) => inputs,
+            Err(err) => return Err(format!("Error serializing build inputs: {:?}", err)),
+        };
+
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_VERSION.to_le_bytes());
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update((map.len() as u64).to_le_bytes());
+        hasher.update(map.as_bytes());
+        hasher.update(&inputs);
+
+        Ok(hasher.finalize().into())
+    }
+
+    // One entry per map file, overwritten whenever its key changes
+    fn entry_path(&self, map_file: &str) -> PathBuf {
+        let name = Sha256::digest(map_file.as_bytes())
+            .iter()
+            .take(8)
+            .fold(String::new(), |acc, byte| acc + &format!("{:02x}", byte));
+
+        let stem = Path::new(map_file)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        self.directory.join(format!("{}-{}.qcache", stem, name))
+    }
+
+    pub fn load(&self, map_file: &str, key: &CacheKey) -> Option<Vec<SceneTreeNode>> {
+        let file = File::open(self.entry_path(map_file)).ok()?;
+
+        // Safety: cache entries are only ever replaced by rename, never written in place
+        let data = unsafe { Mmap::map(&file) }.ok()?;
+
+        if data.len() < HEADER_SIZE
+            || &data[0..4] != CACHE_MAGIC
+            || data[4..8] != CACHE_VERSION.to_le_bytes()
+            || &data[8..HEADER_SIZE] != key
+        {
+            return None;
+        }
+
+        bincode::deserialize(&data[HEADER_SIZE..]).ok()
+    }
+
+    pub fn store(
+        &self,
+        map_file: &str,
+        key: &CacheKey,
+        scene_tree: &[SceneTreeNode],
+    ) -> io::Result<()> {
+        let payload = bincode::serialize(scene_tree)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        fs::create_dir_all(&self.directory)?;
+
+        let path = self.entry_path(map_file);
+        let temp_path = path.with_extension("qcache.tmp");
+
+        let mut file = File::create(&temp_path)?;
+        file.write_all(CACHE_MAGIC)?;
+        file.write_all(&CACHE_VERSION.to_le_bytes())?;
+        file.write_all(key)?;
+        file.write_all(&payload)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(temp_path, path)
+    }
+
+    pub fn clear(&self, map_file: &str) -> io::Result<()> {
+        match fs::remove_file(self.entry_path(map_file)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene_tree::{CollisionGeometry, ConvexCollision};
+    use crate::Vector3;
+    use std::collections::HashMap;
+
+    fn test_inputs<'a>(
+        texture_info: &'a TextureInfo,
+        texture_blacklist: &'a TextureBlacklist,
+        forge_game_data: &'a game_data::forge::GameData,
+        quarchitect_game_data: &'a game_data::GameData,
+    ) -> CacheInputs<'a> {
+        CacheInputs {
+            texture_info,
+            texture_blacklist,
+            forge_game_data,
+            quarchitect_game_data,
+            uv_unwrap: &None,
+            vertex_bake: &None,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let directory =
+            std::env::temp_dir().join(format!("quarchitect-cache-{}", std::process::id()));
+        let cache = BuildCache::new(&directory.to_string_lossy());
+
+        let texture_info = TextureInfo(HashMap::new());
+        let texture_blacklist = TextureBlacklist::default();
+        let forge_game_data = game_data::forge::GameData::default();
+        let quarchitect_game_data = game_data::GameData {
+            entities: vec![],
+            worldspawn_layers: vec![],
+            texture_rules: vec![],
+        };
+        let inputs = test_inputs(
+            &texture_info,
+            &texture_blacklist,
+            &forge_game_data,
+            &quarchitect_game_data,
+        );
+
+        let key = BuildCache::key("{ }", &inputs).unwrap();
+        let stale_key = BuildCache::key("{ \"classname\" \"worldspawn\" }", &inputs).unwrap();
+        assert!(key != stale_key, "Map change did not change the cache key");
+
+        let scene_tree = vec![SceneTreeNode::collision_geometry(
+            Vector3::new(1.0, 2.0, 3.0),
+            CollisionGeometry::convex(vec![ConvexCollision::new(
+                Vector3::default(),
+                vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0)],
+            )]),
+        )];
+
+        cache.store("test.map", &key, &scene_tree).unwrap();
+
+        let loaded = cache.load("test.map", &key);
+        assert!(
+            format!("{:?}", loaded) == format!("{:?}", Some(&scene_tree)),
+            "Cached scene tree \"{:?}\" != \"{:?}\"",
+            loaded,
+            scene_tree
+        );
+
+        assert!(
+            cache.load("test.map", &stale_key).is_none(),
+            "Stale cache entry was loaded"
+        );
+
+        cache.clear("test.map").unwrap();
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn key_ignores_map_order() {
+        let names: Vec<String> = (0..64).map(|i| format!("texture{}", i)).collect();
+        let texture_info = |names: &mut dyn Iterator<Item = &String>| {
+            TextureInfo(
+                names
+                    .map(|name| (name.clone(), crate::Texture::new(16, 16)))
+                    .collect::<HashMap<_, _>>(),
+            )
+        };
+        let forward = texture_info(&mut names.iter());
+        let backward = texture_info(&mut names.iter().rev());
+
+        let texture_blacklist = TextureBlacklist::default();
+        let forge_game_data = game_data::forge::GameData::default();
+        let quarchitect_game_data = game_data::GameData::default();
+
+        let key = |texture_info| {
+            let inputs = test_inputs(
+                texture_info,
+                &texture_blacklist,
+                &forge_game_data,
+                &quarchitect_game_data,
+            );
+            BuildCache::key("{ }", &inputs).unwrap()
+        };
+
+        assert!(
+            key(&forward) == key(&backward),
+            "Equal inputs gave different cache keys"
+        );
+    }
+
+    #[test]
+    fn hit_skips_parse() {
+        let directory =
+            std::env::temp_dir().join(format!("quarchitect-cache-hit-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let map_file = directory.join("hit.map");
+        fs::write(&map_file, "{\n\"classname\" \"worldspawn\"\n}\n").unwrap();
+
+        let build = || {
+            let config = crate::Config::new(
+                &map_file.to_string_lossy(),
+                TextureInfo(HashMap::new()),
+                TextureBlacklist::default(),
+                game_data::forge::GameData::default(),
+                game_data::GameData::default(),
+            )
+            .with_cache(BuildCache::new(&directory.join("cache").to_string_lossy()));
+            let (_scene_tree, report) = crate::run_with_report(config).unwrap();
+            let stages: Vec<String> = report
+                .timings
+                .iter()
+                .map(|timing| timing.stage.clone())
+                .collect();
+            (report.from_cache, stages)
+        };
+
+        let (from_cache, stages) = build();
+        assert!(
+            !from_cache && stages.contains(&"parse".to_string()),
+            "First build {:?}",
+            stages
+        );
+
+        let (from_cache, stages) = build();
+        assert!(
+            from_cache && !stages.contains(&"parse".to_string()),
+            "Cached build {:?}",
+            stages
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Machine-readable identity of a diagnostic, so hosts can filter or annotate without parsing messages
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    Message,
+    MissingEntityDefinition {
+        classname: String,
+    },
+    MissingForgeDefinition {
+        classname: String,
+    },
+    MissingClassname {
+        origin: Option<String>,
+    },
+    AmbiguousWorldspawnLayer {
+        texture: String,
+    },
+    PropertyFallback {
+        classname: String,
+        property: String,
+        value: String,
+        expected: String,
+    },
+    CacheWriteFailed {
+        error: String,
+    },
+    CacheKeyFailed {
+        error: String,
+    },
+    InvalidSearchPath {
+        path: String,
+        error: String,
+    },
+    MissingWad {
+        wad: String,
+    },
+    WadReadFailed {
+        wad: String,
+        error: String,
+    },
+    MissingTexture {
+        texture: String,
+    },
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::Message => Ok(()),
+            DiagnosticKind::MissingEntityDefinition { classname } => {
+                write!(f, "No entity definition for classname {:?}", classname)
+            }
+            DiagnosticKind::MissingForgeDefinition { classname } => {
+                write!(f, "No forge definition for classname {:?}", classname)
+            }
+            DiagnosticKind::MissingClassname { origin } => match origin {
+                Some(origin) => write!(f, "Skipping entity without a classname at {}", origin),
+                None => write!(f, "Skipping entity without a classname"),
+            },
+            DiagnosticKind::AmbiguousWorldspawnLayer { texture } => write!(
+                f,
+                "Worldspawn layer data for texture {:?} is not a singleton",
+                texture
+            ),
+            DiagnosticKind::PropertyFallback {
+                classname,
+                property,
+                value,
+                expected,
+            } => write!(
+                f,
+                "Malformed {} value {:?} for property {:?} of {}",
+                expected, value, property, classname
+            ),
+            DiagnosticKind::CacheWriteFailed { error } => {
+                write!(f, "Failed to write build cache: {}", error)
+            }
+            DiagnosticKind::CacheKeyFailed { error } => {
+                write!(f, "Skipping build cache: {}", error)
+            }
+            DiagnosticKind::InvalidSearchPath { path, error } => {
+                write!(f, "Ignoring search path {:?}: {}", path, error)
+            }
+            DiagnosticKind::MissingWad { wad } => write!(
+                f,
+                "WAD {:?} is not in the map directory or any search path",
+                wad
+            ),
+            DiagnosticKind::WadReadFailed { wad, error } => {
+                write!(f, "Failed to read WAD {:?}: {}", wad, error)
+            }
+            DiagnosticKind::MissingTexture { texture } => {
+                write!(f, "Texture {:?} is not in any WAD", texture)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, kind: DiagnosticKind, message: String) -> Diagnostic {
+        Diagnostic {
+            level,
+            kind,
+            message,
+        }
+    }
+
+    pub fn message(level: Level, message: &str) -> Diagnostic {
+        Diagnostic::new(level, DiagnosticKind::Message, message.into())
+    }
+
+    pub fn warning(kind: DiagnosticKind) -> Diagnostic {
+        let message = kind.to_string();
+        Diagnostic::new(Level::Warning, kind, message)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.level, self.message)
+    }
+}
+
+pub trait DiagnosticSink: Send + Sync {
+    fn emit(&self, diagnostic: &Diagnostic);
+}
+
+// Prints diagnostics at or above a level, with warnings and errors on stderr
+#[derive(Debug, Copy, Clone)]
+pub struct StdoutSink {
+    pub level: Level,
+}
+
+impl StdoutSink {
+    pub fn new(level: Level) -> StdoutSink {
+        StdoutSink { level }
+    }
+}
+
+impl DiagnosticSink for StdoutSink {
+    fn emit(&self, diagnostic: &Diagnostic) {
+        if diagnostic.level < self.level {
+            return;
+        }
+
+        if diagnostic.level >= Level::Warning {
+            eprintln!("{}", diagnostic);
+        } else {
+            println!("{}", diagnostic);
+        }
+    }
+}
+
+// Forwards to the `log` crate facade under the "quarchitect" target
+#[cfg(feature = "log")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LogSink;
+
+#[cfg(feature = "log")]
+impl DiagnosticSink for LogSink {
+    fn emit(&self, diagnostic: &Diagnostic) {
+        let level = match diagnostic.level {
+            Level::Trace => log::Level::Trace,
+            Level::Debug => log::Level::Debug,
+            Level::Info => log::Level::Info,
+            Level::Warning => log::Level::Warn,
+            Level::Error => log::Level::Error,
+        };
+
+        log::log!(target: "quarchitect", level, "{}", diagnostic.message);
+    }
+}
+
+// Warnings and errors raised during a build
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticReport {
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level == Level::Warning)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.level == Level::Error)
+    }
+}
+
+// Handle threaded through the pipeline: forwards everything to the sink and keeps warnings
+#[derive(Clone, Default)]
+pub struct Diagnostics {
+    sink: Option<Arc<dyn DiagnosticSink>>,
+    collected: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl fmt::Debug for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Diagnostics")
+            .field("sink", &self.sink.is_some())
+            .finish()
+    }
+}
+
+impl Diagnostics {
+    pub fn new(sink: Arc<dyn DiagnosticSink>) -> Diagnostics {
+        Diagnostics {
+            sink: Some(sink),
+            collected: Arc::default(),
+        }
+    }
+
+    pub fn emit(&self, diagnostic: Diagnostic) {
+        if let Some(sink) = &self.sink {
+            sink.emit(&diagnostic);
+        }
+
+        if diagnostic.level >= Level::Warning {
+            if let Ok(mut collected) = self.collected.lock() {
+                collected.push(diagnostic);
+            }
+        }
+    }
+
+    pub fn trace(&self, message: &str) {
+        self.emit(Diagnostic::message(Level::Trace, message))
+    }
+
+    pub fn debug(&self, message: &str) {
+        self.emit(Diagnostic::message(Level::Debug, message))
+    }
+
+    pub fn info(&self, message: &str) {
+        self.emit(Diagnostic::message(Level::Info, message))
+    }
+
+    pub fn warning(&self, kind: DiagnosticKind) {
+        self.emit(Diagnostic::warning(kind))
+    }
+
+    pub fn report(&self) -> DiagnosticReport {
+        let diagnostics = match self.collected.lock() {
+            Ok(collected) => collected.clone(),
+            Err(_) => Vec::new(),
+        };
+
+        DiagnosticReport { diagnostics }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingSink(Mutex<usize>);
+
+    impl DiagnosticSink for CountingSink {
+        fn emit(&self, _diagnostic: &Diagnostic) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn collect_warnings() {
+        let sink = Arc::new(CountingSink(Mutex::new(0)));
+        let diagnostics = Diagnostics::new(sink.clone());
+
+        diagnostics.debug("Parse map");
+        diagnostics.warning(DiagnosticKind::MissingEntityDefinition {
+            classname: "func_door".into(),
+        });
+
+        let emitted = *sink.0.lock().unwrap();
+        assert!(emitted == 2, "Emitted {} diagnostics", emitted);
+
+        let report = diagnostics.report();
+        let warnings: Vec<&Diagnostic> = report.warnings().collect();
+        assert!(
+            warnings.len() == 1
+                && warnings[0].message == "No entity definition for classname \"func_door\"",
+            "Report warnings {:?}",
+            warnings
+        );
+    }
+}
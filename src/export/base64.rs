@@ -0,0 +1,75 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b0 = chunk[0] as u32;
+        let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+        let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+        let triple = (b0 << 16) | (b1 << 8) | b2;
+
+        encoded.push(ALPHABET[(triple >> 18) as usize & 63] as char);
+        encoded.push(ALPHABET[(triple >> 12) as usize & 63] as char);
+
+        if chunk.len() > 1 {
+            encoded.push(ALPHABET[(triple >> 6) as usize & 63] as char);
+        } else {
+            encoded.push('=');
+        }
+
+        if chunk.len() > 2 {
+            encoded.push(ALPHABET[triple as usize & 63] as char);
+        } else {
+            encoded.push('=');
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding() {
+        for (input, comp_str) in &[
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+        ] {
+            let encoded = encode(input.as_bytes());
+            assert!(
+                encoded.as_str() == *comp_str,
+                "Base64 string \"{}\" != \"{}\"",
+                encoded,
+                comp_str
+            );
+        }
+    }
+
+    #[test]
+    fn rfc4648_vectors() {
+        for (input, comp_str) in &[
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+            (&[0xfb, 0xff, 0xbf], "+/+/"),
+            (&[0x00, 0x10, 0x83, 0x10, 0x51, 0x87], "ABCDEFGH"),
+        ] {
+            let encoded = encode(input);
+            assert!(
+                encoded.as_str() == *comp_str,
+                "Base64 string \"{}\" != \"{}\"",
+                encoded,
+                comp_str
+            );
+        }
+    }
+}
@@ -0,0 +1,638 @@
+use std::collections::HashMap;
+use std::io;
+
+use super::json::Json;
+use super::y_up;
+use crate::game_data::forge::ChoiceData;
+use crate::game_data::{EntityType, Properties, Property, PropertyApplicationType};
+use crate::scene_tree::{
+    Actor, CollisionGeometry, MeshSurface, SceneTreeNode, SceneTreeType, VisualGeometry,
+};
+use crate::{Vector2, Vector3};
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const COMPONENT_FLOAT: u32 = 5126;
+const COMPONENT_UNSIGNED_INT: u32 = 5125;
+const TARGET_ARRAY_BUFFER: u32 = 34962;
+const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+#[derive(Default)]
+struct Document {
+    nodes: Vec<Json>,
+    meshes: Vec<Json>,
+    materials: Vec<Json>,
+    material_indices: HashMap<String, usize>,
+    accessors: Vec<Json>,
+    buffer_views: Vec<Json>,
+    buffer: Vec<u8>,
+}
+
+impl Document {
+    fn push_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+
+        self.buffer_views.push(Json::object(vec![
+            ("buffer", 0_usize.into()),
+            ("byteOffset", offset.into()),
+            ("byteLength", bytes.len().into()),
+            ("target", target.into()),
+        ]));
+
+        self.buffer_views.len() - 1
+    }
+
+    fn push_floats(&mut self, values: &[f32], components: usize) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect();
+        let view = self.push_view(&bytes, TARGET_ARRAY_BUFFER);
+
+        let accessor_type = match components {
+            2 => "VEC2",
+            3 => "VEC3",
+            _ => "VEC4",
+        };
+
+        let mut accessor = vec![
+            ("bufferView", view.into()),
+            ("componentType", COMPONENT_FLOAT.into()),
+            ("count", (values.len() / components).into()),
+            ("type", accessor_type.into()),
+        ];
+
+        // POSITION accessors must carry bounds
+        if components == 3 {
+            let (min, max) = values.chunks(components).fold(
+                (vec![f32::MAX; components], vec![f32::MIN; components]),
+                |(min, max), v| {
+                    (
+                        min.iter().zip(v).map(|(a, b)| a.min(*b)).collect(),
+                        max.iter().zip(v).map(|(a, b)| a.max(*b)).collect(),
+                    )
+                },
+            );
+            accessor.push(("min", Json::array(min)));
+            accessor.push(("max", Json::array(max)));
+        }
+
+        self.accessors.push(Json::object(accessor));
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices
+            .iter()
+            .flat_map(|i| i.to_le_bytes().to_vec())
+            .collect();
+        let view = self.push_view(&bytes, TARGET_ELEMENT_ARRAY_BUFFER);
+
+        self.accessors.push(Json::object(vec![
+            ("bufferView", view.into()),
+            ("componentType", COMPONENT_UNSIGNED_INT.into()),
+            ("count", indices.len().into()),
+            ("type", "SCALAR".into()),
+        ]));
+        self.accessors.len() - 1
+    }
+
+    fn material(&mut self, texture: &str) -> usize {
+        if let Some(material) = self.material_indices.get(texture) {
+            return *material;
+        }
+
+        self.materials
+            .push(Json::object(vec![("name", texture.into())]));
+        let material = self.materials.len() - 1;
+        self.material_indices.insert(texture.to_string(), material);
+        material
+    }
+
+    fn push_node(&mut self, node: Json) -> usize {
+        self.nodes.push(node);
+        self.nodes.len() - 1
+    }
+}
+
+fn flatten_vector3(vectors: &[Vector3]) -> Vec<f32> {
+    vectors
+        .iter()
+        .flat_map(|v| {
+            let v = y_up(*v);
+            vec![v.x(), v.y(), v.z()]
+        })
+        .collect()
+}
+
+fn flatten_vector2(vectors: &[Vector2]) -> Vec<f32> {
+    vectors.iter().flat_map(|v| vec![v.x(), v.y()]).collect()
+}
+
+fn vector3_json(v: Vector3) -> Json {
+    Json::array(vec![v.x(), v.y(), v.z()])
+}
+
+fn build_primitive(document: &mut Document, surface: &MeshSurface) -> Json {
+    let mut attributes = vec![
+        (
+            "POSITION",
+            document
+                .push_floats(&flatten_vector3(&surface.vertices), 3)
+                .into(),
+        ),
+        (
+            "NORMAL",
+            document
+                .push_floats(&flatten_vector3(&surface.normals), 3)
+                .into(),
+        ),
+    ];
+
+    let tangents: Vec<f32> = surface
+        .tangents
+        .iter()
+        .flat_map(|(tangent, sign)| {
+            let tangent = y_up(*tangent);
+            vec![tangent.x(), tangent.y(), tangent.z(), *sign]
+        })
+        .collect();
+    attributes.push(("TANGENT", document.push_floats(&tangents, 4).into()));
+
+    if let Some(uvs) = &surface.uvs {
+        attributes.push((
+            "TEXCOORD_0",
+            document.push_floats(&flatten_vector2(uvs), 2).into(),
+        ));
+    }
+
+    if let Some(uv2) = &surface.uv2 {
+        attributes.push((
+            "TEXCOORD_1",
+            document.push_floats(&flatten_vector2(uv2), 2).into(),
+        ));
+    }
+
+    if let Some(colors) = &surface.colors {
+        let colors: Vec<f32> = colors
+            .iter()
+            .flat_map(|color| vec![color.r, color.g, color.b])
+            .collect();
+        attributes.push(("COLOR_0", document.push_floats(&colors, 3).into()));
+    }
+
+    // Faces are wound clockwise, glTF expects counter-clockwise
+    let indices: Vec<u32> = surface
+        .indices
+        .chunks(3)
+        .flat_map(|triangle| match triangle {
+            [a, b, c] => vec![*a as u32, *c as u32, *b as u32],
+            _ => Vec::new(),
+        })
+        .collect();
+
+    let mut primitive = vec![
+        ("attributes", Json::object(attributes)),
+        ("indices", document.push_indices(&indices).into()),
+    ];
+
+    if let Some(texture) = &surface.texture {
+        primitive.push(("material", document.material(texture).into()));
+    }
+
+    Json::object(primitive)
+}
+
+fn entity_type_json(entity_type: &EntityType) -> Json {
+    match entity_type {
+        EntityType::Placeholder => Json::object(vec![("type", "placeholder".into())]),
+        EntityType::Class(class) => Json::object(vec![
+            ("type", "class".into()),
+            ("name", class.as_str().into()),
+        ]),
+        EntityType::Prefab(prefab) => Json::object(vec![
+            ("type", "prefab".into()),
+            ("name", prefab.as_str().into()),
+        ]),
+    }
+}
+
+fn property_application_type_json(property_application_type: PropertyApplicationType) -> Json {
+    match property_application_type {
+        PropertyApplicationType::Properties => "properties".into(),
+        PropertyApplicationType::Dictionary => "dictionary".into(),
+        PropertyApplicationType::Metadata => "metadata".into(),
+    }
+}
+
+fn property_json(property: &Property) -> Json {
+    match property {
+        Property::Integer(value) => (*value).into(),
+        Property::Float(value) => (*value).into(),
+        Property::Vector3(value) => vector3_json(*value),
+        Property::String(value) => value.as_str().into(),
+        Property::Color(value) => Json::array(vec![value.r, value.g, value.b]),
+        Property::Choices(ChoiceData::Integer(value)) => (*value).into(),
+        Property::Choices(ChoiceData::Float(value)) => (*value).into(),
+        Property::Choices(ChoiceData::String(value)) => value.as_str().into(),
+        Property::Flags(value) => (*value).into(),
+        Property::TargetSource(value) => value.as_str().into(),
+        Property::TargetDestination(value) => value.as_str().into(),
+    }
+}
+
+fn properties_json(Properties(properties): &Properties) -> Json {
+    let mut keys: Vec<&String> = properties.keys().collect();
+    keys.sort();
+
+    Json::Object(
+        keys.into_iter()
+            .map(|key| (key.clone(), property_json(&properties[key])))
+            .collect(),
+    )
+}
+
+fn actor_extras(actor: &Actor) -> Json {
+    Json::object(vec![
+        ("entity_type", entity_type_json(&actor.entity_type)),
+        ("component_class", actor.component_class.as_deref().into()),
+        (
+            "property_application_type",
+            property_application_type_json(actor.property_application_type),
+        ),
+        ("properties", properties_json(&actor.properties)),
+    ])
+}
+
+fn collision_extras(collision_geometry: &CollisionGeometry) -> Json {
+    let points_json = |points: &[Vector3]| {
+        Json::Array(
+            points
+                .iter()
+                .map(|point| vector3_json(y_up(*point)))
+                .collect(),
+        )
+    };
+
+    let (collision_type, shapes) = match collision_geometry {
+        CollisionGeometry::None => ("none", Vec::new()),
+        CollisionGeometry::Convex(shapes) => (
+            "convex",
+            shapes
+                .iter()
+                .map(|shape| {
+                    Json::object(vec![
+                        ("center", vector3_json(y_up(shape.center))),
+                        ("points", points_json(&shape.points)),
+                    ])
+                })
+                .collect(),
+        ),
+        CollisionGeometry::Concave(shapes) => (
+            "concave",
+            shapes
+                .iter()
+                .map(|shape| {
+                    Json::object(vec![
+                        ("center", vector3_json(y_up(shape.center))),
+                        ("vertices", points_json(&shape.vertices)),
+                        ("indices", Json::array(shape.indices.clone())),
+                    ])
+                })
+                .collect(),
+        ),
+    };
+
+    Json::object(vec![(
+        "collision",
+        Json::object(vec![
+            ("type", collision_type.into()),
+            ("shapes", Json::Array(shapes)),
+        ]),
+    )])
+}
+
+fn build_node(document: &mut Document, node: &SceneTreeNode, parent_origin: Vector3) -> usize {
+    // Geometry is built in world space, so cancel out the parent actor's placement
+    let geometry_translation = vector3_json(y_up(-parent_origin));
+
+    match &node.data {
+        SceneTreeType::Actor(actor, children) => {
+            let origin = parent_origin + node.origin;
+            let children: Vec<usize> = children
+                .iter()
+                .map(|child| build_node(document, child, origin))
+                .collect();
+
+            let mut members = vec![
+                ("name", actor.name.as_str().into()),
+                ("translation", vector3_json(y_up(node.origin))),
+                ("extras", actor_extras(actor)),
+            ];
+
+            if !children.is_empty() {
+                members.push(("children", Json::array(children)));
+            }
+
+            document.push_node(Json::object(members))
+        }
+        SceneTreeType::VisualGeometry(visual_geometry) => {
+            let mut members = vec![
+                ("name", "visual".into()),
+                ("translation", geometry_translation),
+            ];
+
+            if let VisualGeometry::Mesh(visual_mesh) = visual_geometry {
+                let primitives: Vec<Json> = visual_mesh
+                    .surfaces
+                    .iter()
+                    .filter(|surface| !surface.indices.is_empty())
+                    .map(|surface| build_primitive(document, surface))
+                    .collect();
+
+                if !primitives.is_empty() {
+                    document
+                        .meshes
+                        .push(Json::object(vec![("primitives", Json::Array(primitives))]));
+                    members.push(("mesh", (document.meshes.len() - 1).into()));
+                }
+            }
+
+            document.push_node(Json::object(members))
+        }
+        SceneTreeType::CollisionGeometry(collision_geometry) => {
+            document.push_node(Json::object(vec![
+                ("name", "collision".into()),
+                ("translation", geometry_translation),
+                ("extras", collision_extras(collision_geometry)),
+            ]))
+        }
+    }
+}
+
+fn build_document(scene_tree: &[SceneTreeNode]) -> (Json, Vec<u8>, Document) {
+    let mut document = Document::default();
+
+    let roots: Vec<usize> = scene_tree
+        .iter()
+        .map(|node| build_node(&mut document, node, Vector3::default()))
+        .collect();
+
+    let buffer = std::mem::take(&mut document.buffer);
+    let roots = Json::array(roots);
+
+    (roots, buffer, document)
+}
+
+fn gltf_json(roots: Json, document: Document, buffer: Json) -> Json {
+    let mut members = vec![
+        (
+            "asset",
+            Json::object(vec![
+                ("version", "2.0".into()),
+                ("generator", "quarchitect".into()),
+            ]),
+        ),
+        ("scene", 0_usize.into()),
+        (
+            "scenes",
+            Json::Array(vec![Json::object(vec![("nodes", roots)])]),
+        ),
+        ("nodes", Json::Array(document.nodes)),
+    ];
+
+    if !document.meshes.is_empty() {
+        members.push(("meshes", Json::Array(document.meshes)));
+    }
+
+    if !document.materials.is_empty() {
+        members.push(("materials", Json::Array(document.materials)));
+    }
+
+    if !document.accessors.is_empty() {
+        members.push(("accessors", Json::Array(document.accessors)));
+        members.push(("bufferViews", Json::Array(document.buffer_views)));
+        members.push(("buffers", Json::Array(vec![buffer])));
+    }
+
+    Json::object(members)
+}
+
+// Self-contained .gltf text with the binary buffer embedded as a data URI
+pub fn to_gltf(scene_tree: &[SceneTreeNode]) -> String {
+    let (roots, buffer, document) = build_document(scene_tree);
+
+    let buffer_json = Json::object(vec![
+        ("byteLength", buffer.len().into()),
+        (
+            "uri",
+            format!(
+                "data:application/octet-stream;base64,{}",
+                super::base64::encode(&buffer)
+            )
+            .into(),
+        ),
+    ]);
+
+    gltf_json(roots, document, buffer_json).to_string()
+}
+
+pub fn to_glb(scene_tree: &[SceneTreeNode]) -> Vec<u8> {
+    let (roots, mut buffer, document) = build_document(scene_tree);
+
+    let buffer_json = Json::object(vec![("byteLength", buffer.len().into())]);
+    let mut json = gltf_json(roots, document, buffer_json)
+        .to_string()
+        .into_bytes();
+
+    // Chunks are 4-byte aligned, JSON with spaces and BIN with zeroes
+    json.resize(json.len().div_ceil(4) * 4, b' ');
+    buffer.resize(buffer.len().div_ceil(4) * 4, 0);
+
+    let mut length = 12 + 8 + json.len();
+    if !buffer.is_empty() {
+        length += 8 + buffer.len();
+    }
+
+    let mut glb: Vec<u8> = Vec::with_capacity(length);
+    glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+    glb.extend_from_slice(&json);
+
+    if !buffer.is_empty() {
+        glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        glb.extend_from_slice(&buffer);
+    }
+
+    glb
+}
+
+pub fn write_gltf(scene_tree: &[SceneTreeNode], file: &str) -> io::Result<()> {
+    std::fs::write(file, to_gltf(scene_tree))
+}
+
+pub fn write_glb(scene_tree: &[SceneTreeNode], file: &str) -> io::Result<()> {
+    std::fs::write(file, to_glb(scene_tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene_tree::VisualMesh;
+
+    fn triangle_scene() -> Vec<SceneTreeNode> {
+        let surface = MeshSurface::new(
+            Some("wall".into()),
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(1.0, 0.0, 2.0),
+            ],
+            vec![Vector3::new(0.0, 0.0, 1.0); 3],
+            vec![(Vector3::new(1.0, 0.0, 0.0), 1.0); 3],
+            None,
+            None,
+            None,
+            vec![0, 1, 2],
+        );
+
+        vec![SceneTreeNode::entity(
+            "worldspawn".into(),
+            Vector3::default(),
+            EntityType::Placeholder,
+            None,
+            PropertyApplicationType::Properties,
+            Properties::default(),
+            vec![SceneTreeNode::visual_geometry(
+                Vector3::default(),
+                VisualGeometry::Mesh(VisualMesh::new(vec![surface])),
+            )],
+        )]
+    }
+
+    #[test]
+    fn glb_layout() {
+        let glb = to_glb(&triangle_scene());
+
+        let word = |i: usize| u32::from_le_bytes([glb[i], glb[i + 1], glb[i + 2], glb[i + 3]]);
+
+        assert!(word(0) == GLB_MAGIC, "Bad GLB magic");
+        assert!(word(8) as usize == glb.len(), "GLB length mismatch");
+        assert!(word(16) == CHUNK_JSON, "First chunk is not JSON");
+
+        let json_length = word(12) as usize;
+        assert!(json_length & 3 == 0, "JSON chunk is not aligned");
+        assert!(
+            word(20 + json_length + 4) == CHUNK_BIN,
+            "Second chunk is not BIN"
+        );
+    }
+
+    #[test]
+    fn gltf_round_trip() {
+        let glb = to_glb(&triangle_scene());
+        let word = |i: usize| u32::from_le_bytes([glb[i], glb[i + 1], glb[i + 2], glb[i + 3]]);
+
+        let json_length = word(12) as usize;
+        let json: serde_json::Value = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        let bin = &glb[20 + json_length + 8..];
+
+        let byte_length = json["buffers"][0]["byteLength"].as_u64().unwrap() as usize;
+        assert!(
+            byte_length <= bin.len() && bin.len() - byte_length < 4,
+            "Buffer is {} bytes, BIN chunk {}",
+            byte_length,
+            bin.len()
+        );
+
+        let views = json["bufferViews"].as_array().unwrap();
+        for view in views {
+            let offset = view["byteOffset"].as_u64().unwrap() as usize;
+            let length = view["byteLength"].as_u64().unwrap() as usize;
+            assert!(
+                offset % 4 == 0 && offset + length <= byte_length,
+                "Buffer view {}",
+                view
+            );
+        }
+
+        // Every accessor exactly fills its view
+        let accessor_bytes = |accessor: &serde_json::Value| {
+            let view = &views[accessor["bufferView"].as_u64().unwrap() as usize];
+            let offset = view["byteOffset"].as_u64().unwrap() as usize;
+            let length = view["byteLength"].as_u64().unwrap() as usize;
+            &bin[offset..offset + length]
+        };
+
+        let accessors = json["accessors"].as_array().unwrap();
+        for accessor in accessors {
+            let components = match accessor["type"].as_str().unwrap() {
+                "SCALAR" => 1,
+                "VEC2" => 2,
+                "VEC3" => 3,
+                _ => 4,
+            };
+            let count = accessor["count"].as_u64().unwrap() as usize;
+            assert!(
+                accessor_bytes(accessor).len() == count * components * 4,
+                "Accessor {}",
+                accessor
+            );
+        }
+
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let position = &accessors[primitive["attributes"]["POSITION"].as_u64().unwrap() as usize];
+        let positions: Vec<f32> = accessor_bytes(position)
+            .chunks(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+
+        // Quake's z is up, glTF's y
+        let expected = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 2.0, 1.0];
+        assert!(positions == expected, "Positions {:?}", positions);
+        let bounds = |key: &str| -> Vec<f64> {
+            position[key]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|value| value.as_f64().unwrap())
+                .collect()
+        };
+        assert!(
+            bounds("min") == vec![0.0, 0.0, 0.0] && bounds("max") == vec![1.0, 2.0, 1.0],
+            "Position bounds {:?} {:?}",
+            bounds("min"),
+            bounds("max")
+        );
+
+        let indices = &accessors[primitive["indices"].as_u64().unwrap() as usize];
+        let indices: Vec<u32> = accessor_bytes(indices)
+            .chunks(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        assert!(indices == vec![0, 2, 1], "Indices {:?}", indices);
+
+        // The .gltf text embeds the same buffer
+        let gltf: serde_json::Value = serde_json::from_str(&to_gltf(&triangle_scene())).unwrap();
+        let uri = gltf["buffers"][0]["uri"].as_str().unwrap();
+        assert!(
+            gltf["buffers"][0]["byteLength"] == json["buffers"][0]["byteLength"]
+                && uri
+                    == format!(
+                        "data:application/octet-stream;base64,{}",
+                        super::super::base64::encode(&bin[..byte_length])
+                    ),
+            "Embedded buffer {:?}",
+            uri
+        );
+    }
+}
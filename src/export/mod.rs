@@ -0,0 +1,13 @@
+pub mod gltf;
+pub mod obj;
+pub mod tscn;
+
+mod base64;
+mod json;
+
+use crate::Vector3;
+
+// Quake is Z-up, most interchange formats are Y-up
+fn y_up(v: Vector3) -> Vector3 {
+    Vector3::new(v.y(), v.z(), v.x())
+}
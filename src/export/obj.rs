@@ -0,0 +1,408 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+
+use super::y_up;
+use crate::scene_tree::{
+    CollisionGeometry, MeshSurface, SceneTreeNode, SceneTreeType, VisualGeometry,
+};
+use crate::Vector3;
+
+const HULL_EPSILON: f32 = 0.01;
+const COLLISION_MATERIAL: &str = "collision";
+const UNTEXTURED_MATERIAL: &str = "untextured";
+
+#[derive(Debug, Clone)]
+pub struct ObjSettings {
+    pub include_collision: bool,
+    // Extension of the images next to the OBJ that materials reference by texture name, or None
+    // to leave materials untextured
+    pub texture_extension: Option<String>,
+}
+
+impl ObjSettings {
+    pub fn new(include_collision: bool, texture_extension: Option<&str>) -> ObjSettings {
+        let texture_extension = texture_extension.map(String::from);
+        ObjSettings {
+            include_collision,
+            texture_extension,
+        }
+    }
+}
+
+impl Default for ObjSettings {
+    fn default() -> Self {
+        let include_collision = false;
+        let texture_extension = Some("png".into());
+
+        ObjSettings {
+            include_collision,
+            texture_extension,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    obj: String,
+    materials: Vec<String>,
+    object_names: HashSet<String>,
+    vertex_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+}
+
+impl Writer {
+    fn object(&mut self, name: &str) {
+        let mut unique_name = name.to_string();
+        let mut suffix = 1;
+        while !self.object_names.insert(unique_name.clone()) {
+            unique_name = format!("{}_{}", name, suffix);
+            suffix += 1;
+        }
+
+        writeln!(self.obj, "o {}", unique_name).unwrap();
+    }
+
+    fn material(&mut self, material: &str) {
+        if !self.materials.iter().any(|comp| comp == material) {
+            self.materials.push(material.to_string());
+        }
+
+        writeln!(self.obj, "usemtl {}", material).unwrap();
+    }
+
+    fn vertex(&mut self, v: Vector3) {
+        let v = y_up(v);
+        writeln!(self.obj, "v {} {} {}", v.x(), v.y(), v.z()).unwrap();
+        self.vertex_count += 1;
+    }
+
+    fn surface(&mut self, surface: &MeshSurface) {
+        let material = match &surface.texture {
+            Some(texture) => texture.as_str(),
+            None => UNTEXTURED_MATERIAL,
+        };
+
+        writeln!(self.obj, "g {}", material).unwrap();
+        self.material(material);
+
+        let vertex_base = self.vertex_count + 1;
+        let uv_base = self.uv_count + 1;
+        let normal_base = self.normal_count + 1;
+
+        for vertex in &surface.vertices {
+            self.vertex(*vertex);
+        }
+
+        if let Some(uvs) = &surface.uvs {
+            for uv in uvs {
+                writeln!(self.obj, "vt {} {}", uv.x(), 1.0 - uv.y()).unwrap();
+            }
+            self.uv_count += uvs.len();
+        }
+
+        for normal in &surface.normals {
+            let n = y_up(*normal);
+            writeln!(self.obj, "vn {} {} {}", n.x(), n.y(), n.z()).unwrap();
+        }
+        self.normal_count += surface.normals.len();
+
+        // Faces are wound clockwise, OBJ expects counter-clockwise
+        for triangle in surface.indices.chunks(3) {
+            if let [a, b, c] = triangle {
+                self.obj.push('f');
+                for index in &[*a, *c, *b] {
+                    match surface.uvs {
+                        Some(_) => write!(
+                            self.obj,
+                            " {}/{}/{}",
+                            vertex_base + index,
+                            uv_base + index,
+                            normal_base + index
+                        ),
+                        None => write!(
+                            self.obj,
+                            " {}//{}",
+                            vertex_base + index,
+                            normal_base + index
+                        ),
+                    }
+                    .unwrap();
+                }
+                self.obj.push('\n');
+            }
+        }
+    }
+
+    fn polygons(&mut self, vertices: &[Vector3], polygons: &[Vec<usize>]) {
+        let vertex_base = self.vertex_count + 1;
+
+        for vertex in vertices {
+            self.vertex(*vertex);
+        }
+
+        for polygon in polygons {
+            self.obj.push('f');
+            for index in polygon {
+                write!(self.obj, " {}", vertex_base + index).unwrap();
+            }
+            self.obj.push('\n');
+        }
+    }
+
+    fn collision(&mut self, name: &str, collision_geometry: &CollisionGeometry) {
+        match collision_geometry {
+            CollisionGeometry::None => (),
+            CollisionGeometry::Convex(shapes) => {
+                for shape in shapes {
+                    self.object(&format!("{}_collision", name));
+                    self.material(COLLISION_MATERIAL);
+                    self.polygons(&shape.points, &hull_faces(&shape.points));
+                }
+            }
+            CollisionGeometry::Concave(shapes) => {
+                for shape in shapes {
+                    self.object(&format!("{}_collision", name));
+                    self.material(COLLISION_MATERIAL);
+
+                    let triangles: Vec<Vec<usize>> = shape
+                        .indices
+                        .chunks(3)
+                        .filter(|triangle| triangle.len() == 3)
+                        .map(|triangle| vec![triangle[0], triangle[2], triangle[1]])
+                        .collect();
+
+                    self.polygons(&shape.vertices, &triangles);
+                }
+            }
+        }
+    }
+
+    fn node(&mut self, node: &SceneTreeNode, name: &str, include_collision: bool) {
+        match &node.data {
+            SceneTreeType::Actor(actor, children) => {
+                // Unnamed actors keep the name they would otherwise hide
+                let name = match actor.name.as_str() {
+                    "" => name,
+                    actor_name => actor_name,
+                };
+
+                for child in children {
+                    self.node(child, name, include_collision);
+                }
+            }
+            SceneTreeType::VisualGeometry(VisualGeometry::Mesh(visual_mesh)) => {
+                self.object(name);
+                for surface in &visual_mesh.surfaces {
+                    self.surface(surface);
+                }
+            }
+            SceneTreeType::VisualGeometry(VisualGeometry::None) => (),
+            SceneTreeType::CollisionGeometry(collision_geometry) => {
+                if include_collision {
+                    self.collision(name, collision_geometry);
+                }
+            }
+        }
+    }
+}
+
+// Faces of the convex hull around a point cloud, as counter-clockwise polygons
+fn hull_faces(points: &[Vector3]) -> Vec<Vec<usize>> {
+    let mut planes: Vec<(Vector3, f32)> = Vec::new();
+
+    for i in 0..points.len() {
+        for j in i + 1..points.len() {
+            for k in j + 1..points.len() {
+                let normal = (points[j] - points[i]).cross(points[k] - points[i]);
+                if normal.length() < HULL_EPSILON {
+                    continue;
+                }
+                let normal = normal.normalize();
+
+                for normal in &[normal, -normal] {
+                    let dist = normal.dot(points[i]);
+                    let is_hull_plane = points
+                        .iter()
+                        .all(|point| normal.dot(*point) - dist <= HULL_EPSILON);
+                    let is_known_plane = planes.iter().any(|(comp_normal, comp_dist)| {
+                        comp_normal.dot(*normal) > 1.0 - HULL_EPSILON
+                            && (comp_dist - dist).abs() < HULL_EPSILON
+                    });
+
+                    if is_hull_plane && !is_known_plane {
+                        planes.push((*normal, dist));
+                    }
+                }
+            }
+        }
+    }
+
+    planes
+        .into_iter()
+        .map(|(normal, dist)| {
+            let mut face: Vec<usize> = (0..points.len())
+                .filter(|i| (normal.dot(points[*i]) - dist).abs() <= HULL_EPSILON)
+                .collect();
+
+            let center = face
+                .iter()
+                .fold(Vector3::default(), |acc, i| acc + points[*i])
+                / face.len() as f32;
+
+            let u_axis = (points[face[0]] - center).normalize();
+            let v_axis = normal.cross(u_axis);
+
+            let angle = |i: &usize| {
+                let local = points[*i] - center;
+                local.dot(v_axis).atan2(local.dot(u_axis))
+            };
+
+            face.sort_by(|a, b| {
+                angle(a)
+                    .partial_cmp(&angle(b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            face
+        })
+        .collect()
+}
+
+fn build_mtl(materials: &[String], texture_extension: &Option<String>) -> String {
+    materials.iter().fold(String::new(), |acc, material| {
+        if material == COLLISION_MATERIAL {
+            acc + &format!("newmtl {}\nKd 1 0 1\nd 0.5\n\n", material)
+        } else if material == UNTEXTURED_MATERIAL {
+            acc + &format!("newmtl {}\nKd 0.5 0.5 0.5\n\n", material)
+        } else if let Some(extension) = texture_extension {
+            acc + &format!(
+                "newmtl {}\nKd 1 1 1\nmap_Kd {}.{}\n\n",
+                material, material, extension
+            )
+        } else {
+            acc + &format!("newmtl {}\nKd 1 1 1\n\n", material)
+        }
+    })
+}
+
+// OBJ and MTL text for the scene tree, with the OBJ referencing the given MTL file name
+pub fn to_obj(
+    scene_tree: &[SceneTreeNode],
+    mtl_file: &str,
+    settings: &ObjSettings,
+) -> (String, String) {
+    let mut writer = Writer::default();
+
+    // Geometry outside any named actor is named after its root node
+    for (index, node) in scene_tree.iter().enumerate() {
+        writer.node(node, &format!("node_{}", index), settings.include_collision);
+    }
+
+    let obj = format!("mtllib {}\n{}", mtl_file, writer.obj);
+    let mtl = build_mtl(&writer.materials, &settings.texture_extension);
+
+    (obj, mtl)
+}
+
+// Writes the OBJ to the given file and its materials to a sibling .mtl file
+pub fn write_obj(
+    scene_tree: &[SceneTreeNode],
+    file: &str,
+    settings: &ObjSettings,
+) -> io::Result<()> {
+    let mtl_path = Path::new(file).with_extension("mtl");
+    let mtl_file = match mtl_path.file_name() {
+        Some(mtl_file) => mtl_file.to_string_lossy().to_string(),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid OBJ path",
+            ))
+        }
+    };
+
+    let (obj, mtl) = to_obj(scene_tree, &mtl_file, settings);
+
+    std::fs::write(file, obj)?;
+    std::fs::write(mtl_path, mtl)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::{EntityType, Properties, PropertyApplicationType};
+    use crate::scene_tree::VisualMesh;
+
+    fn wall() -> SceneTreeNode {
+        let surface = MeshSurface::new(
+            Some("wall".into()),
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+            ],
+            vec![Vector3::new(0.0, 0.0, 1.0); 3],
+            vec![(Vector3::new(1.0, 0.0, 0.0), 1.0); 3],
+            None,
+            None,
+            None,
+            vec![0, 1, 2],
+        );
+
+        SceneTreeNode::visual_geometry(
+            Vector3::default(),
+            VisualGeometry::Mesh(VisualMesh::new(vec![surface])),
+        )
+    }
+
+    #[test]
+    fn names_and_materials() {
+        let scene_tree = vec![
+            SceneTreeNode::entity(
+                "".into(),
+                Vector3::default(),
+                EntityType::Placeholder,
+                None,
+                PropertyApplicationType::Properties,
+                Properties::default(),
+                vec![wall()],
+            ),
+            wall(),
+        ];
+
+        let (obj, mtl) = to_obj(
+            &scene_tree,
+            "map.mtl",
+            &ObjSettings::new(false, Some("tga")),
+        );
+        let objects: Vec<&str> = obj.lines().filter(|line| line.starts_with("o ")).collect();
+        assert!(
+            objects == vec!["o node_0", "o node_1"],
+            "Object names {:?}",
+            objects
+        );
+        assert!(mtl.contains("map_Kd wall.tga\n"), "MTL {:?}", mtl);
+
+        let (_obj, mtl) = to_obj(&scene_tree, "map.mtl", &ObjSettings::new(false, None));
+        assert!(!mtl.contains("map_Kd"), "MTL {:?}", mtl);
+    }
+
+    #[test]
+    fn cube_hull() {
+        let points: Vec<Vector3> = (0..8)
+            .map(|i| Vector3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32))
+            .collect();
+
+        let faces = hull_faces(&points);
+
+        assert!(faces.len() == 6, "Cube hull has {} faces", faces.len());
+        for face in faces {
+            assert!(face.len() == 4, "Cube face has {} points", face.len());
+        }
+    }
+}
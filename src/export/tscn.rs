@@ -0,0 +1,784 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::io;
+
+use super::y_up;
+use crate::game_data::forge::ChoiceData;
+use crate::game_data::{
+    CollisionLayers, EntityType, Properties, Property, PropertyApplicationType,
+};
+use crate::scene_tree::{
+    Actor, CollisionGeometry, MeshSurface, SceneTreeNode, SceneTreeType, TargetLink,
+    VisualGeometry, VisualMesh,
+};
+use crate::{Vector2, Vector3};
+
+const PRIMITIVE_TRIANGLES: i32 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TscnSettings {
+    pub root_name: String,
+    pub inverse_scale: f32,
+}
+
+impl TscnSettings {
+    pub fn new(root_name: &str, inverse_scale: f32) -> TscnSettings {
+        let root_name = root_name.into();
+        TscnSettings {
+            root_name,
+            inverse_scale,
+        }
+    }
+}
+
+impl Default for TscnSettings {
+    fn default() -> Self {
+        // Matches Qodot's default of 16 Quake units per Godot unit
+        let root_name = "Map".into();
+        let inverse_scale = 16.0;
+
+        TscnSettings {
+            root_name,
+            inverse_scale,
+        }
+    }
+}
+
+struct Writer<'a> {
+    settings: &'a TscnSettings,
+    ext_resources: Vec<String>,
+    ext_resource_ids: HashMap<(String, String), usize>,
+    sub_resources: Vec<String>,
+    nodes: Vec<String>,
+    sibling_names: HashMap<String, HashSet<String>>,
+    // Node path in the .tscn of each actor, keyed by its scene tree path
+    actor_paths: HashMap<Vec<usize>, String>,
+    metadata: Vec<ActorMetadata>,
+}
+
+// Metadata can only be written once every target has a node path
+struct ActorMetadata {
+    node: usize,
+    path: String,
+    // Properties, when applied as metadata
+    properties: Option<Vec<String>>,
+    targets: Vec<TargetLink>,
+}
+
+fn godot_float(value: f32) -> String {
+    format!("{:?}", value)
+}
+
+fn godot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn godot_vector3(v: Vector3) -> String {
+    format!(
+        "Vector3( {}, {}, {} )",
+        godot_float(v.x()),
+        godot_float(v.y()),
+        godot_float(v.z())
+    )
+}
+
+fn godot_array<T>(name: &str, values: &[T], components: impl Fn(&T) -> Vec<f32>) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .flat_map(components)
+        .map(godot_float)
+        .collect();
+    format!("{}( {} )", name, values.join(", "))
+}
+
+fn godot_property(property: &Property) -> String {
+    match property {
+        Property::Integer(value) => value.to_string(),
+        Property::Float(value) => godot_float(*value),
+        Property::Vector3(value) => godot_vector3(*value),
+        Property::String(value) => godot_string(value),
+        Property::Color(value) => format!(
+            "Color( {}, {}, {}, 1 )",
+            godot_float(value.r),
+            godot_float(value.g),
+            godot_float(value.b)
+        ),
+        Property::Choices(ChoiceData::Integer(value)) => value.to_string(),
+        Property::Choices(ChoiceData::Float(value)) => godot_float(*value),
+        Property::Choices(ChoiceData::String(value)) => godot_string(value),
+        Property::Flags(value) => value.to_string(),
+        Property::TargetSource(value) => godot_string(value),
+        Property::TargetDestination(value) => godot_string(value),
+    }
+}
+
+fn godot_dictionary(entries: &[String]) -> String {
+    if entries.is_empty() {
+        return "{\n}".into();
+    }
+
+    format!("{{\n{}\n}}", entries.join(",\n"))
+}
+
+fn property_entries(properties: &[(&String, &Property)]) -> Vec<String> {
+    properties
+        .iter()
+        .map(|(key, property)| format!("{}: {}", godot_string(key), godot_property(property)))
+        .collect()
+}
+
+fn child_path(parent: &str, name: &str) -> String {
+    if parent == "." {
+        name.into()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+// Path from one node to another, both relative to the scene root
+fn relative_path(from: &str, to: &str) -> String {
+    let from: Vec<&str> = from.split('/').collect();
+    let to: Vec<&str> = to.split('/').collect();
+
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let path: Vec<&str> = std::iter::repeat_n("..", from.len() - common)
+        .chain(to[common..].iter().copied())
+        .collect();
+
+    if path.is_empty() {
+        ".".into()
+    } else {
+        path.join("/")
+    }
+}
+
+// Node names may not contain path or property separators
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '.' | ':' | '@' | '/' | '"' | '%' => '_',
+            c => c,
+        })
+        .collect();
+
+    if name.is_empty() {
+        "Node".into()
+    } else {
+        name
+    }
+}
+
+impl<'a> Writer<'a> {
+    fn new(settings: &'a TscnSettings) -> Writer<'a> {
+        Writer {
+            settings,
+            ext_resources: Vec::new(),
+            ext_resource_ids: HashMap::new(),
+            sub_resources: Vec::new(),
+            nodes: Vec::new(),
+            sibling_names: HashMap::new(),
+            actor_paths: HashMap::new(),
+            metadata: Vec::new(),
+        }
+    }
+
+    fn to_godot(&self, v: Vector3) -> Vector3 {
+        y_up(v) / self.settings.inverse_scale
+    }
+
+    fn ext_resource(&mut self, path: &str, resource_type: &str) -> usize {
+        let key = (path.to_string(), resource_type.to_string());
+        if let Some(id) = self.ext_resource_ids.get(&key) {
+            return *id;
+        }
+
+        let id = self.ext_resources.len() + 1;
+        self.ext_resources.push(format!(
+            "[ext_resource path={} type=\"{}\" id={}]",
+            godot_string(path),
+            resource_type,
+            id
+        ));
+        self.ext_resource_ids.insert(key, id);
+        id
+    }
+
+    fn sub_resource(&mut self, resource_type: &str, body: String) -> usize {
+        let id = self.sub_resources.len() + 1;
+        self.sub_resources.push(format!(
+            "[sub_resource type=\"{}\" id={}]\n{}",
+            resource_type, id, body
+        ));
+        id
+    }
+
+    fn unique_name(&mut self, parent: &str, name: &str) -> String {
+        let siblings = self.sibling_names.entry(parent.to_string()).or_default();
+
+        let name = sanitize_name(name);
+        let mut unique_name = name.clone();
+        let mut suffix = 2;
+        while !siblings.insert(unique_name.clone()) {
+            unique_name = format!("{}{}", name, suffix);
+            suffix += 1;
+        }
+
+        unique_name
+    }
+
+    fn transform(&self, origin: Vector3) -> String {
+        let origin = self.to_godot(origin);
+        format!(
+            "transform = Transform( 1, 0, 0, 0, 1, 0, 0, 0, 1, {}, {}, {} )",
+            godot_float(origin.x()),
+            godot_float(origin.y()),
+            godot_float(origin.z())
+        )
+    }
+
+    fn surface(&self, surface: &MeshSurface) -> String {
+        let vector3_array = |values: &[Vector3]| {
+            godot_array("PoolVector3Array", values, |v| {
+                let v = self.to_godot(*v);
+                vec![v.x(), v.y(), v.z()]
+            })
+        };
+
+        let normals = godot_array("PoolVector3Array", &surface.normals, |v| {
+            let v = y_up(*v);
+            vec![v.x(), v.y(), v.z()]
+        });
+
+        let tangents = godot_array("PoolRealArray", &surface.tangents, |(v, sign)| {
+            let v = y_up(*v);
+            vec![v.x(), v.y(), v.z(), *sign]
+        });
+
+        let colors = match &surface.colors {
+            Some(colors) => godot_array("PoolColorArray", colors, |c| vec![c.r, c.g, c.b, 1.0]),
+            None => "null".into(),
+        };
+
+        let vector2_array = |values: &Option<Vec<Vector2>>| match values {
+            Some(values) => godot_array("PoolVector2Array", values, |v| vec![v.x(), v.y()]),
+            None => "null".into(),
+        };
+
+        let indices: Vec<String> = surface.indices.iter().map(|i| i.to_string()).collect();
+
+        let mut entries = vec![
+            format!("\"primitive\": {}", PRIMITIVE_TRIANGLES),
+            format!(
+                "\"arrays\": [ {}, {}, {}, {}, {}, {}, null, null, PoolIntArray( {} ) ]",
+                vector3_array(&surface.vertices),
+                normals,
+                tangents,
+                colors,
+                vector2_array(&surface.uvs),
+                vector2_array(&surface.uv2),
+                indices.join(", ")
+            ),
+            "\"morph_arrays\": [  ]".into(),
+        ];
+
+        if let Some(texture) = &surface.texture {
+            entries.push(format!("\"name\": {}", godot_string(texture)));
+        }
+
+        format!("{{\n{}\n}}", entries.join(",\n"))
+    }
+
+    fn mesh(&mut self, visual_mesh: &VisualMesh) -> Option<usize> {
+        let surfaces: Vec<String> = visual_mesh
+            .surfaces
+            .iter()
+            .filter(|surface| !surface.indices.is_empty())
+            .enumerate()
+            .map(|(i, surface)| format!("surfaces/{} = {}", i, self.surface(surface)))
+            .collect();
+
+        if surfaces.is_empty() {
+            return None;
+        }
+
+        Some(self.sub_resource("ArrayMesh", surfaces.join("\n")))
+    }
+
+    fn node(&mut self, header: String, properties: Vec<String>) {
+        let mut node = header;
+        for property in properties {
+            node.push('\n');
+            node.push_str(&property);
+        }
+        self.nodes.push(node);
+    }
+
+    fn actor_properties(&self, actor: &Actor) -> Vec<String> {
+        let properties = sorted_properties(actor);
+
+        match actor.property_application_type {
+            // Undeclared keys such as origin or scale would clobber the node's own properties
+            PropertyApplicationType::Properties => properties
+                .iter()
+                .filter(|(key, _)| actor.declared_properties.contains(*key))
+                .map(|(key, property)| format!("{} = {}", key, godot_property(property)))
+                .collect(),
+            PropertyApplicationType::Dictionary => vec![format!(
+                "properties = {}",
+                godot_dictionary(&property_entries(&properties))
+            )],
+            // Written alongside the target links once the scene is complete
+            PropertyApplicationType::Metadata => Vec::new(),
+        }
+    }
+
+    // Target links go in metadata as node paths, whichever way properties are applied
+    fn actor_metadata(&self, metadata: &ActorMetadata) -> Option<String> {
+        if metadata.properties.is_none() && metadata.targets.is_empty() {
+            return None;
+        }
+
+        let mut entries = metadata.properties.clone().unwrap_or_default();
+
+        if !metadata.targets.is_empty() {
+            let links: Vec<String> = metadata
+                .targets
+                .iter()
+                .map(|target| {
+                    let nodes: Vec<String> = target
+                        .nodes
+                        .iter()
+                        .filter_map(|node_path| self.actor_paths.get(&node_path.0))
+                        .map(|path| {
+                            format!(
+                                "NodePath({})",
+                                godot_string(&relative_path(&metadata.path, path))
+                            )
+                        })
+                        .collect();
+                    format!("{}: [ {} ]", godot_string(&target.key), nodes.join(", "))
+                })
+                .collect();
+
+            entries.push(format!("\"targets\": {}", godot_dictionary(&links)));
+        }
+
+        Some(format!("__meta__ = {}", godot_dictionary(&entries)))
+    }
+
+    fn actor(
+        &mut self,
+        actor: &Actor,
+        children: &[SceneTreeNode],
+        origin: Vector3,
+        parent_origin: Vector3,
+        parent: &str,
+        tree_path: &[usize],
+    ) {
+        let name = self.unique_name(parent, &actor.name);
+
+        let header = match &actor.entity_type {
+            EntityType::Prefab(prefab) => {
+                let id = self.ext_resource(prefab, "PackedScene");
+                format!(
+                    "[node name={} parent=\"{}\" instance=ExtResource( {} )]",
+                    godot_string(&name),
+                    parent,
+                    id
+                )
+            }
+            EntityType::Class(class) if !class.is_empty() => format!(
+                "[node name={} type=\"{}\" parent=\"{}\"]",
+                godot_string(&name),
+                class,
+                parent
+            ),
+            _ => format!(
+                "[node name={} type=\"Spatial\" parent=\"{}\"]",
+                godot_string(&name),
+                parent
+            ),
+        };
+
+        let mut properties = vec![self.transform(origin)];
+
+        if let Some(component_class) = &actor.component_class {
+            let id = self.ext_resource(component_class, "Script");
+            properties.push(format!("script = ExtResource( {} )", id));
+        }
+
+        properties.append(&mut self.actor_properties(actor));
+
+        self.node(header, properties);
+
+        let path = child_path(parent, &name);
+        self.actor_paths.insert(tree_path.to_vec(), path.clone());
+
+        let properties = match actor.property_application_type {
+            PropertyApplicationType::Metadata => Some(property_entries(&sorted_properties(actor))),
+            _ => None,
+        };
+        self.metadata.push(ActorMetadata {
+            node: self.nodes.len() - 1,
+            path: path.clone(),
+            properties,
+            targets: actor.targets.clone(),
+        });
+
+        let world_origin = parent_origin + origin;
+        for (i, child) in children.iter().enumerate() {
+            let mut child_tree_path = tree_path.to_vec();
+            child_tree_path.push(i);
+            self.scene_tree_node(child, world_origin, &path, &child_tree_path);
+        }
+    }
+
+    fn collision(
+        &mut self,
+        collision_geometry: &CollisionGeometry,
+        parent_origin: Vector3,
+        parent: &str,
+    ) {
+        let shapes: Vec<(Option<CollisionLayers>, &str, String)> = match collision_geometry {
+            CollisionGeometry::None => Vec::new(),
+            CollisionGeometry::Convex(shapes) => shapes
+                .iter()
+                .map(|shape| {
+                    let points = godot_array("PoolVector3Array", &shape.points, |v| {
+                        let v = self.to_godot(*v);
+                        vec![v.x(), v.y(), v.z()]
+                    });
+                    (
+                        shape.layers,
+                        "ConvexPolygonShape",
+                        format!("points = {}", points),
+                    )
+                })
+                .collect(),
+            CollisionGeometry::Concave(shapes) => shapes
+                .iter()
+                .map(|shape| {
+                    let faces: Vec<Vector3> =
+                        shape.indices.iter().map(|i| shape.vertices[*i]).collect();
+                    let data = godot_array("PoolVector3Array", &faces, |v| {
+                        let v = self.to_godot(*v);
+                        vec![v.x(), v.y(), v.z()]
+                    });
+                    (
+                        shape.layers,
+                        "ConcavePolygonShape",
+                        format!("data = {}", data),
+                    )
+                })
+                .collect(),
+        };
+
+        // Godot keeps layers on the body rather than the shape, so each set of layers gets a
+        // StaticBody of its own
+        let mut bodies: Vec<(CollisionLayers, String)> = Vec::new();
+
+        for (layers, shape_type, body) in shapes {
+            let id = self.sub_resource(shape_type, body);
+
+            let (shape_parent, shape_origin) = match layers {
+                None => (parent.to_string(), -parent_origin),
+                Some(layers) => {
+                    let body_path = match bodies.iter().find(|(other, _)| *other == layers) {
+                        Some((_, body_path)) => body_path.clone(),
+                        None => {
+                            let name = self.unique_name(parent, "collision_body");
+                            self.node(
+                                format!(
+                                    "[node name={} type=\"StaticBody\" parent=\"{}\"]",
+                                    godot_string(&name),
+                                    parent
+                                ),
+                                vec![
+                                    self.transform(-parent_origin),
+                                    format!("collision_layer = {}", layers.layer),
+                                    format!("collision_mask = {}", layers.mask),
+                                ],
+                            );
+
+                            let body_path = child_path(parent, &name);
+                            bodies.push((layers, body_path.clone()));
+                            body_path
+                        }
+                    };
+                    (body_path, Vector3::default())
+                }
+            };
+
+            let name = self.unique_name(&shape_parent, "collision");
+            self.node(
+                format!(
+                    "[node name={} type=\"CollisionShape\" parent=\"{}\"]",
+                    godot_string(&name),
+                    shape_parent
+                ),
+                vec![
+                    self.transform(shape_origin),
+                    format!("shape = SubResource( {} )", id),
+                ],
+            );
+        }
+    }
+
+    fn scene_tree_node(
+        &mut self,
+        node: &SceneTreeNode,
+        parent_origin: Vector3,
+        parent: &str,
+        tree_path: &[usize],
+    ) {
+        match &node.data {
+            SceneTreeType::Actor(actor, children) => self.actor(
+                actor,
+                children,
+                node.origin,
+                parent_origin,
+                parent,
+                tree_path,
+            ),
+            // Geometry is built in world space, so cancel out the parent actor's placement
+            SceneTreeType::VisualGeometry(VisualGeometry::Mesh(visual_mesh)) => {
+                if let Some(id) = self.mesh(visual_mesh) {
+                    let name = self.unique_name(parent, "visual");
+                    self.node(
+                        format!(
+                            "[node name={} type=\"MeshInstance\" parent=\"{}\"]",
+                            godot_string(&name),
+                            parent
+                        ),
+                        vec![
+                            self.transform(-parent_origin),
+                            format!("mesh = SubResource( {} )", id),
+                        ],
+                    );
+                }
+            }
+            SceneTreeType::VisualGeometry(VisualGeometry::None) => (),
+            SceneTreeType::CollisionGeometry(collision_geometry) => {
+                self.collision(collision_geometry, parent_origin, parent)
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        for metadata in &self.metadata {
+            if let Some(line) = self.actor_metadata(metadata) {
+                self.nodes[metadata.node] += &format!("\n{}", line);
+            }
+        }
+
+        let load_steps = self.ext_resources.len() + self.sub_resources.len() + 1;
+
+        let mut tscn = String::new();
+        writeln!(tscn, "[gd_scene load_steps={} format=2]", load_steps).unwrap();
+
+        for section in self.ext_resources.iter().chain(self.sub_resources.iter()) {
+            writeln!(tscn).unwrap();
+            writeln!(tscn, "{}", section).unwrap();
+        }
+
+        writeln!(tscn).unwrap();
+        writeln!(
+            tscn,
+            "[node name={} type=\"Spatial\"]",
+            godot_string(&sanitize_name(&self.settings.root_name))
+        )
+        .unwrap();
+
+        for node in &self.nodes {
+            writeln!(tscn).unwrap();
+            writeln!(tscn, "{}", node).unwrap();
+        }
+
+        tscn
+    }
+}
+
+fn sorted_properties(actor: &Actor) -> Vec<(&String, &Property)> {
+    let Properties(properties) = &actor.properties;
+    let mut properties: Vec<(&String, &Property)> = properties.iter().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+    properties
+}
+
+// Godot 3 text scene, with meshes and shapes embedded as sub-resources
+pub fn to_tscn(scene_tree: &[SceneTreeNode], settings: &TscnSettings) -> String {
+    let mut writer = Writer::new(settings);
+
+    for (i, node) in scene_tree.iter().enumerate() {
+        writer.scene_tree_node(node, Vector3::default(), ".", &[i]);
+    }
+
+    writer.finish()
+}
+
+// Writes a .tscn or .escn file, which share the same text format
+pub fn write_tscn(
+    scene_tree: &[SceneTreeNode],
+    file: &str,
+    settings: &TscnSettings,
+) -> io::Result<()> {
+    std::fs::write(file, to_tscn(scene_tree, settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene_tree::{ConvexCollision, NodePath};
+    use std::collections::HashMap;
+
+    #[test]
+    fn to_string() {
+        const TEST_SCENE_STRING: &str = include_str!("test_data/scene.tscn");
+
+        let mut properties = HashMap::new();
+        properties.insert("speed".to_string(), Property::Float(100.0));
+        properties.insert("message".to_string(), Property::String("Hello".into()));
+
+        let surface = MeshSurface::new(
+            Some("door".into()),
+            vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(0.0, 16.0, 0.0),
+                Vector3::new(16.0, 0.0, 0.0),
+            ],
+            vec![Vector3::new(0.0, 0.0, 1.0); 3],
+            vec![(Vector3::new(1.0, 0.0, 0.0), 1.0); 3],
+            None,
+            None,
+            None,
+            vec![0, 1, 2],
+        );
+
+        let scene_tree = vec![SceneTreeNode::entity(
+            "func_door".into(),
+            Vector3::new(0.0, 0.0, 16.0),
+            EntityType::class("KinematicBody"),
+            Some("res://door.gd".into()),
+            PropertyApplicationType::Dictionary,
+            Properties::new(properties),
+            vec![
+                SceneTreeNode::visual_geometry(
+                    Vector3::default(),
+                    VisualGeometry::Mesh(VisualMesh::new(vec![surface])),
+                ),
+                SceneTreeNode::collision_geometry(
+                    Vector3::default(),
+                    CollisionGeometry::convex(vec![ConvexCollision::new(
+                        Vector3::default(),
+                        vec![
+                            Vector3::new(0.0, 0.0, 0.0),
+                            Vector3::new(0.0, 16.0, 0.0),
+                            Vector3::new(16.0, 0.0, 0.0),
+                            Vector3::new(0.0, 0.0, 16.0),
+                        ],
+                    )]),
+                ),
+            ],
+        )];
+
+        let base_string = to_tscn(&scene_tree, &TscnSettings::default());
+
+        assert!(
+            base_string.as_str() == TEST_SCENE_STRING,
+            "Scene string\n\"{:?}\"\n!=\n\"{:?}\"",
+            base_string,
+            TEST_SCENE_STRING
+        );
+    }
+
+    #[test]
+    fn properties_layers_and_targets() {
+        let mut properties = HashMap::new();
+        properties.insert("speed".to_string(), Property::Float(100.0));
+        properties.insert("scale".to_string(), Property::String("2".into()));
+        properties.insert("target".to_string(), Property::String("door".into()));
+
+        let collision = CollisionGeometry::convex(vec![
+            ConvexCollision::new(Vector3::default(), vec![Vector3::default()]),
+            ConvexCollision::new(Vector3::default(), vec![Vector3::default()])
+                .with_layers(Some(CollisionLayers::new(2, 3))),
+        ]);
+
+        let mut trigger_target = TargetLink::new("target", "door");
+        trigger_target.nodes = vec![NodePath(vec![1, 0])];
+
+        let trigger = SceneTreeNode::entity(
+            "trigger_once".into(),
+            Vector3::default(),
+            EntityType::class("Area"),
+            None,
+            PropertyApplicationType::Properties,
+            Properties::new(properties),
+            vec![SceneTreeNode::collision_geometry(
+                Vector3::default(),
+                collision,
+            )],
+        )
+        .with_targets(vec![], vec![trigger_target])
+        .with_declared_properties(vec!["speed".to_string()].into_iter().collect());
+
+        let door = SceneTreeNode::entity(
+            "func_door".into(),
+            Vector3::default(),
+            EntityType::class("KinematicBody"),
+            None,
+            PropertyApplicationType::Metadata,
+            Properties::default(),
+            vec![],
+        );
+        let group = SceneTreeNode::entity(
+            "func_group".into(),
+            Vector3::default(),
+            EntityType::Placeholder,
+            None,
+            PropertyApplicationType::Properties,
+            Properties::default(),
+            vec![door],
+        );
+
+        let tscn = to_tscn(&[trigger, group], &TscnSettings::default());
+
+        // Undeclared keys stay out of the node's own properties
+        assert!(
+            tscn.contains("speed = 100.0")
+                && !tscn.contains("scale =")
+                && !tscn.contains("target ="),
+            "Scene\n{}",
+            tscn
+        );
+
+        let identity = "transform = Transform( 1, 0, 0, 0, 1, 0, 0, 0, 1, 0.0, 0.0, 0.0 )";
+        let expected = [
+            format!(
+                "[node name=\"collision_body\" type=\"StaticBody\" parent=\"trigger_once\"]\n{}\n\
+                 collision_layer = 2\ncollision_mask = 3\n",
+                identity
+            ),
+            "[node name=\"collision\" type=\"CollisionShape\" \
+             parent=\"trigger_once/collision_body\"]\n"
+                .to_string(),
+            "speed = 100.0\n__meta__ = {\n\"targets\": {\n\
+             \"target\": [ NodePath(\"../func_group/func_door\") ]\n}\n}\n"
+                .to_string(),
+            format!(
+                "[node name=\"func_door\" type=\"KinematicBody\" parent=\"func_group\"]\n{}\n\
+                 __meta__ = {{\n}}\n",
+                identity
+            ),
+        ];
+        for expected in &expected {
+            assert!(tscn.contains(expected), "{:?} not in\n{}", expected, tscn);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter, Result};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChoiceData {
     Integer(i32),
     Float(f32),
@@ -18,6 +19,7 @@ impl Display for ChoiceData {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Choice {
     pub name: String,
     pub value: ChoiceData,
@@ -1,6 +1,7 @@
 use super::{Metadata, Property};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ClassType {
     BaseClass,
     PointClass,
@@ -29,6 +30,7 @@ impl From<i64> for ClassType {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity {
     pub class_type: ClassType,
     pub metadata: Vec<Metadata>,
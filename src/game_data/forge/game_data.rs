@@ -1,6 +1,7 @@
-use super::Entity;
+use super::{ClassType, Entity, Metadata, PropertyData};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameData {
     pub name: String,
     pub includes: Vec<String>,
@@ -20,6 +21,78 @@ impl GameData {
         std::fs::write(file, self.to_string())?;
         Ok(())
     }
+
+    // Problems that would produce a broken or ambiguous FGD
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for (i, definition) in self.definitions.iter().enumerate() {
+            if self.definitions[..i]
+                .iter()
+                .any(|other| other.class_name == definition.class_name)
+            {
+                errors.push(format!("Duplicate class {}", definition.class_name));
+            }
+
+            for metadata in &definition.metadata {
+                if let Metadata::Base(base_classes) = metadata {
+                    for base_class in base_classes {
+                        let is_defined = self.definitions.iter().any(|other| {
+                            &other.class_name == base_class
+                                && matches!(other.class_type, ClassType::BaseClass)
+                        });
+
+                        if !is_defined {
+                            errors.push(format!(
+                                "{}: Unknown base class {}",
+                                definition.class_name, base_class
+                            ));
+                        }
+                    }
+                }
+            }
+
+            for (j, property) in definition.properties.iter().enumerate() {
+                if definition.properties[..j]
+                    .iter()
+                    .any(|other| other.name == property.name)
+                {
+                    errors.push(format!(
+                        "{}: Duplicate property {}",
+                        definition.class_name, property.name
+                    ));
+                }
+
+                match &property.data {
+                    PropertyData::Choices(choices, _) if choices.is_empty() => {
+                        errors.push(format!(
+                            "{}: Choices property {} has no choices",
+                            definition.class_name, property.name
+                        ))
+                    }
+                    PropertyData::Choices(choices, default)
+                        if *default < 0 || *default as usize >= choices.len() =>
+                    {
+                        errors.push(format!(
+                            "{}: Choices property {} default index out of range",
+                            definition.class_name, property.name
+                        ))
+                    }
+                    PropertyData::Flags(flags, default)
+                        if flags.len() < 32 && *default >> flags.len() != 0 =>
+                    {
+                        errors.push(format!(
+                            "{}: Flags property {} default sets undeclared bits",
+                            definition.class_name, property.name
+                        ))
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        errors
+    }
 }
 
 impl Default for GameData {
@@ -52,15 +125,14 @@ impl ToString for GameData {
 
 #[cfg(test)]
 mod tests {
-    #[test]
-    fn to_string() {
-        use crate::game_data::forge::{
-            Choice, ClassType, Entity, GameData, Metadata, Property, PropertyData,
-        };
-        use crate::{Color, Vector3};
+    use crate::game_data::forge::{
+        Choice, ClassType, Entity, GameData, Metadata, Property, PropertyData,
+    };
+    use crate::{Color, Vector3};
 
-        const TEST_GAME_DATA_STRING: &str = include_str!("test_data/game_data.fgd");
+    const TEST_GAME_DATA_STRING: &str = include_str!("test_data/game_data.fgd");
 
+    fn test_game_data() -> GameData {
         let point_entity = Entity::new(
             ClassType::PointClass,
             vec![
@@ -118,13 +190,16 @@ mod tests {
             ],
         );
 
-        let game_data = GameData::new(
+        GameData::new(
             "Test Game Data".to_string(),
             vec!["base.fgd".into(), "other.fgd".into()],
             vec![point_entity, solid_entity],
-        );
+        )
+    }
 
-        let base_string = game_data.to_string();
+    #[test]
+    fn to_string() {
+        let base_string = test_game_data().to_string();
 
         assert!(
             base_string.as_str() == TEST_GAME_DATA_STRING,
@@ -133,4 +208,92 @@ mod tests {
             TEST_GAME_DATA_STRING
         );
     }
+
+    #[test]
+    fn validate() {
+        let mut game_data = test_game_data();
+
+        let errors = game_data.validate();
+        assert!(
+            errors
+                == vec![
+                    "point_class: Unknown base class Origin".to_string(),
+                    "solid_class: Unknown base class Origin".to_string(),
+                ],
+            "Validation errors {:?}",
+            errors
+        );
+
+        game_data.definitions.push(Entity::new(
+            ClassType::BaseClass,
+            vec![],
+            "Origin",
+            "Origin",
+            vec![],
+        ));
+        game_data.definitions.push(game_data.definitions[0].clone());
+
+        let errors = game_data.validate();
+        assert!(
+            errors == vec!["Duplicate class point_class".to_string()],
+            "Validation errors {:?}",
+            errors
+        );
+
+        game_data.definitions.pop();
+        let property = &mut game_data.definitions[0].properties[0];
+        if let PropertyData::Choices(_, default) = &mut property.data {
+            *default = 3;
+        }
+
+        let errors = game_data.validate();
+        assert!(
+            errors
+                == vec![
+                    "point_class: Choices property choices_property default index out of range"
+                        .to_string()
+                ],
+            "Validation errors {:?}",
+            errors
+        );
+
+        // Writing the bad default must not panic
+        let fgd = game_data.to_string();
+        assert!(
+            fgd.contains("choices_property(choices) : \"Choices Property\" : \"\" ="),
+            "FGD {}",
+            fgd
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let game_data = test_game_data();
+
+        let json = serde_json::to_string(&game_data).unwrap();
+        let json_game_data: GameData = serde_json::from_str(&json).unwrap();
+
+        let ron = ron::to_string(&game_data).unwrap();
+        let ron_game_data: GameData = ron::from_str(&ron).unwrap();
+
+        let bincode = bincode::serialize(&game_data).unwrap();
+        let bincode_game_data: GameData = bincode::deserialize(&bincode).unwrap();
+
+        for (format, game_data) in &[
+            ("JSON", json_game_data),
+            ("RON", ron_game_data),
+            ("bincode", bincode_game_data),
+        ] {
+            let base_string = game_data.to_string();
+
+            assert!(
+                base_string.as_str() == TEST_GAME_DATA_STRING,
+                "{} game data string\n\"{:?}\"\n!=\n\"{:?}\"",
+                format,
+                base_string,
+                TEST_GAME_DATA_STRING
+            );
+        }
+    }
 }
@@ -2,6 +2,7 @@ use crate::Color;
 use crate::Vector3;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Metadata {
     Base(Vec<String>),
     Color(Color),
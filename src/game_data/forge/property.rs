@@ -2,6 +2,7 @@ use super::Choice;
 use crate::{Color, Vector3};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PropertyData {
     Integer(i32),
     Float(f32),
@@ -15,6 +16,7 @@ pub enum PropertyData {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Property {
     pub name: String,
     pub short_description: String,
@@ -92,12 +94,15 @@ impl ToString for Property {
                     acc + &format!("\t\t{}\n", next.to_string())
                 });
 
+                // An out of range default is reported by GameData::validate, so leave it empty here
+                let default_string = choices
+                    .get(*default as usize)
+                    .map(|choice| choice.value.to_string())
+                    .unwrap_or_default();
+
                 format!(
                     "{}(choices) : \"{}\" : \"{}\" =\n\t[\n{}\t]",
-                    self.name,
-                    self.short_description,
-                    choices[*default as usize].value.to_string(),
-                    choices_string
+                    self.name, self.short_description, default_string, choices_string
                 )
             }
             PropertyData::Flags(flags, default) => {
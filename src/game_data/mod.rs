@@ -18,4 +18,8 @@ pub use types::Property;
 
 pub use types::WorldspawnLayer;
 
+pub use types::CollisionLayers;
+pub use types::TextureRule;
+pub use types::TextureRuleScope;
+
 pub use types::GameData;
@@ -1,6 +1,7 @@
 use super::{CollisionType, VisualType};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BrushData {
     pub visual_type: VisualType,
     pub collision_type: CollisionType,
@@ -4,12 +4,14 @@ use super::{
 };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EntityData {
     Point(PointData),
     Brush(PointData, BrushData),
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity {
     pub classname: String,
     pub data: EntityData,
@@ -1,5 +1,46 @@
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameData {
     pub entities: Vec<crate::game_data::Entity>,
     pub worldspawn_layers: Vec<crate::game_data::WorldspawnLayer>,
+    // Checked in order, the first rule matching a texture wins
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub texture_rules: Vec<crate::game_data::TextureRule>,
+}
+
+impl GameData {
+    pub fn new(
+        entities: Vec<crate::game_data::Entity>,
+        worldspawn_layers: Vec<crate::game_data::WorldspawnLayer>,
+    ) -> GameData {
+        let texture_rules = Vec::new();
+
+        GameData {
+            entities,
+            worldspawn_layers,
+            texture_rules,
+        }
+    }
+
+    pub fn with_texture_rules(
+        mut self,
+        texture_rules: Vec<crate::game_data::TextureRule>,
+    ) -> GameData {
+        self.texture_rules = texture_rules;
+        self
+    }
+}
+
+impl Default for GameData {
+    fn default() -> Self {
+        let entities = Vec::new();
+        let worldspawn_layers = Vec::new();
+        let texture_rules = Vec::new();
+
+        GameData {
+            entities,
+            worldspawn_layers,
+            texture_rules,
+        }
+    }
 }
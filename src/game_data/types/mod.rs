@@ -6,6 +6,7 @@ mod entity_type;
 mod game_data;
 mod point_data;
 mod properties;
+mod texture_rule;
 mod visual_type;
 mod worldspawn_layer;
 
@@ -28,6 +29,10 @@ pub use point_data::PropertyApplicationType;
 pub use properties::Properties;
 pub use properties::Property;
 
+pub use texture_rule::CollisionLayers;
+pub use texture_rule::TextureRule;
+pub use texture_rule::TextureRuleScope;
+
 pub use worldspawn_layer::WorldspawnLayer;
 
 pub use game_data::GameData;
@@ -1,6 +1,7 @@
 use super::{ComponentType, EntityType, Properties};
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PropertyApplicationType {
     Properties,
     Dictionary,
@@ -8,6 +9,7 @@ pub enum PropertyApplicationType {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PointData {
     pub entity_type: EntityType,
     pub component_type: ComponentType,
@@ -1,23 +1,32 @@
 use std::collections::HashMap;
 
+use crate::game_data::forge::ChoiceData;
 use crate::Color;
 use crate::Vector3;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Property {
     Integer(i32),
     Float(f32),
     Vector3(Vector3),
     String(String),
     Color(Color),
-    Choices(i32),
+    Choices(ChoiceData),
     Flags(i32),
-    TargetSource,
-    TargetDestination
+    TargetSource(String),
+    TargetDestination(String),
 }
 
 #[derive(Debug, Clone)]
-pub struct Properties(pub HashMap<String, Property>);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Properties(
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::types::serialize_sorted")
+    )]
+    pub HashMap<String, Property>,
+);
 
 impl Properties {
     pub fn new(properties: HashMap<String, Property>) -> Properties {
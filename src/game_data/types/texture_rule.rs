@@ -0,0 +1,74 @@
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextureRuleScope {
+    // Only the faces with the rule's texture
+    Face,
+    // Every face of any brush with the rule's texture on one of its faces
+    Brush,
+}
+
+// Physics layer and mask bits, as Godot's collision_layer and collision_mask
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollisionLayers {
+    pub layer: u32,
+    pub mask: u32,
+}
+
+impl CollisionLayers {
+    pub fn new(layer: u32, mask: u32) -> CollisionLayers {
+        CollisionLayers { layer, mask }
+    }
+}
+
+// Rules can only take away the visuals or collision an entity's BrushData gives it
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureRule {
+    // Texture name, '*' matching any characters and '?' any single one
+    pub texture: String,
+    pub scope: TextureRuleScope,
+    pub visual: bool,
+    pub collision: bool,
+    pub collision_layers: Option<CollisionLayers>,
+}
+
+impl TextureRule {
+    pub fn new(
+        texture: String,
+        scope: TextureRuleScope,
+        visual: bool,
+        collision: bool,
+    ) -> TextureRule {
+        TextureRule {
+            texture,
+            scope,
+            visual,
+            collision,
+            collision_layers: None,
+        }
+    }
+
+    pub fn with_collision_layers(mut self, collision_layers: CollisionLayers) -> TextureRule {
+        self.collision_layers = Some(collision_layers);
+        self
+    }
+}
+
+impl Default for TextureRule {
+    fn default() -> Self {
+        let texture = "".into();
+        let scope = TextureRuleScope::Face;
+        let visual = true;
+        let collision = true;
+        let collision_layers = None;
+
+        TextureRule {
+            texture,
+            scope,
+            visual,
+            collision,
+            collision_layers,
+        }
+    }
+}
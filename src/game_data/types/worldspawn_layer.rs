@@ -1,6 +1,7 @@
 use super::{CollisionType, ComponentType, EntityType, VisualType};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WorldspawnLayer {
     pub name: String,
     pub texture: String,
@@ -1,5 +1,4 @@
 use crate::map::quake::Brush;
-use crate::map::quake::Entity;
 use crate::TextureInfo;
 use crate::Vector3;
 
@@ -8,13 +7,13 @@ mod geometry;
 use super::brush_plane;
 pub use geometry::Geometry;
 
-pub fn build(textures: &TextureInfo, entity: &Entity, brush: &Brush) -> Geometry {
+pub fn build(textures: &TextureInfo, brush: &Brush) -> Geometry {
 
     // Build planes
     let planes = &brush.planes;
     let plane_geometry: Vec<brush_plane::Geometry> = planes
         .iter()
-        .map(|plane| brush_plane::build(textures, entity, planes, plane))
+        .map(|plane| brush_plane::build(textures, planes, plane))
         .collect();
 
     // Calculate center
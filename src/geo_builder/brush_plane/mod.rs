@@ -1,13 +1,11 @@
 use std::cmp::Ordering;
 
 mod geometry;
-mod normals;
 mod tangents;
 mod uvs;
 mod vertices;
 
 use crate::map::quake::BrushPlane;
-use crate::map::quake::Entity;
 use crate::TextureInfo;
 use crate::Vector3;
 pub use geometry::Geometry;
@@ -16,7 +14,6 @@ use crate::Vertex;
 
 pub fn build(
     TextureInfo(texture_info): &TextureInfo,
-    entity: &Entity,
     planes: &[BrushPlane],
     plane: &BrushPlane,
 ) -> Geometry {
@@ -27,39 +24,21 @@ pub fn build(
         .flat_map(|p1| {
             planes
                 .iter()
-                .flat_map(move |p2| build_plane_vertex(texture_info, entity, planes, plane, p1, p2))
+                .flat_map(move |p2| build_plane_vertex(texture_info, planes, plane, p1, p2))
         })
         .collect();
 
+    // Find unique vertices, smoothing is applied later across the whole entity
     let unique_vertices: Vec<Vertex> = plane_vertices
         .iter()
         .enumerate()
-        .flat_map(|(i, vertex)| {
-            // Find unique vertices and aggregate phong normals
-            match plane_vertices
+        .filter(|(i, vertex)| {
+            plane_vertices
                 .iter()
                 .skip(i + 1)
-                .find(|comp| comp.vertex == vertex.vertex)
-            {
-                None => match entity.get_property("_phong") {
-                    Some("1") => {
-                        let mut vertex = plane_vertices.iter().skip(i + 1).fold(
-                            vertex.clone(),
-                            |mut acc, next| {
-                                if next.vertex == acc.vertex {
-                                    acc.normal = next.normal;
-                                }
-                                acc
-                            },
-                        );
-                        vertex.normal = vertex.normal.normalize();
-                        Some(vertex)
-                    }
-                    _ => Some(vertex.clone()),
-                },
-                _ => None,
-            }
+                .all(|comp| comp.vertex != vertex.vertex)
         })
+        .map(|(_, vertex)| vertex.clone())
         .collect();
 
     let center: Vector3 = unique_vertices
@@ -110,12 +89,11 @@ pub fn build(
         None => None,
     };
 
-    Geometry::new(center, world_vertices, indices, texture)
+    Geometry::new(center, plane.normal(), world_vertices, indices, texture)
 }
 
 fn build_plane_vertex(
     texture_info: Option<&crate::Texture>,
-    entity: &Entity,
     planes: &[BrushPlane],
     plane: &BrushPlane,
     p1: &BrushPlane,
@@ -123,7 +101,7 @@ fn build_plane_vertex(
 ) -> Option<Vertex> {
     if let Some(vertex) = vertices::intersect_brush_planes(plane, p1, p2) {
         if vertices::vertex_in_hull(vertex, planes) {
-            let normal = normals::vertex_normal(entity, plane, p1, p2);
+            let normal = plane.normal();
             let tangent = tangents::vertex_tangent(plane);
 
             let uv = match &texture_info {
@@ -136,7 +114,7 @@ fn build_plane_vertex(
                 _ => None,
             };
 
-            return Some(Vertex::new(vertex, normal, tangent, uv, color));
+            return Some(Vertex::new(vertex, normal, tangent, uv, None, color));
         }
     }
 
@@ -1,21 +1,30 @@
 use crate::map::quake::Entity;
+use crate::TextureBlacklist;
 use crate::TextureInfo;
 use crate::Vector3;
 
 mod geometry;
+mod smoothing;
 
 use super::brush;
 pub use geometry::Geometry;
 
-pub fn build(textures: &TextureInfo, entity: &Entity) -> Geometry {
+pub fn build(
+    textures: &TextureInfo,
+    texture_blacklist: &TextureBlacklist,
+    entity: &Entity,
+) -> Geometry {
 
     // Build brushes
-    let brush_geometry: Vec<brush::Geometry> = entity
+    let mut brush_geometry: Vec<brush::Geometry> = entity
         .brushes
         .iter()
-        .map(|brush| brush::build(textures, entity, brush))
+        .map(|brush| brush::build(textures, brush))
         .collect();
 
+    // Smooth normals across all brushes
+    smoothing::apply(entity, texture_blacklist, &mut brush_geometry);
+
     // Calculate center
     let origin = entity.properties.get("origin");
     let center: Vector3 = match origin {
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use super::brush;
+use crate::map::quake::Entity;
+use crate::TextureBlacklist;
+use crate::Vector3;
+
+const DEFAULT_PHONG_ANGLE: f32 = 89.0;
+const CONCAVE_EPSILON: f32 = 0.001;
+const POSITION_PRECISION: f32 = 100.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Smoothing {
+    pub angle: f32,
+    pub angle_concave: f32,
+}
+
+impl Smoothing {
+    pub fn new(angle: f32, angle_concave: f32) -> Smoothing {
+        Smoothing {
+            angle,
+            angle_concave,
+        }
+    }
+
+    // Read ericw-tools style '_phong', '_phong_angle' and '_phong_angle_concave' keys
+    pub fn from_entity(entity: &Entity) -> Option<Smoothing> {
+        let parse_angle = |key: &str| {
+            entity
+                .get_property(key)
+                .and_then(|value| value.parse::<f32>().ok())
+        };
+
+        let phong_angle = parse_angle("_phong_angle");
+
+        let angle = match (entity.get_property("_phong"), phong_angle) {
+            (Some("1"), Some(phong_angle)) => phong_angle,
+            (Some("1"), None) => DEFAULT_PHONG_ANGLE,
+            (_, Some(phong_angle)) if phong_angle > 0.0 => phong_angle,
+            _ => return None,
+        };
+
+        let angle_concave = parse_angle("_phong_angle_concave").unwrap_or(angle);
+
+        Some(Smoothing::new(angle, angle_concave))
+    }
+}
+
+struct Face {
+    brush_idx: usize,
+    plane_idx: usize,
+    normal: Vector3,
+    center: Vector3,
+}
+
+type PositionKey = (i64, i64, i64);
+
+fn position_key(position: Vector3) -> PositionKey {
+    (
+        (position.x() * POSITION_PRECISION).round() as i64,
+        (position.y() * POSITION_PRECISION).round() as i64,
+        (position.z() * POSITION_PRECISION).round() as i64,
+    )
+}
+
+// Interior angle of a face's wound polygon at the given vertex, used to weight its normal
+fn corner_angle(vertices: &[crate::Vertex], i: usize) -> f32 {
+    let count = vertices.len();
+    if count < 3 {
+        return 0.0;
+    }
+
+    let prev = vertices[(i + count - 1) % count].vertex - vertices[i].vertex;
+    let next = vertices[(i + 1) % count].vertex - vertices[i].vertex;
+
+    if prev.length() == 0.0 || next.length() == 0.0 {
+        return 0.0;
+    }
+
+    prev.normalize()
+        .dot(next.normalize())
+        .clamp(-1.0, 1.0)
+        .acos()
+}
+
+pub fn apply(
+    entity: &Entity,
+    texture_blacklist: &TextureBlacklist,
+    brush_geometry: &mut [brush::Geometry],
+) {
+    let smoothing = match Smoothing::from_entity(entity) {
+        Some(smoothing) => smoothing,
+        None => return,
+    };
+
+    let threshold = (smoothing.angle + 0.01).to_radians().cos();
+    let threshold_concave = (smoothing.angle_concave + 0.01).to_radians().cos();
+
+    // Gather every face of the entity that takes part in smoothing
+    let faces: Vec<Face> = entity
+        .brushes
+        .iter()
+        .zip(brush_geometry.iter())
+        .enumerate()
+        .flat_map(|(brush_idx, (brush, brush_geometry))| {
+            brush
+                .planes
+                .iter()
+                .zip(brush_geometry.plane_geometry.iter())
+                .enumerate()
+                .filter(|(_, (plane, _))| {
+                    !texture_blacklist.is_blacklisted_smoothing(&plane.texture)
+                })
+                .map(move |(plane_idx, (_, plane_geometry))| Face {
+                    brush_idx,
+                    plane_idx,
+                    normal: plane_geometry.normal,
+                    center: plane_geometry.center,
+                })
+        })
+        .collect();
+
+    // Accumulate angle-weighted face normals at each shared position
+    let mut corners: HashMap<PositionKey, Vec<(usize, Vector3)>> = HashMap::new();
+    for (face_idx, face) in faces.iter().enumerate() {
+        let vertices = &brush_geometry[face.brush_idx].plane_geometry[face.plane_idx].vertices;
+        for (i, vertex) in vertices.iter().enumerate() {
+            corners
+                .entry(position_key(vertex.vertex))
+                .or_default()
+                .push((face_idx, face.normal * corner_angle(vertices, i)));
+        }
+    }
+
+    let smoothed_normals: Vec<Vec<Vector3>> = faces
+        .iter()
+        .map(|face| {
+            let vertices = &brush_geometry[face.brush_idx].plane_geometry[face.plane_idx].vertices;
+            vertices
+                .iter()
+                .map(|vertex| {
+                    let corner = match corners.get(&position_key(vertex.vertex)) {
+                        Some(corner) => corner,
+                        None => return face.normal,
+                    };
+
+                    let normal = corner.iter().fold(
+                        Vector3::new(0.0, 0.0, 0.0),
+                        |acc, (other_idx, weighted_normal)| {
+                            let other = &faces[*other_idx];
+                            let concave =
+                                face.normal.dot(other.center - face.center) > CONCAVE_EPSILON;
+                            let threshold = if concave {
+                                threshold_concave
+                            } else {
+                                threshold
+                            };
+
+                            if face.normal.dot(other.normal) >= threshold {
+                                acc + *weighted_normal
+                            } else {
+                                acc
+                            }
+                        },
+                    );
+
+                    if normal.length() > 0.0 {
+                        normal.normalize()
+                    } else {
+                        face.normal
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    for (face, normals) in faces.iter().zip(smoothed_normals) {
+        let vertices = &mut brush_geometry[face.brush_idx].plane_geometry[face.plane_idx].vertices;
+        for (vertex, normal) in vertices.iter_mut().zip(normals) {
+            vertex.normal = normal;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::quake::{Brush, BrushPlane};
+    use crate::{Texture, TextureInfo};
+
+    fn cube_brush(min: Vector3, max: Vector3) -> Brush {
+        let plane = |v0: Vector3, v1: Vector3, v2: Vector3| {
+            BrushPlane::new(&format!(
+                "( {} {} {} ) ( {} {} {} ) ( {} {} {} ) tex 0 0 0 1 1",
+                v0.x(),
+                v0.y(),
+                v0.z(),
+                v1.x(),
+                v1.y(),
+                v1.z(),
+                v2.x(),
+                v2.y(),
+                v2.z()
+            ))
+            .unwrap()
+        };
+
+        let mut brush = Brush::new();
+        brush.planes = vec![
+            plane(
+                Vector3::new(min.x(), min.y(), min.z()),
+                Vector3::new(min.x(), min.y() + 1.0, min.z()),
+                Vector3::new(min.x(), min.y(), min.z() + 1.0),
+            ),
+            plane(
+                Vector3::new(min.x(), min.y(), min.z()),
+                Vector3::new(min.x(), min.y(), min.z() + 1.0),
+                Vector3::new(min.x() + 1.0, min.y(), min.z()),
+            ),
+            plane(
+                Vector3::new(min.x(), min.y(), min.z()),
+                Vector3::new(min.x() + 1.0, min.y(), min.z()),
+                Vector3::new(min.x(), min.y() + 1.0, min.z()),
+            ),
+            plane(
+                Vector3::new(max.x(), max.y(), max.z()),
+                Vector3::new(max.x(), max.y() + 1.0, max.z()),
+                Vector3::new(max.x() + 1.0, max.y(), max.z()),
+            ),
+            plane(
+                Vector3::new(max.x(), max.y(), max.z()),
+                Vector3::new(max.x() + 1.0, max.y(), max.z()),
+                Vector3::new(max.x(), max.y(), max.z() + 1.0),
+            ),
+            plane(
+                Vector3::new(max.x(), max.y(), max.z()),
+                Vector3::new(max.x(), max.y(), max.z() + 1.0),
+                Vector3::new(max.x(), max.y() + 1.0, max.z()),
+            ),
+        ];
+        brush
+    }
+
+    fn build_entity(
+        properties: &[(&str, &str)],
+        blacklist: &TextureBlacklist,
+    ) -> super::super::Geometry {
+        let mut entity = Entity::new();
+        for (key, value) in properties {
+            entity.properties.insert(key.to_string(), value.to_string());
+        }
+        entity.brushes.push(cube_brush(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(32.0, 32.0, 32.0),
+        ));
+
+        let mut texture_info = HashMap::new();
+        texture_info.insert("tex".to_string(), Texture::new(32, 32));
+
+        super::super::build(&TextureInfo(texture_info), blacklist, &entity)
+    }
+
+    fn corner_normals(geometry: &super::super::Geometry) -> Vec<Vector3> {
+        geometry.brush_geometry[0]
+            .plane_geometry
+            .iter()
+            .flat_map(|plane| plane.vertices.iter())
+            .filter(|vertex| vertex.vertex == Vector3::new(32.0, 32.0, 32.0))
+            .map(|vertex| vertex.normal)
+            .collect()
+    }
+
+    #[test]
+    fn flat_without_phong() {
+        let geometry = build_entity(&[], &TextureBlacklist::default());
+        let normals = corner_normals(&geometry);
+
+        assert!(
+            normals.len() == 3,
+            "Expected 3 faces at corner, got {}",
+            normals.len()
+        );
+        for normal in normals {
+            assert!(
+                (normal.length() - 1.0).abs() < 0.001
+                    && (normal.x().abs() == 1.0
+                        || normal.y().abs() == 1.0
+                        || normal.z().abs() == 1.0),
+                "Normal {:?} is not axis-aligned",
+                normal
+            );
+        }
+    }
+
+    #[test]
+    fn smoothed_corner() {
+        let geometry = build_entity(
+            &[("_phong", "1"), ("_phong_angle", "90")],
+            &TextureBlacklist::default(),
+        );
+        let expected = Vector3::new(1.0, 1.0, 1.0).normalize();
+
+        for normal in corner_normals(&geometry) {
+            assert!(
+                (normal - expected).length() < 0.001,
+                "Normal {:?} != {:?}",
+                normal,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn angle_threshold() {
+        let geometry = build_entity(
+            &[("_phong", "1"), ("_phong_angle", "45")],
+            &TextureBlacklist::default(),
+        );
+
+        for normal in corner_normals(&geometry) {
+            assert!(
+                normal.x().abs() == 1.0 || normal.y().abs() == 1.0 || normal.z().abs() == 1.0,
+                "Normal {:?} was smoothed past the threshold",
+                normal
+            );
+        }
+    }
+
+    #[test]
+    fn blacklisted_texture() {
+        let mut blacklist = TextureBlacklist::default();
+        blacklist.smoothing.insert("tex");
+
+        let geometry = build_entity(&[("_phong", "1"), ("_phong_angle", "90")], &blacklist);
+
+        for normal in corner_normals(&geometry) {
+            assert!(
+                normal.x().abs() == 1.0 || normal.y().abs() == 1.0 || normal.z().abs() == 1.0,
+                "Normal {:?} was smoothed despite exclusion",
+                normal
+            );
+        }
+    }
+}
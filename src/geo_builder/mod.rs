@@ -1,14 +1,21 @@
 use crate::map::quake::Entity;
+use crate::Diagnostics;
+use crate::TextureBlacklist;
 use crate::TextureInfo;
 
 pub mod brush;
 pub mod brush_plane;
 pub mod entity;
 
-pub fn run(textures: &TextureInfo, entities: &[Entity]) -> Vec<entity::Geometry> {
-    println!("Running geo builder");
+pub fn run(
+    textures: &TextureInfo,
+    texture_blacklist: &TextureBlacklist,
+    entities: &[Entity],
+    diagnostics: &Diagnostics,
+) -> Vec<entity::Geometry> {
+    diagnostics.debug("Running geo builder");
     entities
         .iter()
-        .map(|entity| entity::build(textures, entity))
+        .map(|entity| entity::build(textures, texture_blacklist, entity))
         .collect()
 }
@@ -1,9 +1,11 @@
 use crate::geo_builder;
 use crate::map;
+use crate::Diagnostics;
 use std::collections::HashMap;
 
 pub fn run(
     entity_data: Vec<(map::quake::Entity, geo_builder::entity::Geometry)>,
+    diagnostics: &Diagnostics,
 ) -> (
     Vec<(map::quake::Entity, geo_builder::entity::Geometry)>,
     HashMap<String, Vec<geo_builder::brush::Geometry>>,
@@ -45,11 +47,11 @@ pub fn run(
             worldspawn_brush_geo = worldspawn_geometry;
         }
 
-        println!(
+        diagnostics.debug(&format!(
             "Worldspawn Brush Geo: {:?}, Worldspawn Layer Geo: {:?}",
             worldspawn_brush_geo.len(),
             worldspawn_layers.len()
-        );
+        ));
 
         worldspawn_geometry.brush_geometry = worldspawn_brush_geo;
         worldspawn_entity_data.insert(0, (worldspawn_entity, worldspawn_geometry));
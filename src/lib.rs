@@ -1,26 +1,56 @@
+pub mod diagnostics;
+pub mod export;
 pub mod game_data;
+pub mod lint;
+pub mod report;
 pub mod scene_tree;
+pub mod texture_source;
+pub mod vfs;
 pub mod wad;
+#[cfg(feature = "watch")]
+pub mod watch;
 
+#[cfg(feature = "cache")]
+mod cache;
 mod geo_builder;
 mod layer_filter;
 mod map;
 mod types;
+mod uv_unwrap;
+mod vertex_bake;
 
+#[cfg(feature = "cache")]
+pub use cache::BuildCache;
+pub use diagnostics::{DiagnosticReport, DiagnosticSink, Diagnostics};
+pub use report::BuildReport;
 pub use types::{
-    Color, Mat2, Quat, Texture, TextureBlacklist, TextureInfo, Vector2, Vector3, Vertex,
+    AnimationChain, AnimationChains, Color, Liquid, Mat2, Quat, SurfaceKind, Texture,
+    TextureAnimation, TextureBlacklist, TextureInfo, TexturePatterns, TexturePreset,
+    TextureSemantics, Vector2, Vector3, Vertex,
 };
+pub use uv_unwrap::UnwrapSettings;
+pub use vertex_bake::BakeSettings;
 
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::sync::Arc;
+use std::time::Instant;
 use std::{fmt, fs};
 
 #[derive(Debug)]
 pub struct Config {
     map_file: String,
     texture_info: TextureInfo,
+    wad_search_paths: Option<Vec<String>>,
+    texture_roots: Vec<String>,
     texture_blacklist: TextureBlacklist,
     forge_game_data: game_data::forge::GameData,
     quarchitect_game_data: game_data::GameData,
+    uv_unwrap: Option<UnwrapSettings>,
+    vertex_bake: Option<BakeSettings>,
+    #[cfg(feature = "cache")]
+    cache: Option<BuildCache>,
+    diagnostics: Diagnostics,
 }
 
 impl Config {
@@ -32,14 +62,63 @@ impl Config {
         quarchitect_game_data: game_data::GameData,
     ) -> Config {
         let map_file = map_file.into();
+        let wad_search_paths = None;
+        let texture_roots = Vec::new();
+        let uv_unwrap = None;
+        let vertex_bake = None;
+        #[cfg(feature = "cache")]
+        let cache = None;
+        let diagnostics = Diagnostics::default();
         Config {
             map_file,
             texture_info,
+            wad_search_paths,
+            texture_roots,
             texture_blacklist,
             forge_game_data,
             quarchitect_game_data,
+            uv_unwrap,
+            vertex_bake,
+            #[cfg(feature = "cache")]
+            cache,
+            diagnostics,
         }
     }
+
+    // Fill in texture sizes from the WADs named by worldspawn's "wad" key, looked up in the map's
+    // directory and then these directories or archives
+    pub fn with_wad_search_paths(mut self, wad_search_paths: Vec<String>) -> Config {
+        self.wad_search_paths = Some(wad_search_paths);
+        self
+    }
+
+    // Fill in texture sizes from loose images under these directories, named like "base/wall"
+    // for base/wall.png as TrenchBroom does
+    pub fn with_texture_roots(mut self, texture_roots: Vec<String>) -> Config {
+        self.texture_roots = texture_roots;
+        self
+    }
+
+    pub fn with_uv_unwrap(mut self, uv_unwrap: UnwrapSettings) -> Config {
+        self.uv_unwrap = Some(uv_unwrap);
+        self
+    }
+
+    pub fn with_vertex_bake(mut self, vertex_bake: BakeSettings) -> Config {
+        self.vertex_bake = Some(vertex_bake);
+        self
+    }
+
+    #[cfg(feature = "cache")]
+    pub fn with_cache(mut self, cache: BuildCache) -> Config {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_diagnostics(mut self, sink: Arc<dyn DiagnosticSink>) -> Config {
+        self.diagnostics = Diagnostics::new(sink);
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -53,16 +132,126 @@ impl fmt::Display for QuarchitectError {
 impl Error for QuarchitectError {}
 
 pub fn run(config: Config) -> Result<Vec<scene_tree::SceneTreeNode>, Box<dyn Error>> {
-    println!("TODO-3: Profile performance cost centers against ad_sepulcher.map, multithread with rayon");
+    let (scene_tree, _report) = run_with_report(config)?;
+    Ok(scene_tree)
+}
+
+// Like run, but also returns statistics, stage timings and the warnings raised along the way
+pub fn run_with_report(
+    config: Config,
+) -> Result<(Vec<scene_tree::SceneTreeNode>, BuildReport), Box<dyn Error>> {
+    // TODO-3: Profile performance cost centers against ad_sepulcher.map, multithread with rayon
+    let diagnostics = &config.diagnostics;
+    let mut report = BuildReport::default();
 
     // Parse map into tokens and entities
-    println!("Parse map");
-    let file_string = fs::read_to_string(config.map_file)?;
-    let tokens = map::quake::tokenizer::run(file_string);
-    let (_token_paths, entities) = map::quake::parser::run(&tokens)?;
+    diagnostics.debug("Parse map");
+    let start = Instant::now();
+    let file_string = fs::read_to_string(&config.map_file)?;
+    report.record_timing("read", start);
+
+    // Resolve worldspawn WADs and loose images before the cache lookup, since their textures feed
+    // the cache key. Otherwise parsing waits until the cache has missed.
+    let mut parsed_entities = None;
+    let texture_info = if config.wad_search_paths.is_some() || !config.texture_roots.is_empty() {
+        let entities = parse_map(&file_string, &mut report)?;
+
+        let start = Instant::now();
+        let (texture_info, source_files) = texture_source::run(
+            &config.map_file,
+            &config.wad_search_paths,
+            &config.texture_roots,
+            &config.texture_info,
+            &entities,
+            diagnostics,
+        );
+        report.source_files = source_files;
+        report.record_timing("textures", start);
+        parsed_entities = Some(entities);
+        texture_info
+    } else {
+        config.texture_info.clone()
+    };
+
+    // Reuse the previous build if nothing that feeds into it has changed
+    #[cfg(feature = "cache")]
+    let cache_key = match &config.cache {
+        Some(cache) => {
+            let start = Instant::now();
+            let key = BuildCache::key(
+                &file_string,
+                &cache::CacheInputs {
+                    texture_info: &texture_info,
+                    texture_blacklist: &config.texture_blacklist,
+                    forge_game_data: &config.forge_game_data,
+                    quarchitect_game_data: &config.quarchitect_game_data,
+                    uv_unwrap: &config.uv_unwrap,
+                    vertex_bake: &config.vertex_bake,
+                },
+            );
+
+            // Without a key there is no telling a stale entry from a fresh one, so build as usual
+            match key {
+                Ok(key) => {
+                    let scene_tree = cache.load(&config.map_file, &key);
+                    report.record_timing("cache_load", start);
+
+                    if let Some(scene_tree) = scene_tree {
+                        diagnostics.info("Loaded scene tree from build cache");
+                        report.from_cache = true;
+                        report.record_diagnostics(diagnostics.report());
+                        return Ok((scene_tree, report));
+                    }
+
+                    Some(key)
+                }
+                Err(error) => {
+                    diagnostics.warning(diagnostics::DiagnosticKind::CacheKeyFailed { error });
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let entities = match parsed_entities {
+        Some(entities) => entities,
+        None => parse_map(&file_string, &mut report)?,
+    };
+
+    report.record_map(&entities, &texture_info, &config.quarchitect_game_data);
 
     // Build geometry
-    let entity_geometry = geo_builder::run(&config.texture_info, &entities);
+    let start = Instant::now();
+    let mut entity_geometry = geo_builder::run(
+        &texture_info,
+        &config.texture_blacklist,
+        &entities,
+        diagnostics,
+    );
+    report.record_timing("geometry", start);
+
+    report.record_geometry(&entities, &entity_geometry);
+
+    // Generate lightmap UVs
+    if let Some(uv_unwrap) = &config.uv_unwrap {
+        let start = Instant::now();
+        uv_unwrap::run(uv_unwrap, &mut entity_geometry);
+        report.record_timing("uv_unwrap", start);
+    }
+
+    // Bake ambient occlusion and lighting into vertex colors
+    if let Some(vertex_bake) = &config.vertex_bake {
+        let start = Instant::now();
+        vertex_bake::run(
+            vertex_bake,
+            &config.texture_blacklist,
+            &config.quarchitect_game_data,
+            &entities,
+            &mut entity_geometry,
+        );
+        report.record_timing("vertex_bake", start);
+    }
 
     // Couple entities to their geometry
     let entity_data: Vec<(map::quake::Entity, geo_builder::entity::Geometry)> = entities
@@ -71,21 +260,76 @@ pub fn run(config: Config) -> Result<Vec<scene_tree::SceneTreeNode>, Box<dyn Err
         .collect();
 
     // Split layers out of worldspawn
-    let (entity_data, worldspawn_layer_data) = layer_filter::run(entity_data);
+    let start = Instant::now();
+    let (entity_data, worldspawn_layer_data) = layer_filter::run(entity_data, diagnostics);
+    report.record_timing("layers", start);
+
+    // Animation frames other than +0 are often only in the WADs, so chain every known texture
+    let animation_chains = AnimationChains::new(
+        texture_info.0.keys().map(String::as_str).chain(
+            entity_data
+                .iter()
+                .flat_map(|(entity, _geometry)| entity.brushes.iter())
+                .flat_map(|brush| brush.planes.iter())
+                .map(|plane| plane.texture.as_str()),
+        ),
+    );
 
     // Build engine representation
+    let start = Instant::now();
     let scene_tree = scene_tree::run(
         &config.forge_game_data,
         &config.quarchitect_game_data,
         &config.texture_blacklist,
+        &animation_chains,
         &entity_data,
         &worldspawn_layer_data,
+        diagnostics,
     );
+    report.record_timing("scene_tree", start);
 
-    Ok(scene_tree)
+    report.record_scene_tree(&scene_tree);
+
+    #[cfg(feature = "cache")]
+    if let (Some(cache), Some(key)) = (&config.cache, &cache_key) {
+        let start = Instant::now();
+        if let Err(err) = cache.store(&config.map_file, key, &scene_tree) {
+            diagnostics.warning(diagnostics::DiagnosticKind::CacheWriteFailed {
+                error: err.to_string(),
+            });
+        }
+        report.record_timing("cache_store", start);
+    }
+
+    report.record_diagnostics(diagnostics.report());
+
+    Ok((scene_tree, report))
+}
+
+fn parse_map(
+    file_string: &str,
+    report: &mut BuildReport,
+) -> Result<Vec<map::quake::Entity>, Box<dyn Error>> {
+    let start = Instant::now();
+    let tokens = map::quake::tokenizer::run(file_string.into());
+    let (_token_paths, entities) = map::quake::parser::run(&tokens)?;
+    report.record_timing("parse", start);
+    Ok(entities)
 }
 
+// Prints the token diff between two maps
 pub fn run_diff(file_a: &str, file_b: &str) -> Result<(), Box<dyn Error>> {
+    let diagnostics = Diagnostics::new(Arc::new(diagnostics::StdoutSink::new(
+        diagnostics::Level::Info,
+    )));
+    run_diff_with_diagnostics(file_a, file_b, &diagnostics)
+}
+
+pub fn run_diff_with_diagnostics(
+    file_a: &str,
+    file_b: &str,
+    diagnostics: &Diagnostics,
+) -> Result<(), Box<dyn Error>> {
     let file_a_string = fs::read_to_string(file_a)?;
     let file_b_string = fs::read_to_string(file_b)?;
 
@@ -93,7 +337,61 @@ pub fn run_diff(file_a: &str, file_b: &str) -> Result<(), Box<dyn Error>> {
     let file_b_tokens = map::quake::tokenizer::run(file_b_string);
 
     let diff = map::quake::tokenizer::diff_tokens(&file_a_tokens, &file_b_tokens);
-    println!("{:#?}", diff);
+    diagnostics.info(&format!("{:#?}", diff));
 
     Ok(())
 }
+
+#[derive(Debug, Default)]
+pub struct MapStats {
+    pub entity_count: usize,
+    pub brush_count: usize,
+    pub plane_count: usize,
+    pub classnames: BTreeMap<String, usize>,
+    pub textures: BTreeMap<String, usize>,
+}
+
+pub fn run_stats(map_file: &str) -> Result<MapStats, Box<dyn Error>> {
+    let file_string = fs::read_to_string(map_file)?;
+    let tokens = map::quake::tokenizer::run(file_string);
+    let (_token_paths, entities) = map::quake::parser::run(&tokens)?;
+
+    let mut stats = MapStats {
+        entity_count: entities.len(),
+        ..MapStats::default()
+    };
+
+    for entity in &entities {
+        let classname = entity.get_property("classname").unwrap_or_default();
+        *stats.classnames.entry(classname.into()).or_default() += 1;
+
+        stats.brush_count += entity.brushes.len();
+        for brush in &entity.brushes {
+            stats.plane_count += brush.planes.len();
+            for plane in &brush.planes {
+                *stats.textures.entry(plane.texture.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+pub fn run_lint(
+    map_file: &str,
+    settings: &lint::LintSettings,
+    forge_game_data: &game_data::forge::GameData,
+    texture_info: &TextureInfo,
+) -> Result<Vec<lint::LintFinding>, Box<dyn Error>> {
+    let file_string = fs::read_to_string(map_file)?;
+    let tokens = map::quake::tokenizer::run(file_string);
+    let (token_paths, entities) = map::quake::parser::run(&tokens)?;
+
+    Ok(lint::run(
+        &entities,
+        &token_paths,
+        settings,
+        forge_game_data,
+        texture_info,
+    ))
+}
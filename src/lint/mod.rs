@@ -0,0 +1,637 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+pub use crate::map::quake::parser::{
+    BrushPath, BrushPlanePath, EntityPath, PropertyPath, TokenPath,
+};
+
+use crate::game_data::forge::{ClassType, GameData};
+use crate::geo_builder::brush;
+use crate::map::quake::{Brush, BrushPlane, Entity};
+use crate::scene_tree::TargetLink;
+use crate::{TextureInfo, Vector3};
+
+const PLANE_EPSILON: f32 = 0.001;
+const AREA_EPSILON: f32 = 0.001;
+const GRID_EPSILON: f32 = 0.01;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintCheck {
+    InvalidBrush,
+    MissingTarget,
+    EntityInSolid,
+    MissingClassname,
+    OffGridVertex,
+    MissingTexture,
+    Worldspawn,
+    ClassDefinition,
+}
+
+impl LintCheck {
+    pub const ALL: [LintCheck; 8] = [
+        LintCheck::InvalidBrush,
+        LintCheck::MissingTarget,
+        LintCheck::EntityInSolid,
+        LintCheck::MissingClassname,
+        LintCheck::OffGridVertex,
+        LintCheck::MissingTexture,
+        LintCheck::Worldspawn,
+        LintCheck::ClassDefinition,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintCheck::InvalidBrush => "invalid-brush",
+            LintCheck::MissingTarget => "missing-target",
+            LintCheck::EntityInSolid => "entity-in-solid",
+            LintCheck::MissingClassname => "missing-classname",
+            LintCheck::OffGridVertex => "off-grid-vertex",
+            LintCheck::MissingTexture => "missing-texture",
+            LintCheck::Worldspawn => "worldspawn",
+            LintCheck::ClassDefinition => "class-definition",
+        }
+    }
+}
+
+impl fmt::Display for LintCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for LintCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintCheck::ALL
+            .iter()
+            .find(|check| check.name() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown lint check {}", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintSettings {
+    pub disabled: HashSet<LintCheck>,
+    pub grid_size: f32,
+}
+
+impl LintSettings {
+    pub fn new(disabled: HashSet<LintCheck>, grid_size: f32) -> LintSettings {
+        LintSettings {
+            disabled,
+            grid_size,
+        }
+    }
+
+    pub fn enabled(&self, check: LintCheck) -> bool {
+        !self.disabled.contains(&check)
+    }
+
+    pub fn with_check(mut self, check: LintCheck, enabled: bool) -> LintSettings {
+        if enabled {
+            self.disabled.remove(&check);
+        } else {
+            self.disabled.insert(check);
+        }
+        self
+    }
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        // Every check runs, and vertices are expected on the integer grid
+        let disabled = HashSet::new();
+        let grid_size = 1.0;
+
+        LintSettings {
+            disabled,
+            grid_size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub check: LintCheck,
+    pub path: TokenPath,
+    // One-based line in the map file, if the path refers to a token that exists
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}: {} [{}]", line, self.message, self.check),
+            None => write!(f, "{} [{}]", self.message, self.check),
+        }
+    }
+}
+
+struct Findings<'a> {
+    settings: &'a LintSettings,
+    lines: HashMap<&'a TokenPath, usize>,
+    findings: Vec<LintFinding>,
+}
+
+impl<'a> Findings<'a> {
+    fn push(&mut self, check: LintCheck, path: TokenPath, message: String) {
+        if !self.settings.enabled(check) {
+            return;
+        }
+
+        let line = self.lines.get(&path).map(|token_idx| token_idx + 1);
+        self.findings.push(LintFinding {
+            check,
+            path,
+            line,
+            message,
+        });
+    }
+}
+
+pub(crate) fn run(
+    entities: &[Entity],
+    token_paths: &HashMap<usize, TokenPath>,
+    settings: &LintSettings,
+    forge_game_data: &GameData,
+    texture_info: &TextureInfo,
+) -> Vec<LintFinding> {
+    let mut findings = Findings {
+        settings,
+        lines: token_paths
+            .iter()
+            .map(|(token_idx, path)| (path, *token_idx))
+            .collect(),
+        findings: Vec::new(),
+    };
+
+    check_worldspawn(entities, &mut findings);
+    check_classnames(entities, forge_game_data, &mut findings);
+    check_targets(entities, forge_game_data, &mut findings);
+    check_brushes(entities, texture_info, &mut findings);
+    check_entities_in_solid(entities, &mut findings);
+    check_missing_textures(entities, texture_info, &mut findings);
+
+    let mut findings = findings.findings;
+    findings.sort_by_key(|finding| finding.line);
+    findings
+}
+
+fn check_worldspawn(entities: &[Entity], findings: &mut Findings) {
+    let worldspawn: Vec<usize> = entities
+        .iter()
+        .enumerate()
+        .filter(|(_, entity)| entity.get_property("classname") == Some("worldspawn"))
+        .map(|(entity_idx, _)| entity_idx)
+        .collect();
+
+    if worldspawn.is_empty() {
+        findings.push(
+            LintCheck::Worldspawn,
+            TokenPath::entity(0),
+            "No worldspawn entity".into(),
+        );
+    }
+
+    for entity_idx in worldspawn.iter().skip(1) {
+        findings.push(
+            LintCheck::Worldspawn,
+            TokenPath::entity(*entity_idx),
+            "Duplicate worldspawn entity".into(),
+        );
+    }
+
+    if let Some(entity_idx) = worldspawn.first().filter(|entity_idx| **entity_idx != 0) {
+        findings.push(
+            LintCheck::Worldspawn,
+            TokenPath::entity(*entity_idx),
+            "Worldspawn is not the first entity".into(),
+        );
+    }
+}
+
+fn check_classnames(entities: &[Entity], forge_game_data: &GameData, findings: &mut Findings) {
+    for (entity_idx, entity) in entities.iter().enumerate() {
+        let classname = match entity.get_property("classname") {
+            Some(classname) => classname,
+            None => {
+                findings.push(
+                    LintCheck::MissingClassname,
+                    TokenPath::entity(entity_idx),
+                    "Entity has no classname".into(),
+                );
+                continue;
+            }
+        };
+
+        if classname == "worldspawn" {
+            continue;
+        }
+
+        let definition = forge_game_data
+            .definitions
+            .iter()
+            .find(|definition| definition.class_name == classname);
+
+        let message = match definition.map(|definition| definition.class_type) {
+            None => format!("Unknown classname {}", classname),
+            Some(ClassType::PointClass) if !entity.brushes.is_empty() => {
+                format!("Point class {} has brushes", classname)
+            }
+            Some(ClassType::SolidClass) if entity.brushes.is_empty() => {
+                format!("Solid class {} has no brushes", classname)
+            }
+            Some(ClassType::BaseClass) => format!("Base class {} cannot be placed", classname),
+            _ => continue,
+        };
+
+        findings.push(
+            LintCheck::ClassDefinition,
+            TokenPath::property(entity_idx, "classname".into()),
+            message,
+        );
+    }
+}
+
+// Uses the same keys as the scene tree's target links, including FGD target properties
+fn check_targets(entities: &[Entity], forge_game_data: &GameData, findings: &mut Findings) {
+    let entity_targets: Vec<(Vec<String>, Vec<TargetLink>)> = entities
+        .iter()
+        .map(|entity| {
+            let classname = entity.get_property("classname").unwrap_or_default();
+            let definition = forge_game_data
+                .definitions
+                .iter()
+                .find(|definition| definition.class_name == classname);
+            crate::scene_tree::entity_targets(definition, entity)
+        })
+        .collect();
+
+    let targetnames: HashSet<&str> = entity_targets
+        .iter()
+        .flat_map(|(target_names, _)| target_names.iter().map(String::as_str))
+        .collect();
+
+    for (entity_idx, (_, targets)) in entity_targets.iter().enumerate() {
+        for target in targets {
+            if !targetnames.contains(target.target.as_str()) {
+                findings.push(
+                    LintCheck::MissingTarget,
+                    TokenPath::property(entity_idx, target.key.clone()),
+                    format!("No entity with targetname {:?}", target.target),
+                );
+            }
+        }
+    }
+}
+
+fn check_brushes(entities: &[Entity], texture_info: &TextureInfo, findings: &mut Findings) {
+    for (entity_idx, entity) in entities.iter().enumerate() {
+        for (brush_idx, brush) in entity.brushes.iter().enumerate() {
+            check_brush(entity_idx, brush_idx, brush, texture_info, findings);
+        }
+    }
+}
+
+// Brushes are intersections of half-spaces, so non-convex input shows up as planes that
+// cut the brush away entirely or leave faces without area
+fn check_brush(
+    entity_idx: usize,
+    brush_idx: usize,
+    brush: &Brush,
+    texture_info: &TextureInfo,
+    findings: &mut Findings,
+) {
+    if brush.planes.len() < 4 {
+        findings.push(
+            LintCheck::InvalidBrush,
+            TokenPath::brush(entity_idx, brush_idx),
+            format!(
+                "Brush has {} planes, at least 4 are needed",
+                brush.planes.len()
+            ),
+        );
+        return;
+    }
+
+    for (plane_idx, plane) in brush.planes.iter().enumerate() {
+        let path = TokenPath::brush_plane(entity_idx, brush_idx, plane_idx);
+
+        if (plane.v1 - plane.v0).cross(plane.v2 - plane.v0).length() < PLANE_EPSILON {
+            findings.push(
+                LintCheck::InvalidBrush,
+                path,
+                "Plane points are collinear".into(),
+            );
+            return;
+        }
+
+        for (other_idx, other) in brush.planes.iter().enumerate().take(plane_idx) {
+            if planes_match(plane, other, 1.0) {
+                findings.push(
+                    LintCheck::InvalidBrush,
+                    path.clone(),
+                    format!("Plane duplicates plane {}", other_idx),
+                );
+            } else if planes_match(plane, other, -1.0) {
+                findings.push(
+                    LintCheck::InvalidBrush,
+                    path.clone(),
+                    format!("Plane is coplanar with and opposite to plane {}", other_idx),
+                );
+            }
+        }
+    }
+
+    let geometry = brush::build(texture_info, brush);
+
+    let mut face_count = 0;
+    let mut off_grid = false;
+    for (plane_idx, plane_geometry) in geometry.plane_geometry.iter().enumerate() {
+        let path = TokenPath::brush_plane(entity_idx, brush_idx, plane_idx);
+        let vertices: Vec<Vector3> = plane_geometry
+            .vertices
+            .iter()
+            .map(|vertex| vertex.vertex)
+            .collect();
+
+        if vertices.is_empty() {
+            findings.push(
+                LintCheck::InvalidBrush,
+                path,
+                "Plane does not touch the brush".into(),
+            );
+            continue;
+        }
+
+        if polygon_area(&vertices) < AREA_EPSILON {
+            findings.push(LintCheck::InvalidBrush, path, "Face has zero area".into());
+            continue;
+        }
+
+        face_count += 1;
+
+        // Neighbouring faces share vertices, so only the first off-grid face is reported
+        let grid_size = findings.settings.grid_size;
+        if off_grid {
+            continue;
+        }
+
+        if let Some(vertex) = vertices.iter().find(|vertex| !on_grid(**vertex, grid_size)) {
+            off_grid = true;
+            findings.push(
+                LintCheck::OffGridVertex,
+                path,
+                format!(
+                    "Vertex ({} {} {}) is off the {} unit grid",
+                    vertex.x(),
+                    vertex.y(),
+                    vertex.z(),
+                    grid_size
+                ),
+            );
+        }
+    }
+
+    if face_count < 4 {
+        findings.push(
+            LintCheck::InvalidBrush,
+            TokenPath::brush(entity_idx, brush_idx),
+            "Brush does not enclose a volume".into(),
+        );
+    }
+}
+
+fn planes_match(a: &BrushPlane, b: &BrushPlane, sign: f32) -> bool {
+    (a.normal() - b.normal() * sign).length() < PLANE_EPSILON
+        && (a.dist() - b.dist() * sign).abs() < PLANE_EPSILON
+}
+
+fn polygon_area(vertices: &[Vector3]) -> f32 {
+    if vertices.len() < 3 {
+        return 0.0;
+    }
+
+    let origin = vertices[0];
+    vertices[1..]
+        .windows(2)
+        .map(|edge| (edge[0] - origin).cross(edge[1] - origin).length() * 0.5)
+        .sum()
+}
+
+fn on_grid(vertex: Vector3, grid_size: f32) -> bool {
+    if grid_size <= 0.0 {
+        return true;
+    }
+
+    [vertex.x(), vertex.y(), vertex.z()].iter().all(|comp| {
+        let offset = (comp / grid_size).round() * grid_size - comp;
+        offset.abs() < GRID_EPSILON
+    })
+}
+
+fn check_entities_in_solid(entities: &[Entity], findings: &mut Findings) {
+    let worldspawn = match entities
+        .iter()
+        .find(|entity| entity.get_property("classname") == Some("worldspawn"))
+    {
+        Some(worldspawn) => worldspawn,
+        None => return,
+    };
+
+    for (entity_idx, entity) in entities.iter().enumerate() {
+        if !entity.brushes.is_empty() {
+            continue;
+        }
+
+        let origin = match entity.get_property("origin").and_then(parse_origin) {
+            Some(origin) => origin,
+            None => continue,
+        };
+
+        let inside = worldspawn.brushes.iter().position(|brush| {
+            brush.planes.len() >= 4
+                && brush
+                    .planes
+                    .iter()
+                    .all(|plane| plane.normal().dot(origin) < plane.dist() - PLANE_EPSILON)
+        });
+
+        if let Some(brush_idx) = inside {
+            findings.push(
+                LintCheck::EntityInSolid,
+                TokenPath::property(entity_idx, "origin".into()),
+                format!("Origin is inside worldspawn brush {}", brush_idx),
+            );
+        }
+    }
+}
+
+fn parse_origin(value: &str) -> Option<Vector3> {
+    let comps: Vec<f32> = value
+        .split_whitespace()
+        .map(|comp| comp.parse().ok())
+        .collect::<Option<Vec<f32>>>()?;
+
+    match comps.as_slice() {
+        [x, y, z] => Some(Vector3::new(*x, *y, *z)),
+        _ => None,
+    }
+}
+
+fn check_missing_textures(
+    entities: &[Entity],
+    TextureInfo(texture_info): &TextureInfo,
+    findings: &mut Findings,
+) {
+    // Report each texture once, at its first use
+    let mut reported: HashSet<&str> = HashSet::new();
+
+    for (entity_idx, entity) in entities.iter().enumerate() {
+        for (brush_idx, brush) in entity.brushes.iter().enumerate() {
+            for (plane_idx, plane) in brush.planes.iter().enumerate() {
+                if texture_info.contains_key(&plane.texture)
+                    || !reported.insert(plane.texture.as_str())
+                {
+                    continue;
+                }
+
+                findings.push(
+                    LintCheck::MissingTexture,
+                    TokenPath::brush_plane(entity_idx, brush_idx, plane_idx),
+                    format!("Texture {} is not in the loaded WADs", plane.texture),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Texture;
+
+    fn lint(settings: &LintSettings) -> Vec<LintFinding> {
+        let map = include_str!("test_data/lint.map");
+        let tokens = crate::map::quake::tokenizer::run(map.into());
+        let (token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+
+        let mut textures = HashMap::new();
+        textures.insert("floor".to_string(), Texture::new(64, 64));
+
+        run(
+            &entities,
+            &token_paths,
+            settings,
+            &GameData::default(),
+            &TextureInfo(textures),
+        )
+    }
+
+    #[test]
+    fn lint_map() {
+        let settings = LintSettings::default().with_check(LintCheck::ClassDefinition, false);
+        let findings: Vec<(LintCheck, Option<usize>)> = lint(&settings)
+            .iter()
+            .map(|finding| (finding.check, finding.line))
+            .collect();
+
+        let expected = vec![
+            (LintCheck::OffGridVertex, Some(9)),
+            (LintCheck::MissingTexture, Some(12)),
+            (LintCheck::InvalidBrush, Some(15)),
+            (LintCheck::InvalidBrush, Some(23)),
+            (LintCheck::MissingTarget, Some(34)),
+            (LintCheck::EntityInSolid, Some(35)),
+            (LintCheck::MissingClassname, Some(37)),
+        ];
+
+        assert!(
+            findings == expected,
+            "Lint findings\n{:?}\n!=\n{:?}",
+            findings,
+            expected
+        );
+    }
+
+    #[test]
+    fn target_keys() {
+        use crate::game_data::forge::{Entity as ForgeEntity, Property, PropertyData};
+
+        let map = r#"
+{
+"classname" "worldspawn"
+}
+{
+"classname" "func_button"
+"targetname" "button"
+"target2" "door"
+"killtarget" "gone"
+"destination" "nowhere"
+}
+{
+"classname" "func_door"
+"targetname" "door"
+"target3" "button"
+"target4" "missing"
+}
+"#;
+        let tokens = crate::map::quake::tokenizer::run(map.into());
+        let (token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+
+        let button = ForgeEntity::new(
+            ClassType::SolidClass,
+            vec![],
+            "func_button",
+            "",
+            vec![Property::new(
+                "destination",
+                "Destination",
+                "",
+                PropertyData::TargetDestination,
+            )],
+        );
+        let forge_game_data = GameData::new("test".into(), vec![], vec![button]);
+
+        let findings: Vec<String> = run(
+            &entities,
+            &token_paths,
+            &LintSettings::default(),
+            &forge_game_data,
+            &TextureInfo(HashMap::new()),
+        )
+        .into_iter()
+        .filter(|finding| finding.check == LintCheck::MissingTarget)
+        .map(|finding| finding.message)
+        .collect();
+
+        let expected = vec![
+            "No entity with targetname \"gone\"",
+            "No entity with targetname \"nowhere\"",
+            "No entity with targetname \"missing\"",
+        ];
+
+        assert!(
+            findings == expected,
+            "Missing targets\n{:?}\n!=\n{:?}",
+            findings,
+            expected
+        );
+    }
+
+    #[test]
+    fn disable_checks() {
+        let settings = LintCheck::ALL
+            .iter()
+            .fold(LintSettings::default(), |settings, check| {
+                settings.with_check(*check, false)
+            });
+
+        let findings = lint(&settings);
+        assert!(findings.is_empty(), "Lint findings {:?}", findings);
+    }
+}
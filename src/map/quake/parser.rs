@@ -6,9 +6,9 @@ use super::Entity;
 use super::Token;
 use crate::QuarchitectError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EntityPath {
-    entity_idx: usize,
+    pub entity_idx: usize,
 }
 
 impl EntityPath {
@@ -17,10 +17,10 @@ impl EntityPath {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PropertyPath {
-    entity_idx: usize,
-    property_name: String,
+    pub entity_idx: usize,
+    pub property_name: String,
 }
 
 impl PropertyPath {
@@ -32,10 +32,10 @@ impl PropertyPath {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BrushPath {
-    entity_idx: usize,
-    brush_idx: usize,
+    pub entity_idx: usize,
+    pub brush_idx: usize,
 }
 
 impl BrushPath {
@@ -47,11 +47,11 @@ impl BrushPath {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BrushPlanePath {
-    entity_idx: usize,
-    brush_idx: usize,
-    plane_idx: usize,
+    pub entity_idx: usize,
+    pub brush_idx: usize,
+    pub plane_idx: usize,
 }
 
 impl BrushPlanePath {
@@ -85,7 +85,7 @@ impl ParseScope {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TokenPath {
     Entity(EntityPath),
     Property(PropertyPath),
@@ -94,48 +94,47 @@ pub enum TokenPath {
 }
 
 impl TokenPath {
-    fn entity(entity_idx: usize) -> TokenPath {
+    pub fn entity(entity_idx: usize) -> TokenPath {
         TokenPath::Entity(EntityPath::new(entity_idx))
     }
 
-    fn property(entity_idx: usize, property_name: String) -> TokenPath {
+    pub fn property(entity_idx: usize, property_name: String) -> TokenPath {
         TokenPath::Property(PropertyPath::new(entity_idx, property_name))
     }
 
-    fn brush(entity_idx: usize, brush_idx: usize) -> TokenPath {
+    pub fn brush(entity_idx: usize, brush_idx: usize) -> TokenPath {
         TokenPath::Brush(BrushPath::new(entity_idx, brush_idx))
     }
 
-    fn brush_plane(entity_idx: usize, brush_idx: usize, brush_plane_idx: usize) -> TokenPath {
+    pub fn brush_plane(entity_idx: usize, brush_idx: usize, brush_plane_idx: usize) -> TokenPath {
         TokenPath::BrushPlane(BrushPlanePath::new(entity_idx, brush_idx, brush_plane_idx))
     }
 }
 
-pub fn run(
-    tokens: &[Token],
-) -> Result<(HashMap<&Token, TokenPath>, Vec<Entity>), QuarchitectError> {
-    println!("TODO-3: Rewrite with nom");
+// Token paths are keyed by token index, which is also the line index since tokens are read per line
+pub fn run(tokens: &[Token]) -> Result<(HashMap<usize, TokenPath>, Vec<Entity>), QuarchitectError> {
+    // TODO-3: Rewrite with nom
 
     let mut scope = ParseScope::file();
 
-    let mut token_paths: HashMap<&Token, TokenPath> = HashMap::new();
+    let mut token_paths: HashMap<usize, TokenPath> = HashMap::new();
     let mut entities: Vec<Entity> = Vec::new();
 
-    for token in tokens.iter() {
+    for (token_idx, token) in tokens.iter().enumerate() {
         match token {
             Token::OpenBrace => match scope {
                 ParseScope::File => {
                     let entity_id = entities.len();
                     scope = ParseScope::entity(entity_id);
                     entities.push(Entity::new());
-                    token_paths.insert(token, TokenPath::entity(entity_id));
+                    token_paths.insert(token_idx, TokenPath::entity(entity_id));
                 }
                 ParseScope::Entity(entity_path) => {
                     let brushes = &mut entities.last_mut().unwrap().brushes;
                     let brush_idx = brushes.len();
                     scope = ParseScope::brush(entity_path.entity_idx, brush_idx);
                     brushes.push(Brush::new());
-                    token_paths.insert(token, TokenPath::brush(entity_path.entity_idx, brush_idx));
+                    token_paths.insert(token_idx, TokenPath::brush(entity_path.entity_idx, brush_idx));
                 }
                 ParseScope::Brush(_) => return Err(QuarchitectError("Open brace in brush scope")),
             },
@@ -155,7 +154,7 @@ pub fn run(
                         .properties
                         .insert(k.clone(), v.clone());
                     token_paths.insert(
-                        token,
+                        token_idx,
                         TokenPath::property(entity_path.entity_idx, k.clone()),
                     );
                 }
@@ -178,7 +177,7 @@ pub fn run(
                     let brush_plane = BrushPlane::new(bp.as_str())?;
                     brush_planes.push(brush_plane);
                     token_paths.insert(
-                        token,
+                        token_idx,
                         TokenPath::brush_plane(
                             brush_path.entity_idx,
                             brush_path.brush_idx,
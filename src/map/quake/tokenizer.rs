@@ -41,7 +41,7 @@ impl Property {
 }
 
 pub fn run(file_string: String) -> Vec<Token> {
-    println!("TODO-3: Rewrite with nom");
+    // TODO-3: Rewrite with nom
 
     file_string
         .lines()
@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::diagnostics::{DiagnosticKind, DiagnosticReport};
+use crate::game_data::GameData;
+use crate::geo_builder::entity;
+use crate::map::quake::Entity;
+use crate::scene_tree::{CollisionGeometry, SceneTreeNode, SceneTreeType, VisualGeometry};
+use crate::TextureInfo;
+
+// Totals for every entity sharing a classname, or every brush in a worldspawn layer
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ClassStats {
+    pub entities: usize,
+    pub brushes: usize,
+    pub faces: usize,
+    pub triangles: usize,
+    pub collision_shapes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageTiming {
+    pub stage: String,
+    pub duration: Duration,
+}
+
+// A brush that produced fewer than four faces with area, usually from bad or coincident planes
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegenerateBrush {
+    pub entity_idx: usize,
+    pub brush_idx: usize,
+    pub classname: Option<String>,
+}
+
+// A property value that failed to parse as its declared type, replaced with its Forge default
+// or, for undeclared typed keys, left as a string
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyFallback {
+    pub classname: String,
+    pub property: String,
+    pub value: String,
+    pub expected: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildReport {
+    pub classes: BTreeMap<String, ClassStats>,
+    pub timings: Vec<StageTiming>,
+    pub missing_wads: BTreeSet<String>,
+    pub missing_textures: BTreeSet<String>,
+    pub unknown_classnames: BTreeSet<String>,
+    pub degenerate_brushes: Vec<DegenerateBrush>,
+    pub property_fallbacks: Vec<PropertyFallback>,
+    // WADs, archives and texture images read alongside the map
+    pub source_files: Vec<PathBuf>,
+    // Builds loaded from the cache only carry timings and diagnostics
+    pub from_cache: bool,
+    pub diagnostics: DiagnosticReport,
+}
+
+impl BuildReport {
+    pub fn total_duration(&self) -> Duration {
+        self.timings.iter().map(|timing| timing.duration).sum()
+    }
+
+    pub(crate) fn record_timing(&mut self, stage: &str, start: Instant) {
+        self.timings.push(StageTiming {
+            stage: stage.into(),
+            duration: start.elapsed(),
+        });
+    }
+
+    pub(crate) fn record_map(
+        &mut self,
+        entities: &[Entity],
+        TextureInfo(texture_info): &TextureInfo,
+        quarchitect_game_data: &GameData,
+    ) {
+        for entity in entities {
+            let classname = entity.get_property("classname").unwrap_or_default();
+
+            let class_stats = self.classes.entry(classname.into()).or_default();
+            class_stats.entities += 1;
+            class_stats.brushes += entity.brushes.len();
+
+            for brush in &entity.brushes {
+                class_stats.faces += brush.planes.len();
+
+                for plane in &brush.planes {
+                    if !texture_info.contains_key(&plane.texture) {
+                        self.missing_textures.insert(plane.texture.clone());
+                    }
+                }
+            }
+
+            if !classname.is_empty()
+                && !quarchitect_game_data
+                    .entities
+                    .iter()
+                    .any(|entity_definition| entity_definition.classname == classname)
+            {
+                self.unknown_classnames.insert(classname.into());
+            }
+        }
+    }
+
+    pub(crate) fn record_geometry(
+        &mut self,
+        entities: &[Entity],
+        entity_geometry: &[entity::Geometry],
+    ) {
+        for (entity_idx, (entity, entity_geometry)) in
+            entities.iter().zip(entity_geometry.iter()).enumerate()
+        {
+            for (brush_idx, brush_geometry) in entity_geometry.brush_geometry.iter().enumerate() {
+                let face_count = brush_geometry
+                    .plane_geometry
+                    .iter()
+                    .filter(|plane_geometry| plane_geometry.vertices.len() >= 3)
+                    .count();
+
+                if face_count < 4 {
+                    self.degenerate_brushes.push(DegenerateBrush {
+                        entity_idx,
+                        brush_idx,
+                        classname: entity.get_property("classname").map(String::from),
+                    });
+                }
+            }
+        }
+    }
+
+    pub(crate) fn record_scene_tree(&mut self, scene_tree: &[SceneTreeNode]) {
+        for node in scene_tree {
+            if let SceneTreeType::Actor(actor, children) = &node.data {
+                let class_stats = self.classes.entry(actor.name.clone()).or_default();
+
+                for child in children {
+                    match &child.data {
+                        SceneTreeType::VisualGeometry(VisualGeometry::Mesh(mesh)) => {
+                            class_stats.triangles += mesh
+                                .surfaces
+                                .iter()
+                                .map(|surface| surface.indices.len() / 3)
+                                .sum::<usize>();
+                        }
+                        SceneTreeType::CollisionGeometry(CollisionGeometry::Convex(shapes)) => {
+                            class_stats.collision_shapes += shapes.len()
+                        }
+                        SceneTreeType::CollisionGeometry(CollisionGeometry::Concave(shapes)) => {
+                            class_stats.collision_shapes += shapes.len()
+                        }
+                        _ => (),
+                    }
+                }
+
+                self.record_scene_tree(children);
+            }
+        }
+    }
+
+    pub(crate) fn record_diagnostics(&mut self, diagnostics: DiagnosticReport) {
+        for diagnostic in &diagnostics.diagnostics {
+            match &diagnostic.kind {
+                DiagnosticKind::PropertyFallback {
+                    classname,
+                    property,
+                    value,
+                    expected,
+                } => self.property_fallbacks.push(PropertyFallback {
+                    classname: classname.clone(),
+                    property: property.clone(),
+                    value: value.clone(),
+                    expected: expected.clone(),
+                }),
+                DiagnosticKind::MissingWad { wad } => {
+                    self.missing_wads.insert(wad.clone());
+                }
+                _ => (),
+            }
+        }
+
+        self.diagnostics = diagnostics;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Texture, TextureBlacklist};
+    use std::collections::HashMap;
+
+    #[test]
+    fn record_map_and_geometry() {
+        let map = include_str!("test_data/report.map");
+        let tokens = crate::map::quake::tokenizer::run(map.into());
+        let (_token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+
+        let mut textures = HashMap::new();
+        textures.insert("floor".to_string(), Texture::new(64, 64));
+        let texture_info = TextureInfo(textures);
+
+        let entity_geometry = crate::geo_builder::run(
+            &texture_info,
+            &TextureBlacklist::default(),
+            &entities,
+            &crate::Diagnostics::default(),
+        );
+
+        let mut report = BuildReport::default();
+        report.record_map(&entities, &texture_info, &GameData::default());
+        report.record_geometry(&entities, &entity_geometry);
+
+        let worldspawn = report.classes["worldspawn"];
+        assert!(
+            worldspawn.entities == 1 && worldspawn.brushes == 2 && worldspawn.faces == 9,
+            "Worldspawn stats {:?}",
+            worldspawn
+        );
+
+        let missing: Vec<&String> = report.missing_textures.iter().collect();
+        assert!(missing == vec!["wall"], "Missing textures {:?}", missing);
+
+        let unknown: Vec<&String> = report.unknown_classnames.iter().collect();
+        assert!(
+            unknown == vec!["info_player_start", "worldspawn"],
+            "Unknown classnames {:?}",
+            unknown
+        );
+
+        assert!(
+            report.degenerate_brushes
+                == vec![DegenerateBrush {
+                    entity_idx: 0,
+                    brush_idx: 1,
+                    classname: Some("worldspawn".into()),
+                }],
+            "Degenerate brushes {:?}",
+            report.degenerate_brushes
+        );
+    }
+}
@@ -1,56 +1,69 @@
 use std::collections::HashMap;
 
 pub use types::{
-    Actor, CollisionGeometry, MeshSurface, SceneTreeNode, SceneTreeType, VisualGeometry,
+    Actor, CollisionGeometry, ConcaveCollision, ConvexCollision, MeshSurface, NodePath,
+    SceneTreeNode, SceneTreeType, TargetLink, VisualGeometry, VisualMesh,
 };
 
-use types::{ConcaveCollision, ConvexCollision, VisualMesh};
-
 use crate::{
+    diagnostics::DiagnosticKind,
     game_data::{
-        BrushData, CollisionType, ComponentType, EntityType, GameData, Properties, Property,
-        PropertyApplicationType, VisualType, WorldspawnLayer,
+        forge::{Choice, ChoiceData},
+        BrushData, CollisionLayers, CollisionType, ComponentType, EntityType, GameData, Properties,
+        Property, PropertyApplicationType, VisualType, WorldspawnLayer,
     },
-    geo_builder::{brush, entity},
+    geo_builder::{brush, brush_plane, entity},
     map::quake::Entity,
-    Color, TextureBlacklist, Vector2, Vector3, Vertex,
+    AnimationChains, Color, Diagnostics, TextureBlacklist, TextureSemantics, Vector2, Vector3,
+    Vertex,
 };
 
 mod predicates;
+mod targets;
+mod texture_rules;
 mod types;
 
+pub(crate) use targets::entity_targets;
+use texture_rules::TextureRules;
+
 pub fn run(
     forge_game_data: &crate::game_data::forge::GameData,
     quarchitect_game_data: &crate::game_data::GameData,
     texture_blacklist: &crate::types::TextureBlacklist,
+    animation_chains: &AnimationChains,
     entity_data: &[(Entity, entity::Geometry)],
     worldspawn_layers: &HashMap<String, Vec<brush::Geometry>>,
+    diagnostics: &Diagnostics,
 ) -> Vec<SceneTreeNode> {
-    println!("TODO-2: Implement godot group assignment");
+    // TODO-2: Implement godot group assignment
     // Global '_groups' property, comma-separated string of group names to apply during build
 
-    println!("TODO-1: Make TB groups optional");
+    // TODO-1: Make TB groups optional
     // Should they default to on now they're more user-friendly?
 
-    println!("TODO-1: Implement group node type assignment");
+    // TODO-1: Implement group node type assignment
     // Introduce a new 'group_entity' point class that takes a brush entity classname as a parameter,
     // and causes its parent group to spawn as an instance of that classname instead of as a func_group
 
-    entity_data
+    let texture_rules = TextureRules::new(&quarchitect_game_data.texture_rules);
+
+    let mut scene_tree: Vec<SceneTreeNode> = entity_data
         .iter()
         .take(1)
         .flat_map(build_entity(
             quarchitect_game_data,
             forge_game_data,
             texture_blacklist,
+            &texture_rules,
             entity_data,
-            Vector3::new(0.0, 0.0, 0.0)
+            Vector3::new(0.0, 0.0, 0.0),
+            diagnostics,
         ))
-        .chain(
-            worldspawn_layers
-                .iter()
-                .flat_map(build_worldspawn_layer(quarchitect_game_data)),
-        )
+        .chain(worldspawn_layers.iter().flat_map(build_worldspawn_layer(
+            quarchitect_game_data,
+            &texture_rules,
+            diagnostics,
+        )))
         .chain(
             entity_data
                 .iter()
@@ -60,34 +73,89 @@ pub fn run(
                     quarchitect_game_data,
                     forge_game_data,
                     texture_blacklist,
+                    &texture_rules,
                     entity_data,
-                    Vector3::new(0.0, 0.0, 0.0)
+                    Vector3::new(0.0, 0.0, 0.0),
+                    diagnostics,
                 )),
         )
-        .collect()
+        .collect();
+
+    targets::resolve(&mut scene_tree);
+    apply_animation_chains(&mut scene_tree, animation_chains);
+
+    scene_tree
+}
+
+// Surfaces only know their own texture name when built, so fill in each animation's other frames
+fn apply_animation_chains(scene_tree: &mut [SceneTreeNode], animation_chains: &AnimationChains) {
+    for node in scene_tree.iter_mut() {
+        match &mut node.data {
+            SceneTreeType::Actor(_, children) => apply_animation_chains(children, animation_chains),
+            SceneTreeType::VisualGeometry(VisualGeometry::Mesh(mesh)) => {
+                for surface in mesh.surfaces.iter_mut() {
+                    if let Some(texture) = &surface.texture {
+                        surface.semantics = Some(TextureSemantics::new(texture, animation_chains));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
 }
 
 fn build_entity<'a>(
     quarchitect_game_data: &'a GameData,
     forge_game_data: &'a crate::game_data::forge::GameData,
     texture_blacklist: &'a TextureBlacklist,
+    texture_rules: &'a TextureRules<'a>,
     entity_data: &'a [(Entity, entity::Geometry)],
-    origin: Vector3
+    origin: Vector3,
+    diagnostics: &'a Diagnostics,
 ) -> impl Fn(&'a (Entity, entity::Geometry)) -> Option<SceneTreeNode> + 'a {
     move |(entity, entity_geometry): &(Entity, entity::Geometry)| {
         let mut children: Vec<SceneTreeNode> = Vec::new();
 
+        let classname = match entity.properties.get("classname") {
+            Some(classname) => classname,
+            None => {
+                diagnostics.warning(DiagnosticKind::MissingClassname {
+                    origin: entity.properties.get("origin").cloned(),
+                });
+                return None;
+            }
+        };
+
+        if !quarchitect_game_data
+            .entities
+            .iter()
+            .any(|entity_definition| &entity_definition.classname == classname)
+        {
+            diagnostics.warning(DiagnosticKind::MissingEntityDefinition {
+                classname: classname.clone(),
+            });
+        }
+
         match get_entity_visual_geometry(
             quarchitect_game_data,
             texture_blacklist,
+            texture_rules,
             entity,
             entity_geometry,
+            diagnostics,
         ) {
             VisualGeometry::None => (),
             v => children.push(SceneTreeNode::visual_geometry(entity_geometry.center, v)),
         }
 
-        match get_entity_collision_geometry(quarchitect_game_data, entity, entity_geometry) {
+        match get_entity_collision_geometry(
+            quarchitect_game_data,
+            texture_blacklist,
+            texture_rules,
+            entity,
+            entity_geometry,
+            diagnostics,
+        ) {
             CollisionGeometry::None => (),
             c => children.push(SceneTreeNode::collision_geometry(entity_geometry.center, c)),
         }
@@ -104,27 +172,50 @@ fn build_entity<'a>(
                 quarchitect_game_data,
                 forge_game_data,
                 texture_blacklist,
+                texture_rules,
                 entity_data,
-                -entity_geometry.center
+                -entity_geometry.center,
+                diagnostics,
             ))
             .collect();
 
         children.append(&mut child_entities);
 
-        Some(SceneTreeNode::entity(
-            entity.properties.get("classname").unwrap().clone(),
+        let forge_entity = entity.properties.get("classname").and_then(|classname| {
+            forge_game_data
+                .definitions
+                .iter()
+                .find(|forge_entity| &forge_entity.class_name == classname)
+        });
+        let (target_names, targets) = targets::entity_targets(forge_entity, entity);
+        let declared_properties = forge_entity
+            .iter()
+            .flat_map(|forge_entity| forge_entity.properties.iter())
+            .map(|forge_property| forge_property.name.clone())
+            .collect();
+
+        let node = SceneTreeNode::entity(
+            classname.clone(),
             origin + entity_geometry.center,
             get_entity_type(quarchitect_game_data, &entity),
             get_entity_component_class(quarchitect_game_data, &entity),
             get_entity_property_application_type(quarchitect_game_data, &entity),
-            get_entity_properties(forge_game_data, &entity),
+            get_entity_properties(forge_game_data, &entity, diagnostics),
             children,
-        ))
+        );
+
+        Some(
+            node.with_targets(target_names, targets)
+                .with_raw_properties(entity.properties.clone())
+                .with_declared_properties(declared_properties),
+        )
     }
 }
 
 fn build_worldspawn_layer<'a>(
     quarchitect_game_data: &'a crate::game_data::GameData,
+    texture_rules: &'a TextureRules<'a>,
+    diagnostics: &'a Diagnostics,
 ) -> impl Fn((&String, &Vec<brush::Geometry>)) -> Option<SceneTreeNode> + 'a {
     move |(layer_texture, brush_geometry): (&String, &Vec<brush::Geometry>)| {
         let layer_data: Vec<&WorldspawnLayer> = quarchitect_game_data
@@ -149,8 +240,12 @@ fn build_worldspawn_layer<'a>(
                 ComponentType::None => None,
             };
 
-            let visual_geometry =
-                get_worldspawn_layer_visual_geometry(layer_data, layer_texture, brush_geometry);
+            let visual_geometry = get_worldspawn_layer_visual_geometry(
+                layer_data,
+                layer_texture,
+                brush_geometry,
+                texture_rules,
+            );
             match visual_geometry {
                 VisualGeometry::None => (),
                 _ => children.push(SceneTreeNode::visual_geometry(
@@ -159,20 +254,28 @@ fn build_worldspawn_layer<'a>(
                 )),
             }
 
-            let collision_geometry =
-                get_worldspawn_layer_collision_geometry(layer_data, brush_geometry);
+            let collision_geometry = get_worldspawn_layer_collision_geometry(
+                layer_data,
+                brush_geometry,
+                texture_rules,
+                diagnostics,
+            );
             match collision_geometry {
                 CollisionGeometry::None => (),
                 _ => children.push(SceneTreeNode::collision_geometry(
                     Vector3::default(),
-                    get_worldspawn_layer_collision_geometry(layer_data, brush_geometry),
+                    collision_geometry,
                 )),
             }
-        } else {
-            println!(
-                "Worldspawn layer data for texture {:?} is not a singleton",
+        } else if layer_data.is_empty() {
+            diagnostics.debug(&format!(
+                "No worldspawn layer data for texture {:?}",
                 layer_texture
-            );
+            ));
+        } else {
+            diagnostics.warning(DiagnosticKind::AmbiguousWorldspawnLayer {
+                texture: layer_texture.clone(),
+            });
         }
 
         if children.is_empty() {
@@ -272,6 +375,7 @@ fn get_entity_property_application_type(
 fn get_entity_properties(
     forge_game_data: &crate::game_data::forge::GameData,
     entity: &Entity,
+    diagnostics: &Diagnostics,
 ) -> Properties {
     let classname = entity.properties.get("classname");
 
@@ -280,72 +384,77 @@ fn get_entity_properties(
         .iter()
         .find(|forge_entity| Some(&forge_entity.class_name) == classname);
 
-    let forge_entity = match forge_entity {
-        Some(forge_entity) => forge_entity,
-        None => {
-            eprintln!("Failed to get forge entity for classname {:?}", classname);
-            return Properties::default();
-        }
-    };
+    if forge_entity.is_none() {
+        diagnostics.warning(DiagnosticKind::MissingForgeDefinition {
+            classname: classname.cloned().unwrap_or_default(),
+        });
+    }
 
-    let mut properties: HashMap<String, Property> = HashMap::new();
-
-    let mut add_internal_property_integer = |property_name: &str| {
-        if entity.properties.contains_key(property_name) {
-            properties.insert(
-                property_name.into(),
-                crate::game_data::Property::Integer(
-                    entity
-                        .properties
-                        .get(property_name)
-                        .unwrap()
-                        .parse::<i32>()
-                        .unwrap(),
-                ),
-            );
-        }
+    let mismatch = |key: &str, value: &String, expected: &str| {
+        diagnostics.warning(DiagnosticKind::PropertyFallback {
+            classname: classname.cloned().unwrap_or_default(),
+            property: key.into(),
+            value: value.clone(),
+            expected: expected.into(),
+        })
     };
 
-    add_internal_property_integer("_tb_id");
-    add_internal_property_integer("_tb_group");
+    // Keys the FGD doesn't declare are kept as their raw strings
+    let mut properties: HashMap<String, Property> = entity
+        .properties
+        .iter()
+        .map(|(key, value)| (key.clone(), Property::String(value.clone())))
+        .collect();
 
-    let mut add_internal_property_string = |property_name: &str| {
-        if entity.properties.contains_key(property_name) {
-            properties.insert(
-                property_name.into(),
-                crate::game_data::Property::String(
-                    entity.properties.get(property_name).unwrap().clone(),
-                ),
-            );
+    // TrenchBroom group and layer ids stay strings if they don't parse
+    for key in &["_tb_id", "_tb_group", "_tb_layer"] {
+        if let Some(value) = entity.properties.get(*key) {
+            match value.parse::<i32>() {
+                Ok(id) => {
+                    properties.insert(key.to_string(), Property::Integer(id));
+                }
+                Err(_) => mismatch(key, value, "integer"),
+            }
         }
-    };
+    }
 
-    add_internal_property_string("_tb_type");
-    add_internal_property_string("_tb_name");
+    let forge_properties = forge_entity
+        .iter()
+        .flat_map(|forge_entity| forge_entity.properties.iter());
 
-    for forge_property in &forge_entity.properties {
+    for forge_property in forge_properties {
         let key = &forge_property.name;
         let value = entity.properties.get(key);
 
+        let fallback = |value: &String, expected: &str| mismatch(key, value, expected);
+
         let value = match &forge_property.data {
             crate::game_data::forge::PropertyData::Integer(default) => {
-                let value = value.map(|value| value.parse::<i32>());
-                Property::Integer(value.unwrap_or(Ok(*default)).unwrap_or(*default))
+                Property::Integer(parse_property(
+                    value,
+                    *default,
+                    "integer",
+                    |value| value.parse().ok(),
+                    &fallback,
+                ))
             }
             crate::game_data::forge::PropertyData::Float(default) => {
-                let value = value.map(|value| value.parse::<f32>());
-                Property::Float(value.unwrap_or(Ok(*default)).unwrap_or(*default))
+                Property::Float(parse_property(
+                    value,
+                    *default,
+                    "float",
+                    |value| value.parse().ok(),
+                    &fallback,
+                ))
             }
             crate::game_data::forge::PropertyData::Vector3(default) => {
-                let vector3 = match value {
-                    Some(value) => match parse_vector3_property(value) {
-                        Some(value) => value,
-                        None => *default,
-                    },
-                    None => *default,
-                };
-
-                Property::Vector3(vector3)
+                Property::Vector3(parse_property(
+                    value,
+                    *default,
+                    "vector",
+                    parse_vector3_property,
+                    &fallback,
+                ))
             }
             crate::game_data::forge::PropertyData::String(default) => {
                 let value = match value {
@@ -355,40 +464,44 @@ fn get_entity_properties(
                 Property::String(value.clone())
             }
             crate::game_data::forge::PropertyData::Color(default) => {
-                let color = match value {
-                    Some(value) => match parse_color_property(value) {
-                        Some(value) => value,
-                        None => *default,
-                    },
-                    None => *default,
-                };
-
-                Property::Color(color)
+                Property::Color(parse_property(
+                    value,
+                    *default,
+                    "color",
+                    parse_color_property,
+                    &fallback,
+                ))
             }
-            crate::game_data::forge::PropertyData::Choices(_, default) => {
-                let value = match value {
-                    Some(value) => match value.parse::<i32>() {
-                        Ok(value) => value,
-                        Err(_) => *default,
-                    },
-                    None => *default,
+            crate::game_data::forge::PropertyData::Choices(choices, default) => {
+                // The default is an index into the declared choices
+                let default = match choices.get(*default as usize).or_else(|| choices.first()) {
+                    Some(choice) => choice.value.clone(),
+                    None => continue,
                 };
 
-                Property::Choices(value)
+                Property::Choices(parse_property(
+                    value,
+                    default,
+                    "choice",
+                    |value| parse_choice_property(choices, value),
+                    &fallback,
+                ))
             }
             crate::game_data::forge::PropertyData::Flags(_, default) => {
-                let value = match value {
-                    Some(value) => match value.parse::<i32>() {
-                        Ok(value) => value,
-                        Err(_) => *default,
-                    },
-                    None => *default,
-                };
-
-                Property::Choices(value)
+                Property::Flags(parse_property(
+                    value,
+                    *default,
+                    "flags",
+                    |value| value.parse().ok(),
+                    &fallback,
+                ))
+            }
+            crate::game_data::forge::PropertyData::TargetSource => {
+                Property::TargetSource(value.cloned().unwrap_or_default())
+            }
+            crate::game_data::forge::PropertyData::TargetDestination => {
+                Property::TargetDestination(value.cloned().unwrap_or_default())
             }
-            crate::game_data::forge::PropertyData::TargetSource => Property::TargetSource,
-            crate::game_data::forge::PropertyData::TargetDestination => Property::TargetDestination,
         };
 
         properties.insert(key.clone(), value);
@@ -397,6 +510,38 @@ fn get_entity_properties(
     Properties::new(properties)
 }
 
+// Parse a property value, reporting malformed values before falling back to the default
+fn parse_property<T>(
+    value: Option<&String>,
+    default: T,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<T>,
+    fallback: &dyn Fn(&String, &str),
+) -> T {
+    match value {
+        Some(value) => match parse(value) {
+            Some(value) => value,
+            None => {
+                fallback(value, expected);
+                default
+            }
+        },
+        None => default,
+    }
+}
+
+fn parse_choice_property(choices: &[Choice], value: &str) -> Option<ChoiceData> {
+    choices
+        .iter()
+        .map(|choice| &choice.value)
+        .find(|choice| match choice {
+            ChoiceData::Integer(choice) => value.parse::<i32>().ok() == Some(*choice),
+            ChoiceData::Float(choice) => value.parse::<f32>().ok() == Some(*choice),
+            ChoiceData::String(choice) => value == choice,
+        })
+        .cloned()
+}
+
 fn parse_vector3_property(value: &str) -> Option<Vector3> {
     let mut comps = value.split(' ');
 
@@ -452,8 +597,10 @@ fn parse_float(comps: &mut dyn std::iter::Iterator<Item = &str>) -> Option<f32>
 fn get_entity_visual_geometry(
     quarchitect_game_data: &crate::game_data::GameData,
     texture_blacklist: &crate::types::TextureBlacklist,
+    texture_rules: &TextureRules,
     entity: &Entity,
     entity_geometry: &entity::Geometry,
+    diagnostics: &Diagnostics,
 ) -> VisualGeometry {
     let classname = entity.properties.get("classname");
 
@@ -469,16 +616,20 @@ fn get_entity_visual_geometry(
                     crate::game_data::EntityData::Point(_) => return VisualGeometry::None,
                     crate::game_data::EntityData::Brush(_, brush_data) => brush_data,
                 },
-                None => {
-                    println!("No entity definition for classname {:?}", classname);
-                    return VisualGeometry::None;
-                }
+                None => return VisualGeometry::None,
             }
         }
-        None => panic!("No classname in entity"),
+        None => return VisualGeometry::None,
     };
 
-    get_brush_entity_visual_geometry(entity, entity_geometry, brush_data, texture_blacklist)
+    get_brush_entity_visual_geometry(
+        entity,
+        entity_geometry,
+        brush_data,
+        texture_blacklist,
+        texture_rules,
+        diagnostics,
+    )
 }
 
 fn get_brush_entity_visual_geometry(
@@ -486,8 +637,10 @@ fn get_brush_entity_visual_geometry(
     entity_geometry: &entity::Geometry,
     brush_data: &BrushData,
     texture_blacklist: &TextureBlacklist,
+    texture_rules: &TextureRules,
+    diagnostics: &Diagnostics,
 ) -> VisualGeometry {
-    println!("Visual type: {:?}", brush_data.visual_type);
+    diagnostics.debug(&format!("Visual type: {:?}", brush_data.visual_type));
     match brush_data.visual_type {
         crate::game_data::VisualType::Mesh => {
             // Collect brushes with this texture
@@ -521,6 +674,7 @@ fn get_brush_entity_visual_geometry(
                 .flat_map(build_brush_entity_texture_surface(
                     entity_geometry,
                     texture_blacklist,
+                    texture_rules,
                 ))
                 .collect();
 
@@ -540,12 +694,14 @@ fn get_worldspawn_layer_visual_geometry(
     worldspawn_layer: &WorldspawnLayer,
     texture: &str,
     brush_geometry: &[crate::geo_builder::brush::Geometry],
+    texture_rules: &TextureRules,
 ) -> VisualGeometry {
     match worldspawn_layer.visual_type {
         VisualType::None => VisualGeometry::None,
         VisualType::Mesh => match build_brush_entity_texture_surface(
             &entity::Geometry::new(Vector3::default(), brush_geometry.to_vec()),
             &TextureBlacklist::default(),
+            texture_rules,
         )(Some(texture.to_string()))
         {
             Some(mesh_surface) => VisualGeometry::Mesh(VisualMesh::new(vec![mesh_surface])),
@@ -557,27 +713,47 @@ fn get_worldspawn_layer_visual_geometry(
 fn get_worldspawn_layer_collision_geometry(
     worldspawn_layer: &WorldspawnLayer,
     brush_geometry: &[crate::geo_builder::brush::Geometry],
+    texture_rules: &TextureRules,
+    diagnostics: &Diagnostics,
 ) -> CollisionGeometry {
     let layer_entity_geometry = entity::Geometry::new(Vector3::default(), brush_geometry.to_vec());
 
     match worldspawn_layer.collision_type {
         CollisionType::None => CollisionGeometry::None,
-        CollisionType::Convex => get_entity_convex_collision(&layer_entity_geometry),
-        CollisionType::Concave => get_entity_concave_collision(&layer_entity_geometry),
+        CollisionType::Convex => get_entity_convex_collision(
+            &layer_entity_geometry,
+            &TextureBlacklist::default(),
+            texture_rules,
+        ),
+        CollisionType::Concave => get_entity_concave_collision(
+            &layer_entity_geometry,
+            &TextureBlacklist::default(),
+            texture_rules,
+            diagnostics,
+        ),
     }
 }
 
 fn build_brush_entity_texture_surface<'a>(
     entity_geometry: &'a entity::Geometry,
     texture_blacklist: &'a TextureBlacklist,
+    texture_rules: &'a TextureRules<'a>,
 ) -> impl Fn(Option<String>) -> Option<MeshSurface> + 'a {
     move |texture| {
-        let (vertices, indices) = gather_entity_geometry(
-            entity_geometry,
-            Some(&predicates::brush::not_blacklisted(&texture_blacklist)),
-            Some(&predicates::plane::has_texture(&texture)),
-            Some(&predicates::vertex::unique),
-        );
+        let not_blacklisted = predicates::brush::not_blacklisted(&texture_blacklist);
+        let (vertices, indices) = {
+            let has_texture = predicates::plane::has_texture(&texture);
+            gather_entity_geometry(
+                entity_geometry,
+                Some(&|brush_geometry: &&brush::Geometry| {
+                    not_blacklisted(brush_geometry) && texture_rules.brush_visible(brush_geometry)
+                }),
+                Some(&|plane_geometry: &&brush_plane::Geometry| {
+                    has_texture(plane_geometry) && texture_rules.plane_visible(plane_geometry)
+                }),
+                Some(&predicates::vertex::unique),
+            )
+        };
 
         if vertices.is_empty() {
             return None;
@@ -587,10 +763,11 @@ fn build_brush_entity_texture_surface<'a>(
         let normals: Vec<Vector3> = vertices.iter().map(|vertex| vertex.normal).collect();
         let tangents: Vec<(Vector3, f32)> = vertices.iter().map(|vertex| vertex.tangent).collect();
         let uvs: Option<Vec<Vector2>> = vertices.iter().map(|vertex| vertex.uv).collect();
+        let uv2: Option<Vec<Vector2>> = vertices.iter().map(|vertex| vertex.uv2).collect();
         let colors: Option<Vec<Color>> = vertices.iter().map(|vertex| vertex.color).collect();
 
         let mesh_surface =
-            MeshSurface::new(texture, verts, normals, tangents, uvs, colors, indices);
+            MeshSurface::new(texture, verts, normals, tangents, uvs, uv2, colors, indices);
 
         Some(mesh_surface)
     }
@@ -598,8 +775,11 @@ fn build_brush_entity_texture_surface<'a>(
 
 fn get_entity_collision_geometry(
     quarchitect_game_data: &crate::game_data::GameData,
+    texture_blacklist: &TextureBlacklist,
+    texture_rules: &TextureRules,
     entity: &Entity,
     entity_geometry: &entity::Geometry,
+    diagnostics: &Diagnostics,
 ) -> CollisionGeometry {
     let classname = entity.properties.get("classname");
     let brush_data = match classname {
@@ -614,26 +794,40 @@ fn get_entity_collision_geometry(
                     crate::game_data::EntityData::Point(_) => return CollisionGeometry::None,
                     crate::game_data::EntityData::Brush(_, brush_data) => brush_data,
                 },
-                None => {
-                    println!("No entity definition for classname {:?}", classname);
-                    return CollisionGeometry::None;
-                }
+                None => return CollisionGeometry::None,
             }
         }
-        None => panic!("No classname in entity"),
+        None => return CollisionGeometry::None,
     };
 
     match brush_data.collision_type {
-        crate::game_data::CollisionType::Convex => get_entity_convex_collision(entity_geometry),
-        crate::game_data::CollisionType::Concave => get_entity_concave_collision(entity_geometry),
+        crate::game_data::CollisionType::Convex => {
+            get_entity_convex_collision(entity_geometry, texture_blacklist, texture_rules)
+        }
+        crate::game_data::CollisionType::Concave => get_entity_concave_collision(
+            entity_geometry,
+            texture_blacklist,
+            texture_rules,
+            diagnostics,
+        ),
         crate::game_data::CollisionType::None => CollisionGeometry::None,
     }
 }
 
-fn get_entity_convex_collision(entity_geometry: &entity::Geometry) -> CollisionGeometry {
+// Convex shapes are whole brushes, so only brush blacklisting can remove them
+fn get_entity_convex_collision(
+    entity_geometry: &entity::Geometry,
+    texture_blacklist: &TextureBlacklist,
+    texture_rules: &TextureRules,
+) -> CollisionGeometry {
+    let keeps_collision = predicates::brush::keeps_collision(texture_blacklist);
+
     let convex_shapes: Vec<ConvexCollision> = entity_geometry
         .brush_geometry
         .iter()
+        .filter(|brush_geometry| {
+            keeps_collision(brush_geometry) && texture_rules.brush_collides(brush_geometry)
+        })
         .map(|brush_geometry| {
             let points = brush_geometry
                 .plane_geometry
@@ -664,31 +858,98 @@ fn get_entity_convex_collision(entity_geometry: &entity::Geometry) -> CollisionG
                 .collect();
 
             ConvexCollision::new(brush_geometry.center, points)
+                .with_layers(texture_rules.convex_layers(brush_geometry))
         })
         .collect();
 
     CollisionGeometry::convex(convex_shapes)
 }
 
-fn get_entity_concave_collision(entity_geometry: &entity::Geometry) -> CollisionGeometry {
-    println!("Gathering concave collision geometry");
-    let (vertices, indices) = gather_entity_geometry(
-        entity_geometry,
-        None,
-        None,
-        Some(&predicates::vertex::unique_position),
-    );
-    let collision_vertices: Vec<Vector3> = vertices.iter().map(|vertex| vertex.vertex).collect();
+// One shape per set of collision layers, since each face can carry its own
+fn get_entity_concave_collision(
+    entity_geometry: &entity::Geometry,
+    texture_blacklist: &TextureBlacklist,
+    texture_rules: &TextureRules,
+    diagnostics: &Diagnostics,
+) -> CollisionGeometry {
+    diagnostics.trace("Gathering concave collision geometry");
+    let keeps_brush = predicates::brush::keeps_collision(texture_blacklist);
+    let keeps_plane = predicates::plane::keeps_collision(texture_blacklist);
+    let brush_collides = |brush_geometry: &&brush::Geometry| {
+        keeps_brush(brush_geometry) && texture_rules.brush_collides(brush_geometry)
+    };
+    let plane_collides = |plane_geometry: &&brush_plane::Geometry| {
+        keeps_plane(plane_geometry) && texture_rules.plane_collides(plane_geometry)
+    };
 
-    if collision_vertices.is_empty() {
-        return CollisionGeometry::None;
+    let mut layer_sets: Vec<Option<CollisionLayers>> = Vec::new();
+    for brush_geometry in entity_geometry.brush_geometry.iter().filter(brush_collides) {
+        let brush_layers = texture_rules.brush_layers(brush_geometry);
+        let layers: Vec<Option<CollisionLayers>> = match brush_layers {
+            Some(_) => vec![brush_layers],
+            None => brush_geometry
+                .plane_geometry
+                .iter()
+                .filter(plane_collides)
+                .map(|plane_geometry| texture_rules.plane_layers(plane_geometry))
+                .collect(),
+        };
+
+        for layers in layers {
+            if !layer_sets.contains(&layers) {
+                layer_sets.push(layers);
+            }
+        }
     }
 
-    let concave_shapes = vec![ConcaveCollision::new(
-        entity_geometry.center,
-        collision_vertices,
-        indices,
-    )];
+    let concave_shapes: Vec<ConcaveCollision> = layer_sets
+        .into_iter()
+        .flat_map(|layers| {
+            // Brushes whose rule sets layers for every face, then the faces of the rest
+            let (mut vertices, mut indices) = gather_entity_geometry(
+                entity_geometry,
+                Some(&|brush_geometry: &&brush::Geometry| {
+                    layers.is_some()
+                        && brush_collides(brush_geometry)
+                        && texture_rules.brush_layers(brush_geometry) == layers
+                }),
+                Some(&plane_collides),
+                Some(&predicates::vertex::unique_position),
+            );
+            let (face_vertices, face_indices) = gather_entity_geometry(
+                entity_geometry,
+                Some(&|brush_geometry: &&brush::Geometry| {
+                    brush_collides(brush_geometry)
+                        && texture_rules.brush_layers(brush_geometry).is_none()
+                }),
+                Some(&|plane_geometry: &&brush_plane::Geometry| {
+                    plane_collides(plane_geometry)
+                        && texture_rules.plane_layers(plane_geometry) == layers
+                }),
+                Some(&predicates::vertex::unique_position),
+            );
+
+            let offset = vertices.len();
+            indices.extend(face_indices.into_iter().map(|index| index + offset));
+            vertices.extend(face_vertices);
+
+            let collision_vertices: Vec<Vector3> =
+                vertices.iter().map(|vertex| vertex.vertex).collect();
+
+            if collision_vertices.is_empty() {
+                return None;
+            }
+
+            Some(
+                ConcaveCollision::new(entity_geometry.center, collision_vertices, indices)
+                    .with_layers(layers),
+            )
+        })
+        .collect();
+
+    if concave_shapes.is_empty() {
+        return CollisionGeometry::None;
+    }
 
     CollisionGeometry::concave(concave_shapes)
 }
@@ -813,3 +1074,419 @@ fn filter_vertices<'a>(
 
     (new_vertices, indices)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::DiagnosticReport;
+    use crate::game_data::forge::{self, PropertyData};
+    use crate::game_data::{TextureRule, TextureRuleScope};
+
+    fn forge_game_data() -> forge::GameData {
+        let definition = forge::Entity {
+            class_type: forge::ClassType::SolidClass,
+            class_name: "func_door".into(),
+            properties: vec![
+                forge::Property::new("speed", "Speed", "", PropertyData::Integer(100)),
+                forge::Property::new(
+                    "spawnflags",
+                    "Flags",
+                    "",
+                    PropertyData::Flags(vec!["start_open".into(), "toggle".into()], 0),
+                ),
+                forge::Property::new(
+                    "sounds",
+                    "Sounds",
+                    "",
+                    PropertyData::Choices(
+                        vec![
+                            Choice::string("Stone", "stone"),
+                            Choice::string("Metal", "metal"),
+                        ],
+                        0,
+                    ),
+                ),
+            ],
+            ..forge::Entity::default()
+        };
+
+        let mut forge_game_data = forge::GameData::default();
+        forge_game_data.definitions.push(definition);
+        forge_game_data
+    }
+
+    fn entity_properties(properties: &[(&str, &str)]) -> (Properties, DiagnosticReport) {
+        let mut entity = Entity::new();
+        for (key, value) in properties {
+            entity.properties.insert(key.to_string(), value.to_string());
+        }
+
+        let diagnostics = Diagnostics::default();
+        let properties = get_entity_properties(&forge_game_data(), &entity, &diagnostics);
+        (properties, diagnostics.report())
+    }
+
+    #[test]
+    fn typed_properties() {
+        let (Properties(properties), report) = entity_properties(&[
+            ("classname", "func_door"),
+            ("spawnflags", "2"),
+            ("sounds", "metal"),
+            ("_tb_id", "3"),
+            ("wait", "-1"),
+        ]);
+
+        assert!(
+            properties["spawnflags"] == Property::Flags(2),
+            "Flags {:?}",
+            properties["spawnflags"]
+        );
+        assert!(
+            properties["sounds"] == Property::Choices(ChoiceData::String("metal".into())),
+            "Choices {:?}",
+            properties["sounds"]
+        );
+        assert!(
+            properties["speed"] == Property::Integer(100),
+            "Default {:?}",
+            properties["speed"]
+        );
+        assert!(
+            properties["_tb_id"] == Property::Integer(3),
+            "TrenchBroom id {:?}",
+            properties["_tb_id"]
+        );
+        assert!(
+            properties["wait"] == Property::String("-1".into()),
+            "Undeclared {:?}",
+            properties["wait"]
+        );
+        assert!(
+            report.diagnostics.is_empty(),
+            "Diagnostics {:?}",
+            report.diagnostics
+        );
+    }
+
+    #[test]
+    fn type_mismatches() {
+        let (Properties(properties), report) = entity_properties(&[
+            ("classname", "func_door"),
+            ("speed", "fast"),
+            ("sounds", "wood"),
+            ("_tb_id", "abc"),
+        ]);
+
+        assert!(
+            properties["speed"] == Property::Integer(100),
+            "Fallback {:?}",
+            properties["speed"]
+        );
+        assert!(
+            properties["sounds"] == Property::Choices(ChoiceData::String("stone".into())),
+            "Choice fallback {:?}",
+            properties["sounds"]
+        );
+        assert!(
+            properties["_tb_id"] == Property::String("abc".into()),
+            "TrenchBroom id {:?}",
+            properties["_tb_id"]
+        );
+
+        let mut mismatches: Vec<String> = report
+            .warnings()
+            .filter_map(|diagnostic| match &diagnostic.kind {
+                DiagnosticKind::PropertyFallback { property, .. } => Some(property.clone()),
+                _ => None,
+            })
+            .collect();
+        mismatches.sort();
+
+        assert!(
+            mismatches == vec!["_tb_id", "sounds", "speed"],
+            "Mismatches {:?}",
+            mismatches
+        );
+    }
+
+    #[test]
+    fn missing_classname() {
+        let map = format!("{}/src/lint/test_data/lint.map", env!("CARGO_MANIFEST_DIR"));
+
+        let mut textures = HashMap::new();
+        textures.insert("floor".to_string(), crate::Texture::new(64, 64));
+
+        let config = crate::Config::new(
+            &map,
+            crate::TextureInfo(textures),
+            TextureBlacklist::default(),
+            forge::GameData::default(),
+            GameData::default(),
+        );
+        let (scene_tree, report) = crate::run_with_report(config).unwrap();
+
+        let skipped: Vec<&DiagnosticKind> = report
+            .diagnostics
+            .warnings()
+            .map(|diagnostic| &diagnostic.kind)
+            .filter(|kind| matches!(kind, DiagnosticKind::MissingClassname { .. }))
+            .collect();
+        assert!(
+            skipped
+                == vec![&DiagnosticKind::MissingClassname {
+                    origin: Some("0 0 32".into())
+                }],
+            "Missing classname warnings {:?}",
+            skipped
+        );
+
+        let names: Vec<&str> = scene_tree
+            .iter()
+            .filter_map(|node| match &node.data {
+                SceneTreeType::Actor(actor, _) => Some(actor.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            names == vec!["worldspawn", "trigger_relay"],
+            "Scene tree actors {:?}",
+            names
+        );
+    }
+
+    #[test]
+    fn blacklisted_collision() {
+        let map = r#"{
+"classname" "worldspawn"
+{
+( -16 -16 -16 ) ( -16 -15 -16 ) ( -16 -16 -15 ) clip 0 0 0 1 1
+( -16 -16 -16 ) ( -16 -16 -15 ) ( -15 -16 -16 ) clip 0 0 0 1 1
+( -16 -16 -16 ) ( -15 -16 -16 ) ( -16 -15 -16 ) clip 0 0 0 1 1
+( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) clip 0 0 0 1 1
+( 0 0 0 ) ( 1 0 0 ) ( 0 0 1 ) clip 0 0 0 1 1
+( 0 0 0 ) ( 0 0 1 ) ( 0 1 0 ) clip 0 0 0 1 1
+}
+{
+( 0 0 0 ) ( 0 1 0 ) ( 0 0 1 ) hint 0 0 0 1 1
+( 0 0 0 ) ( 0 0 1 ) ( 1 0 0 ) hint 0 0 0 1 1
+( 0 0 0 ) ( 1 0 0 ) ( 0 1 0 ) hint 0 0 0 1 1
+( 16 16 16 ) ( 16 17 16 ) ( 17 16 16 ) hint 0 0 0 1 1
+( 16 16 16 ) ( 17 16 16 ) ( 16 16 17 ) hint 0 0 0 1 1
+( 16 16 16 ) ( 16 16 17 ) ( 16 17 16 ) hint 0 0 0 1 1
+}
+}
+"#;
+        let tokens = crate::map::quake::tokenizer::run(map.into());
+        let (_token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+
+        let mut textures = HashMap::new();
+        textures.insert("clip".to_string(), crate::Texture::new(16, 16));
+        textures.insert("hint".to_string(), crate::Texture::new(16, 16));
+
+        let texture_blacklist = TextureBlacklist::preset(crate::TexturePreset::Quake);
+        let entity_geometry = crate::geo_builder::run(
+            &crate::TextureInfo(textures),
+            &texture_blacklist,
+            &entities,
+            &Diagnostics::default(),
+        );
+
+        // Both brushes are hidden, but only the clip brush still collides
+        let visual = get_brush_entity_visual_geometry(
+            &entities[0],
+            &entity_geometry[0],
+            &BrushData::new(VisualType::Mesh, CollisionType::Convex),
+            &texture_blacklist,
+            &TextureRules::default(),
+            &Diagnostics::default(),
+        );
+        assert!(
+            matches!(visual, VisualGeometry::None),
+            "Visual geometry {:?}",
+            visual
+        );
+
+        match get_entity_convex_collision(
+            &entity_geometry[0],
+            &texture_blacklist,
+            &TextureRules::default(),
+        ) {
+            CollisionGeometry::Convex(shapes) => assert!(
+                shapes.len() == 1 && shapes[0].center == Vector3::new(-8.0, -8.0, -8.0),
+                "Convex shapes {:?}",
+                shapes
+            ),
+            collision => panic!("Unexpected collision {:?}", collision),
+        }
+    }
+
+    #[test]
+    fn texture_rule_geometry() {
+        // Cube from min to min + 16, textured -x, -y, -z, +z, +y, +x
+        let cube = |min: i32, textures: [&str; 6]| {
+            let (a, b) = (min, min + 16);
+            let points = [
+                [[a, a, a], [a, a + 1, a], [a, a, a + 1]],
+                [[a, a, a], [a, a, a + 1], [a + 1, a, a]],
+                [[a, a, a], [a + 1, a, a], [a, a + 1, a]],
+                [[b, b, b], [b, b + 1, b], [b + 1, b, b]],
+                [[b, b, b], [b + 1, b, b], [b, b, b + 1]],
+                [[b, b, b], [b, b, b + 1], [b, b + 1, b]],
+            ];
+
+            let mut brush = String::from("{\n");
+            for (points, texture) in points.iter().zip(textures.iter()) {
+                for [x, y, z] in points {
+                    brush += &format!("( {} {} {} ) ", x, y, z);
+                }
+                brush += &format!("{} 0 0 0 1 1\n", texture);
+            }
+            brush + "}\n"
+        };
+
+        let clip = ["brick", "brick", "brick", "brick", "playerclip", "brick"];
+        let map = format!(
+            "{{\n\"classname\" \"worldspawn\"\n{}{}{}}}\n",
+            cube(-64, ["foliage"; 6]),
+            cube(0, ["glass"; 6]),
+            cube(64, clip),
+        );
+        let tokens = crate::map::quake::tokenizer::run(map);
+        let (_token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+
+        let mut textures = HashMap::new();
+        for texture in &["foliage", "glass", "brick", "playerclip"] {
+            textures.insert(texture.to_string(), crate::Texture::new(16, 16));
+        }
+
+        let entity_geometry = crate::geo_builder::run(
+            &crate::TextureInfo(textures),
+            &TextureBlacklist::default(),
+            &entities,
+            &Diagnostics::default(),
+        );
+
+        let glass_layers = CollisionLayers::new(2, 1);
+        let clip_layers = CollisionLayers::new(4, 4);
+        let texture_rules = vec![
+            TextureRule::new("foli*".into(), TextureRuleScope::Face, true, false),
+            TextureRule::new("glass".into(), TextureRuleScope::Face, true, true)
+                .with_collision_layers(glass_layers),
+            TextureRule::new("playerclip".into(), TextureRuleScope::Brush, false, true)
+                .with_collision_layers(clip_layers),
+        ];
+        let texture_rules = TextureRules::new(&texture_rules);
+
+        // The playerclip face hides its whole brush, brick included
+        let visual = get_brush_entity_visual_geometry(
+            &entities[0],
+            &entity_geometry[0],
+            &BrushData::new(VisualType::Mesh, CollisionType::Concave),
+            &TextureBlacklist::default(),
+            &texture_rules,
+            &Diagnostics::default(),
+        );
+        let surfaces: Vec<Option<String>> = match &visual {
+            VisualGeometry::Mesh(mesh) => mesh
+                .surfaces
+                .iter()
+                .map(|surface| surface.texture.clone())
+                .collect(),
+            _ => Vec::new(),
+        };
+        assert!(
+            surfaces == vec![Some("foliage".into()), Some("glass".into())],
+            "Surfaces {:?}",
+            surfaces
+        );
+
+        // Foliage never collides, and the brick faces take the playerclip layers
+        match get_entity_concave_collision(
+            &entity_geometry[0],
+            &TextureBlacklist::default(),
+            &texture_rules,
+            &Diagnostics::default(),
+        ) {
+            CollisionGeometry::Concave(shapes) => {
+                let layers: Vec<(Option<CollisionLayers>, usize)> = shapes
+                    .iter()
+                    .map(|shape| (shape.layers, shape.indices.len()))
+                    .collect();
+                assert!(
+                    layers == vec![(Some(glass_layers), 36), (Some(clip_layers), 36)],
+                    "Concave layers {:?}",
+                    layers
+                );
+            }
+            collision => panic!("Unexpected collision {:?}", collision),
+        }
+
+        match get_entity_convex_collision(
+            &entity_geometry[0],
+            &TextureBlacklist::default(),
+            &texture_rules,
+        ) {
+            CollisionGeometry::Convex(shapes) => {
+                let layers: Vec<Option<CollisionLayers>> =
+                    shapes.iter().map(|shape| shape.layers).collect();
+                assert!(
+                    layers == vec![Some(glass_layers), Some(clip_layers)],
+                    "Convex layers {:?}",
+                    layers
+                );
+            }
+            collision => panic!("Unexpected collision {:?}", collision),
+        }
+
+        // Worldspawn layers follow the same rules
+        let mut game_data = GameData::default();
+        for texture in &["foliage", "glass"] {
+            game_data.worldspawn_layers.push(WorldspawnLayer {
+                texture: texture.to_string(),
+                ..WorldspawnLayer::default()
+            });
+        }
+
+        let diagnostics = Diagnostics::default();
+        let build_layer = build_worldspawn_layer(&game_data, &texture_rules, &diagnostics);
+        let layer_children = |texture: &str, brush: usize| {
+            let brush_geometry = vec![entity_geometry[0].brush_geometry[brush].clone()];
+            match build_layer((&texture.to_string(), &brush_geometry)) {
+                Some(SceneTreeNode {
+                    data: SceneTreeType::Actor(_, children),
+                    ..
+                }) => children,
+                _ => Vec::new(),
+            }
+        };
+
+        let foliage = layer_children("foliage", 0);
+        assert!(
+            matches!(
+                foliage.as_slice(),
+                [SceneTreeNode {
+                    data: SceneTreeType::VisualGeometry(VisualGeometry::Mesh(_)),
+                    ..
+                }]
+            ),
+            "Foliage layer {:?}",
+            foliage
+        );
+
+        let glass = layer_children("glass", 1);
+        let layers: Vec<Option<CollisionLayers>> = glass
+            .iter()
+            .flat_map(|child| match &child.data {
+                SceneTreeType::CollisionGeometry(CollisionGeometry::Concave(shapes)) => {
+                    shapes.iter().map(|shape| shape.layers).collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect();
+        assert!(
+            glass.len() == 2 && layers == vec![Some(glass_layers)],
+            "Glass layer {:?}",
+            glass
+        );
+    }
+}
@@ -17,3 +17,18 @@ pub fn not_blacklisted<'a>(
         false
     }
 }
+
+// Brushes stay solid unless every textured face is blacklisted without collision
+pub fn keeps_collision<'a>(
+    texture_blacklist: &'a TextureBlacklist,
+) -> impl Fn(&&crate::geo_builder::brush::Geometry) -> bool + 'a {
+    move |brush_geometry: &&crate::geo_builder::brush::Geometry| {
+        brush_geometry
+            .plane_geometry
+            .iter()
+            .any(|plane| match &plane.texture {
+                Some(texture) => !texture_blacklist.is_collision_blacklisted_brush(texture),
+                None => true,
+            })
+    }
+}
@@ -1,3 +1,5 @@
+use crate::TextureBlacklist;
+
 pub fn has_texture<'a>(
     texture: &'a Option<String>,
 ) -> impl Fn(&&crate::geo_builder::brush_plane::Geometry) -> bool + 'a {
@@ -5,3 +7,13 @@ pub fn has_texture<'a>(
         &plane_geometry.texture == texture
     }
 }
+
+pub fn keeps_collision<'a>(
+    texture_blacklist: &'a TextureBlacklist,
+) -> impl Fn(&&crate::geo_builder::brush_plane::Geometry) -> bool + 'a {
+    move |plane_geometry: &&crate::geo_builder::brush_plane::Geometry| match &plane_geometry.texture
+    {
+        Some(texture) => !texture_blacklist.is_collision_blacklisted_plane(texture),
+        None => true,
+    }
+}
@@ -6,6 +6,7 @@ pub fn unique(i: usize, vertex: &Vertex, vertices: &[&Vertex]) -> bool {
             && comp.normal == vertex.normal
             && comp.tangent == vertex.tangent
             && comp.uv == vertex.uv
+            && comp.uv2 == vertex.uv2
             && comp.color == vertex.color
     });
 
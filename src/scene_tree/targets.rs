@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use super::{NodePath, SceneTreeNode, SceneTreeType, TargetLink};
+use crate::game_data::forge::{self, PropertyData};
+use crate::map::quake::Entity;
+
+// Keys Quake-derived games use to refer to other entities, whether or not the FGD declares them
+const TARGET_NAME_KEYS: [&str; 1] = ["targetname"];
+const TARGET_KEYS: [&str; 5] = ["target", "killtarget", "target2", "target3", "target4"];
+
+// The names this entity can be targeted by, and its unresolved links to other entities
+pub fn entity_targets(
+    forge_entity: Option<&forge::Entity>,
+    entity: &Entity,
+) -> (Vec<String>, Vec<TargetLink>) {
+    let forge_keys = |data: &PropertyData| -> Vec<&str> {
+        forge_entity
+            .iter()
+            .flat_map(|forge_entity| forge_entity.properties.iter())
+            .filter(|property| &property.data == data)
+            .map(|property| property.name.as_str())
+            .collect()
+    };
+
+    let mut target_name_keys: Vec<&str> = TARGET_NAME_KEYS.to_vec();
+    target_name_keys.extend(forge_keys(&PropertyData::TargetSource));
+
+    let mut target_keys: Vec<&str> = TARGET_KEYS.to_vec();
+    target_keys.extend(forge_keys(&PropertyData::TargetDestination));
+
+    let mut target_names: Vec<String> = Vec::new();
+    for key in target_name_keys {
+        if let Some(value) = entity.get_property(key).filter(|value| !value.is_empty()) {
+            if !target_names.iter().any(|name| name == value) {
+                target_names.push(value.into());
+            }
+        }
+    }
+
+    let mut targets: Vec<TargetLink> = Vec::new();
+    for key in target_keys {
+        if let Some(value) = entity.get_property(key).filter(|value| !value.is_empty()) {
+            if !targets.iter().any(|target| target.key == key) {
+                targets.push(TargetLink::new(key, value));
+            }
+        }
+    }
+
+    (target_names, targets)
+}
+
+fn collect_target_names(
+    scene_tree: &[SceneTreeNode],
+    path: &mut Vec<usize>,
+    target_names: &mut HashMap<String, Vec<NodePath>>,
+) {
+    for (index, node) in scene_tree.iter().enumerate() {
+        if let SceneTreeType::Actor(actor, children) = &node.data {
+            path.push(index);
+
+            for target_name in &actor.target_names {
+                target_names
+                    .entry(target_name.clone())
+                    .or_default()
+                    .push(NodePath(path.clone()));
+            }
+
+            collect_target_names(children, path, target_names);
+            path.pop();
+        }
+    }
+}
+
+fn link_targets(scene_tree: &mut [SceneTreeNode], target_names: &HashMap<String, Vec<NodePath>>) {
+    for node in scene_tree.iter_mut() {
+        if let SceneTreeType::Actor(actor, children) = &mut node.data {
+            for target in actor.targets.iter_mut() {
+                target.nodes = target_names
+                    .get(&target.target)
+                    .cloned()
+                    .unwrap_or_default();
+            }
+
+            link_targets(children, target_names);
+        }
+    }
+}
+
+// Point every target link at the actors it names, once the tree has its final shape
+pub fn resolve(scene_tree: &mut [SceneTreeNode]) {
+    let mut target_names: HashMap<String, Vec<NodePath>> = HashMap::new();
+    collect_target_names(scene_tree, &mut Vec::new(), &mut target_names);
+    link_targets(scene_tree, &target_names);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::{EntityType, Properties, PropertyApplicationType};
+    use crate::Vector3;
+
+    fn actor(name: &str, children: Vec<SceneTreeNode>) -> SceneTreeNode {
+        SceneTreeNode::entity(
+            name.into(),
+            Vector3::default(),
+            EntityType::Placeholder,
+            None,
+            PropertyApplicationType::Properties,
+            Properties::default(),
+            children,
+        )
+    }
+
+    #[test]
+    fn resolve_links() {
+        let mut trigger = Entity::new();
+        trigger.properties.insert("target".into(), "door".into());
+        trigger.properties.insert("on_exit".into(), "lamp".into());
+
+        let mut forge_entity = forge::Entity::default();
+        forge_entity.properties.push(forge::Property::new(
+            "on_exit",
+            "On exit",
+            "",
+            PropertyData::TargetDestination,
+        ));
+
+        let (target_names, targets) = entity_targets(Some(&forge_entity), &trigger);
+        let trigger = actor("trigger_multiple", vec![]).with_targets(target_names, targets);
+
+        let door = actor("func_door", vec![]).with_targets(vec!["door".into()], vec![]);
+        let lamp = actor("light", vec![]).with_targets(vec!["lamp".into()], vec![]);
+        let group = actor("func_group", vec![door, lamp]);
+
+        let mut scene_tree = vec![trigger, group];
+        resolve(&mut scene_tree);
+
+        let targets = match &scene_tree[0].data {
+            SceneTreeType::Actor(actor, _) => actor.targets.clone(),
+            _ => unreachable!(),
+        };
+
+        let links: Vec<(&str, &str, Vec<NodePath>)> = targets
+            .iter()
+            .map(|link| (link.key.as_str(), link.target.as_str(), link.nodes.clone()))
+            .collect();
+        let expected = vec![
+            ("target", "door", vec![NodePath(vec![1, 0])]),
+            ("on_exit", "lamp", vec![NodePath(vec![1, 1])]),
+        ];
+        assert!(links == expected, "Target links {:?}", links);
+
+        let name = match targets[0].nodes[0]
+            .resolve(&scene_tree)
+            .map(|node| &node.data)
+        {
+            Some(SceneTreeType::Actor(actor, _)) => actor.name.as_str(),
+            _ => "",
+        };
+        assert!(name == "func_door", "Resolved node {:?}", name);
+    }
+}
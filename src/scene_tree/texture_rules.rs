@@ -0,0 +1,85 @@
+use crate::game_data::{CollisionLayers, TextureRule, TextureRuleScope};
+use crate::geo_builder::{brush, brush_plane};
+use crate::TexturePatterns;
+
+// Game data texture rules with their patterns compiled once per build
+#[derive(Debug, Default)]
+pub struct TextureRules<'a> {
+    rules: Vec<(TexturePatterns, &'a TextureRule)>,
+}
+
+impl<'a> TextureRules<'a> {
+    pub fn new(texture_rules: &'a [TextureRule]) -> TextureRules<'a> {
+        let rules = texture_rules
+            .iter()
+            .map(|rule| (TexturePatterns::new(vec![rule.texture.clone()]), rule))
+            .collect();
+
+        TextureRules { rules }
+    }
+
+    // The first rule matching a texture
+    fn get(&self, texture: &Option<String>) -> Option<&'a TextureRule> {
+        let texture = texture.as_ref()?;
+        self.rules
+            .iter()
+            .find(|(patterns, _)| patterns.is_match(texture))
+            .map(|(_, rule)| *rule)
+    }
+
+    fn brush_rules<'b>(
+        &'b self,
+        brush_geometry: &'b brush::Geometry,
+    ) -> impl Iterator<Item = &'a TextureRule> + 'b {
+        brush_geometry
+            .plane_geometry
+            .iter()
+            .flat_map(move |plane_geometry| self.get(&plane_geometry.texture))
+            .filter(|rule| rule.scope == TextureRuleScope::Brush)
+    }
+
+    pub fn brush_visible(&self, brush_geometry: &brush::Geometry) -> bool {
+        self.brush_rules(brush_geometry).all(|rule| rule.visual)
+    }
+
+    pub fn plane_visible(&self, plane_geometry: &brush_plane::Geometry) -> bool {
+        self.get(&plane_geometry.texture)
+            .is_none_or(|rule| rule.visual)
+    }
+
+    // Convex shapes cannot lose single faces, so a brush only stops colliding once none of its
+    // faces do
+    pub fn brush_collides(&self, brush_geometry: &brush::Geometry) -> bool {
+        self.brush_rules(brush_geometry).all(|rule| rule.collision)
+            && brush_geometry
+                .plane_geometry
+                .iter()
+                .any(|plane_geometry| self.plane_collides(plane_geometry))
+    }
+
+    pub fn plane_collides(&self, plane_geometry: &brush_plane::Geometry) -> bool {
+        self.get(&plane_geometry.texture)
+            .is_none_or(|rule| rule.collision)
+    }
+
+    // Layers from a brush-scoped rule, which take precedence over those of each face
+    pub fn brush_layers(&self, brush_geometry: &brush::Geometry) -> Option<CollisionLayers> {
+        self.brush_rules(brush_geometry)
+            .find_map(|rule| rule.collision_layers)
+    }
+
+    pub fn plane_layers(&self, plane_geometry: &brush_plane::Geometry) -> Option<CollisionLayers> {
+        self.get(&plane_geometry.texture)
+            .and_then(|rule| rule.collision_layers)
+    }
+
+    // A whole brush as one convex shape takes the first layers any of its faces has
+    pub fn convex_layers(&self, brush_geometry: &brush::Geometry) -> Option<CollisionLayers> {
+        self.brush_layers(brush_geometry).or_else(|| {
+            brush_geometry
+                .plane_geometry
+                .iter()
+                .find_map(|plane_geometry| self.plane_layers(plane_geometry))
+        })
+    }
+}
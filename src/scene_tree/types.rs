@@ -1,6 +1,7 @@
 mod actor;
 mod collision_geometry;
 mod scene_tree;
+mod target_link;
 mod visual_geometry;
 
 pub use actor::Actor;
@@ -9,6 +10,8 @@ pub use collision_geometry::ConcaveCollision;
 pub use collision_geometry::ConvexCollision;
 pub use scene_tree::SceneTreeNode;
 pub use scene_tree::SceneTreeType;
+pub use target_link::NodePath;
+pub use target_link::TargetLink;
 pub use visual_geometry::MeshSurface;
 pub use visual_geometry::VisualGeometry;
 pub use visual_geometry::VisualMesh;
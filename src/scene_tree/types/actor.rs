@@ -1,12 +1,22 @@
+use std::collections::{HashMap, HashSet};
+
+use super::TargetLink;
 use crate::game_data::{EntityType, Properties, PropertyApplicationType};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Actor {
     pub name: String,
     pub entity_type: EntityType,
     pub component_class: Option<String>,
     pub property_application_type: PropertyApplicationType,
     pub properties: Properties,
+    // Property values as written in the map, before conversion
+    pub raw_properties: HashMap<String, String>,
+    // Keys the forge game data declares for this class, rather than kept only as raw strings
+    pub declared_properties: HashSet<String>,
+    pub target_names: Vec<String>,
+    pub targets: Vec<TargetLink>,
 }
 
 impl Actor {
@@ -17,12 +27,20 @@ impl Actor {
         property_application_type: PropertyApplicationType,
         properties: Properties,
     ) -> Actor {
+        let raw_properties = HashMap::new();
+        let declared_properties = HashSet::new();
+        let target_names = Vec::new();
+        let targets = Vec::new();
         Actor {
             name,
             entity_type,
             component_class,
             property_application_type,
             properties,
+            raw_properties,
+            declared_properties,
+            target_names,
+            targets,
         }
     }
 }
@@ -1,6 +1,8 @@
+use crate::game_data::CollisionLayers;
 use crate::Vector3;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CollisionGeometry {
     None,
     Convex(Vec<ConvexCollision>),
@@ -18,30 +20,53 @@ impl CollisionGeometry {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConvexCollision {
     pub center: Vector3,
     pub points: Vec<Vector3>,
+    // From the brush's texture rules, engine defaults when None
+    pub layers: Option<CollisionLayers>,
 }
 
 impl ConvexCollision {
     pub fn new(center: Vector3, points: Vec<Vector3>) -> ConvexCollision {
-        ConvexCollision { center, points }
+        let layers = None;
+        ConvexCollision {
+            center,
+            points,
+            layers,
+        }
+    }
+
+    pub fn with_layers(mut self, layers: Option<CollisionLayers>) -> ConvexCollision {
+        self.layers = layers;
+        self
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConcaveCollision {
     pub center: Vector3,
     pub vertices: Vec<Vector3>,
     pub indices: Vec<usize>,
+    // Faces are split into one shape per set of layers their texture rules give them
+    pub layers: Option<CollisionLayers>,
 }
 
 impl ConcaveCollision {
     pub fn new(center: Vector3, vertices: Vec<Vector3>, indices: Vec<usize>) -> ConcaveCollision {
+        let layers = None;
         ConcaveCollision {
             center,
             vertices,
             indices,
+            layers,
         }
     }
+
+    pub fn with_layers(mut self, layers: Option<CollisionLayers>) -> ConcaveCollision {
+        self.layers = layers;
+        self
+    }
 }
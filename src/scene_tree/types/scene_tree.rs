@@ -1,16 +1,24 @@
+use std::collections::{HashMap, HashSet};
+
 use super::Actor;
 use super::CollisionGeometry;
+use super::TargetLink;
 use super::VisualGeometry;
 
 use crate::game_data::{EntityType, Properties, PropertyApplicationType};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SceneTreeNode {
     pub origin: crate::Vector3,
     pub data: SceneTreeType,
 }
 
+// Actors are much larger than the geometry variants, but boxing them would change every match on
+// Actor(actor, children)
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SceneTreeType {
     Actor(Actor, Vec<SceneTreeNode>),
     VisualGeometry(VisualGeometry),
@@ -35,6 +43,35 @@ impl SceneTreeNode {
         SceneTreeNode { origin, data }
     }
 
+    pub fn with_targets(
+        mut self,
+        target_names: Vec<String>,
+        targets: Vec<TargetLink>,
+    ) -> SceneTreeNode {
+        if let SceneTreeType::Actor(actor, _) = &mut self.data {
+            actor.target_names = target_names;
+            actor.targets = targets;
+        }
+        self
+    }
+
+    pub fn with_raw_properties(mut self, raw_properties: HashMap<String, String>) -> SceneTreeNode {
+        if let SceneTreeType::Actor(actor, _) = &mut self.data {
+            actor.raw_properties = raw_properties;
+        }
+        self
+    }
+
+    pub fn with_declared_properties(
+        mut self,
+        declared_properties: HashSet<String>,
+    ) -> SceneTreeNode {
+        if let SceneTreeType::Actor(actor, _) = &mut self.data {
+            actor.declared_properties = declared_properties;
+        }
+        self
+    }
+
     pub fn visual_geometry(
         origin: crate::Vector3,
         visual_geometry: VisualGeometry,
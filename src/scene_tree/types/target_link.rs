@@ -0,0 +1,41 @@
+use super::{SceneTreeNode, SceneTreeType};
+
+// Child indices from the scene tree root down to a node, counting geometry children
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodePath(pub Vec<usize>);
+
+impl NodePath {
+    pub fn resolve<'a>(&self, scene_tree: &'a [SceneTreeNode]) -> Option<&'a SceneTreeNode> {
+        let NodePath(indices) = self;
+        let (first, rest) = indices.split_first()?;
+
+        let mut node = scene_tree.get(*first)?;
+        for index in rest {
+            node = match &node.data {
+                SceneTreeType::Actor(_, children) => children.get(*index)?,
+                _ => return None,
+            };
+        }
+
+        Some(node)
+    }
+}
+
+// A target property on an actor, with every actor whose target name matches its value
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TargetLink {
+    pub key: String,
+    pub target: String,
+    pub nodes: Vec<NodePath>,
+}
+
+impl TargetLink {
+    pub fn new(key: &str, target: &str) -> TargetLink {
+        let key = key.into();
+        let target = target.into();
+        let nodes = Vec::new();
+        TargetLink { key, target, nodes }
+    }
+}
@@ -1,14 +1,17 @@
 use crate::Color;
+use crate::TextureSemantics;
 use crate::Vector2;
 use crate::Vector3;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VisualGeometry {
     None,
     Mesh(VisualMesh),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VisualMesh {
     pub surfaces: Vec<MeshSurface>,
 }
@@ -20,32 +23,42 @@ impl VisualMesh {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MeshSurface {
     pub texture: Option<String>,
+    // Liquid, sky, transparency and animation frames implied by the texture name
+    pub semantics: Option<TextureSemantics>,
     pub vertices: Vec<Vector3>,
     pub normals: Vec<Vector3>,
     pub tangents: Vec<(Vector3, f32)>,
     pub uvs: Option<Vec<Vector2>>,
+    pub uv2: Option<Vec<Vector2>>,
     pub colors: Option<Vec<Color>>,
     pub indices: Vec<usize>,
 }
 
 impl MeshSurface {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         texture: Option<String>,
         vertices: Vec<Vector3>,
         normals: Vec<Vector3>,
         tangents: Vec<(Vector3, f32)>,
         uvs: Option<Vec<Vector2>>,
+        uv2: Option<Vec<Vector2>>,
         colors: Option<Vec<Color>>,
         indices: Vec<usize>,
     ) -> MeshSurface {
+        let semantics = texture.as_deref().map(TextureSemantics::from_name);
+
         MeshSurface {
             texture,
+            semantics,
             vertices,
             normals,
             tangents,
             uvs,
+            uv2,
             colors,
             indices,
         }
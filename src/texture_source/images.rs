@@ -0,0 +1,290 @@
+use nom::{
+    bytes::complete::tag,
+    number::complete::{be_u16, be_u32, le_u16, le_u32, le_u8},
+    IResult,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::{Diagnostics, Texture, TextureInfo};
+
+// Enough for the fixed headers of every supported format
+const HEADER_BYTES: usize = 128;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Tga,
+    Jpeg,
+    Dds,
+}
+
+impl ImageFormat {
+    pub fn from_extension(extension: &str) -> Option<ImageFormat> {
+        match extension.to_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "tga" => Some(ImageFormat::Tga),
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "dds" => Some(ImageFormat::Dds),
+            _ => None,
+        }
+    }
+
+    // Lower wins when one folder holds the same name in several formats
+    fn priority(self) -> usize {
+        match self {
+            ImageFormat::Png => 0,
+            ImageFormat::Tga => 1,
+            ImageFormat::Jpeg => 2,
+            ImageFormat::Dds => 3,
+        }
+    }
+}
+
+fn png_parser(i: &[u8]) -> IResult<&[u8], (u32, u32)> {
+    let (i, (_, _, _, width, height)) = nom::sequence::tuple((
+        tag(b"\x89PNG\r\n\x1a\n"),
+        be_u32,
+        tag(b"IHDR"),
+        be_u32,
+        be_u32,
+    ))(i)?;
+    Ok((i, (width, height)))
+}
+
+fn tga_parser(i: &[u8]) -> IResult<&[u8], (u32, u32)> {
+    let (i, (_, _, image_type, _, _, width, height)) = nom::sequence::tuple((
+        le_u8,
+        le_u8,
+        le_u8,
+        nom::bytes::complete::take(5usize),
+        nom::bytes::complete::take(4usize),
+        le_u16,
+        le_u16,
+    ))(i)?;
+
+    // TGA has no magic number, so reject anything that isn't a known image type
+    match image_type {
+        1 | 2 | 3 | 9 | 10 | 11 => Ok((i, (width as u32, height as u32))),
+        _ => Err(nom::Err::Error((i, nom::error::ErrorKind::Tag))),
+    }
+}
+
+fn dds_parser(i: &[u8]) -> IResult<&[u8], (u32, u32)> {
+    let (i, (_, _, _, height, width)) =
+        nom::sequence::tuple((tag(b"DDS "), le_u32, le_u32, le_u32, le_u32))(i)?;
+    Ok((i, (width, height)))
+}
+
+// Sample precision, then height and width
+fn jpeg_frame_parser(i: &[u8]) -> IResult<&[u8], (u32, u32)> {
+    let (i, (_, height, width)) = nom::sequence::tuple((le_u8, be_u16, be_u16))(i)?;
+    Ok((i, (width as u32, height as u32)))
+}
+
+// Walk JPEG segments until a start-of-frame marker, which holds the dimensions
+fn jpeg_size<R: Read + Seek>(reader: &mut R) -> Result<(u32, u32), String> {
+    let mut marker = [0u8; 4];
+    if reader.read_exact(&mut marker[..2]).is_err() || marker[..2] != [0xff, 0xd8] {
+        return Err("Missing JPEG start of image".into());
+    }
+
+    loop {
+        if let Err(err) = reader.read_exact(&mut marker) {
+            return Err(format!("No JPEG frame header: {:?}", err));
+        }
+
+        if marker[0] != 0xff {
+            return Err(format!("Malformed JPEG marker {:?}", &marker[..2]));
+        }
+
+        let length = u16::from_be_bytes([marker[2], marker[3]]) as i64;
+
+        match marker[1] {
+            // SOF0 to SOF15, except DHT, JPG and DAC which share the range
+            0xc0..=0xcf if ![0xc4, 0xc8, 0xcc].contains(&marker[1]) => {
+                let mut frame = [0u8; 5];
+                if let Err(err) = reader.read_exact(&mut frame) {
+                    return Err(format!("Truncated JPEG frame header: {:?}", err));
+                }
+
+                return match jpeg_frame_parser(&frame) {
+                    Ok((_, size)) => Ok(size),
+                    Err(err) => Err(format!("Malformed JPEG frame: {:?}", err)),
+                };
+            }
+            0xd9 | 0xda => return Err("No JPEG frame header before image data".into()),
+            _ => {
+                if let Err(err) = reader.seek(SeekFrom::Current(length - 2)) {
+                    return Err(format!("Error skipping JPEG segment: {:?}", err));
+                }
+            }
+        }
+    }
+}
+
+// Width and height from an image's header, without decoding any pixels
+pub fn image_size(image_file: &Path, format: ImageFormat) -> Result<(u32, u32), String> {
+    let mut file = match File::open(image_file) {
+        Ok(file) => file,
+        Err(err) => return Err(format!("Error opening {:?}: {:?}", image_file, err)),
+    };
+
+    if format == ImageFormat::Jpeg {
+        return jpeg_size(&mut file);
+    }
+
+    let mut header_buf = Vec::with_capacity(HEADER_BYTES);
+    if let Err(err) = file.take(HEADER_BYTES as u64).read_to_end(&mut header_buf) {
+        return Err(format!("Error reading {:?}: {:?}", image_file, err));
+    }
+
+    let size = match format {
+        ImageFormat::Png => png_parser(&header_buf),
+        ImageFormat::Tga => tga_parser(&header_buf),
+        ImageFormat::Dds => dds_parser(&header_buf),
+        ImageFormat::Jpeg => unreachable!(),
+    };
+
+    match size {
+        Ok((_, size)) => Ok(size),
+        Err(err) => Err(format!("Error parsing {:?} header: {:?}", image_file, err)),
+    }
+}
+
+// Every image under a texture root, keyed by its path relative to the root without extension,
+// e.g. "base/wall" for textures/base/wall.png, as TrenchBroom names loose textures
+pub fn scan_images(texture_root: &str, diagnostics: &Diagnostics) -> Result<TextureInfo, String> {
+    scan_image_files(texture_root, &mut Vec::new(), diagnostics)
+}
+
+// Like scan_images, also collecting every directory and image file it looked at
+pub(crate) fn scan_image_files(
+    texture_root: &str,
+    source_files: &mut Vec<PathBuf>,
+    diagnostics: &Diagnostics,
+) -> Result<TextureInfo, String> {
+    let root = Path::new(texture_root);
+    if !root.is_dir() {
+        return Err(format!("No such texture directory {:?}", root));
+    }
+
+    let mut images: HashMap<String, (ImageFormat, Texture)> = HashMap::new();
+    let mut directories = vec![root.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        source_files.push(directory.clone());
+
+        let entries = match std::fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(err) => return Err(format!("Error reading {:?}: {:?}", directory, err)),
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                directories.push(path);
+                continue;
+            }
+
+            let format = match path
+                .extension()
+                .and_then(|extension| ImageFormat::from_extension(&extension.to_string_lossy()))
+            {
+                Some(format) => format,
+                None => continue,
+            };
+            source_files.push(path.clone());
+
+            let name: Vec<String> = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .with_extension("")
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            let name = name.join("/");
+
+            if let Some((existing, _)) = images.get(&name) {
+                if existing.priority() <= format.priority() {
+                    continue;
+                }
+            }
+
+            match image_size(&path, format) {
+                Ok((width, height)) => {
+                    images.insert(name, (format, Texture::new(width, height)));
+                }
+                Err(err) => diagnostics.debug(&format!("Skipping texture image: {}", err)),
+            }
+        }
+    }
+
+    Ok(TextureInfo(
+        images
+            .into_iter()
+            .map(|(name, (_, texture))| (name, texture))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(path: &str) -> String {
+        format!(
+            "{}/src/texture_source/test_data/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            path
+        )
+    }
+
+    #[test]
+    fn header_sizes() {
+        let sizes: Vec<(u32, u32)> = [
+            ("textures/base/wall.png", ImageFormat::Png),
+            ("textures/base/floor.tga", ImageFormat::Tga),
+            // Header-only JPEG, its frame header follows a comment segment
+            ("textures/sky/Clouds.jpg", ImageFormat::Jpeg),
+            ("textures/detail.dds", ImageFormat::Dds),
+        ]
+        .iter()
+        .map(|(path, format)| image_size(Path::new(&fixture(path)), *format).unwrap())
+        .collect();
+
+        assert!(
+            sizes == vec![(64, 32), (16, 48), (256, 128), (32, 16)],
+            "Image sizes {:?}",
+            sizes
+        );
+
+        assert!(image_size(Path::new(&fixture("textures/readme.txt")), ImageFormat::Png).is_err());
+    }
+
+    #[test]
+    fn scan_texture_root() {
+        let TextureInfo(textures) =
+            scan_images(&fixture("textures"), &Diagnostics::default()).unwrap();
+
+        let mut names: Vec<&String> = textures.keys().collect();
+        names.sort();
+        assert!(
+            names == vec!["base/floor", "base/wall", "detail", "sky/Clouds"],
+            "Texture names {:?}",
+            names
+        );
+
+        // PNG is preferred over the 8x8 TGA of the same name
+        let wall = &textures["base/wall"];
+        assert!(
+            (wall.width, wall.height) == (64, 32),
+            "Wall size {:?}",
+            wall
+        );
+
+        assert!(scan_images(&fixture("missing"), &Diagnostics::default()).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::DiagnosticKind;
+use crate::map::quake::Entity;
+use crate::vfs::Vfs;
+use crate::{wad, Diagnostics, Texture, TextureInfo};
+
+mod images;
+
+use images::scan_image_files;
+pub use images::{image_size, scan_images, ImageFormat};
+
+// WAD paths named by worldspawn's "wad" key, which TrenchBroom separates with semicolons
+pub(crate) fn worldspawn_wads(entities: &[Entity]) -> Vec<String> {
+    let worldspawn = entities
+        .iter()
+        .find(|entity| entity.get_property("classname") == Some("worldspawn"));
+
+    match worldspawn.and_then(|worldspawn| worldspawn.get_property("wad")) {
+        Some(wads) => wads
+            .split(';')
+            .map(str::trim)
+            .filter(|wad| !wad.is_empty())
+            .map(String::from)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+// Explicit texture info, then the worldspawn WADs if WAD search paths are given, then loose
+// images under each texture root. Sizes found earlier are never replaced by later sources.
+// Also returns the WADs, archives, directories and images that were read, for watching.
+pub(crate) fn run(
+    map_file: &str,
+    wad_search_paths: &Option<Vec<String>>,
+    texture_roots: &[String],
+    texture_info: &TextureInfo,
+    entities: &[Entity],
+    diagnostics: &Diagnostics,
+) -> (TextureInfo, Vec<PathBuf>) {
+    let TextureInfo(mut textures) = texture_info.clone();
+    let mut source_files = Vec::new();
+
+    if let Some(wad_search_paths) = wad_search_paths {
+        resolve_wads(
+            map_file,
+            wad_search_paths,
+            entities,
+            &mut textures,
+            &mut source_files,
+            diagnostics,
+        );
+    }
+
+    for texture_root in texture_roots {
+        match scan_image_files(texture_root, &mut source_files, diagnostics) {
+            Ok(TextureInfo(images)) => {
+                for (name, texture) in images {
+                    textures.entry(name).or_insert(texture);
+                }
+            }
+            Err(error) => diagnostics.warning(DiagnosticKind::InvalidSearchPath {
+                path: texture_root.clone(),
+                error,
+            }),
+        }
+    }
+
+    let missing: BTreeSet<&String> = entities
+        .iter()
+        .flat_map(|entity| entity.brushes.iter())
+        .flat_map(|brush| brush.planes.iter())
+        .map(|plane| &plane.texture)
+        .filter(|texture| !textures.contains_key(*texture))
+        .collect();
+
+    for texture in missing {
+        diagnostics.warning(DiagnosticKind::MissingTexture {
+            texture: texture.clone(),
+        });
+    }
+
+    (TextureInfo(textures), source_files)
+}
+
+// The map's own directory is searched first, then each search path in order, whether directory or
+// archive. Where two WADs hold the same texture the first one named wins, as in qbsp.
+fn resolve_wads(
+    map_file: &str,
+    search_paths: &[String],
+    entities: &[Entity],
+    textures: &mut HashMap<String, Texture>,
+    source_files: &mut Vec<PathBuf>,
+    diagnostics: &Diagnostics,
+) {
+    let mut vfs = Vfs::new();
+
+    // Later mounts take precedence, so mount in reverse
+    for search_path in search_paths.iter().rev() {
+        if let Err(error) = vfs.mount(search_path) {
+            diagnostics.warning(DiagnosticKind::InvalidSearchPath {
+                path: search_path.clone(),
+                error,
+            });
+        }
+    }
+
+    let map_directory = match Path::new(map_file).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().into_owned(),
+        _ => ".".into(),
+    };
+    vfs.mount(&map_directory).ok();
+
+    for wad in worldspawn_wads(entities) {
+        let wad_file = match find_wad(&vfs, &wad) {
+            Some(wad_file) => wad_file,
+            None => {
+                diagnostics.warning(DiagnosticKind::MissingWad { wad });
+                continue;
+            }
+        };
+
+        diagnostics.debug(&format!("Reading texture headers from {}", wad_file));
+
+        // WADs inside an archive change along with the archive itself
+        if let Some(source_file) = Path::new(&wad_file).ancestors().find(|path| path.is_file()) {
+            source_files.push(source_file.to_path_buf());
+        }
+
+        match wad::read_texture_headers(&wad_file, None) {
+            Ok(mip_textures) => {
+                for mip_texture in mip_textures {
+                    let texture = Texture::new(mip_texture.width, mip_texture.height);
+                    textures.entry(mip_texture.name).or_insert(texture);
+                }
+            }
+            Err(error) => diagnostics.warning(DiagnosticKind::WadReadFailed { wad, error }),
+        }
+    }
+}
+
+// Editors often store absolute paths from another machine, so try the path as written and then
+// each shorter suffix of it, down to the bare file name
+fn find_wad(vfs: &Vfs, wad: &str) -> Option<String> {
+    let wad = wad.replace('\\', "/");
+    if Path::new(&wad).is_file() {
+        return Some(wad);
+    }
+
+    let components: Vec<&str> = wad
+        .split('/')
+        .filter(|component| !component.is_empty() && !component.ends_with(':'))
+        .collect();
+
+    (0..components.len()).find_map(|i| vfs.find(&components[i..].join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Level;
+
+    fn entities(wad_key: &str) -> Vec<Entity> {
+        let map = format!(
+            r#"{{
+"classname" "worldspawn"
+"wad" "{}"
+{{
+( 0 0 0 ) ( 0 1 0 ) ( 1 0 0 ) first 0 0 0 1 1
+( 0 0 0 ) ( 1 0 0 ) ( 0 0 1 ) second 0 0 0 1 1
+( 0 0 0 ) ( 0 0 1 ) ( 0 1 0 ) explicit 0 0 0 1 1
+( 8 8 8 ) ( 8 9 8 ) ( 9 8 8 ) missing 0 0 0 1 1
+}}
+}}
+"#,
+            wad_key
+        );
+
+        let tokens = crate::map::quake::tokenizer::run(map);
+        let (_token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+        entities
+    }
+
+    fn fixture(path: &str) -> String {
+        format!("{}/src/{}", env!("CARGO_MANIFEST_DIR"), path)
+    }
+
+    #[test]
+    fn split_wad_key() {
+        let wads = worldspawn_wads(&entities("C:\\quake\\id1\\base.wad; gfx/base.wad;"));
+        assert!(
+            wads == vec!["C:\\quake\\id1\\base.wad", "gfx/base.wad"],
+            "WADs {:?}",
+            wads
+        );
+    }
+
+    #[test]
+    fn resolve_from_search_paths() {
+        let mut explicit = HashMap::new();
+        explicit.insert("explicit".to_string(), Texture::new(8, 8));
+        explicit.insert("first".to_string(), Texture::new(1, 1));
+
+        let diagnostics = Diagnostics::default();
+        let (TextureInfo(textures), source_files) = run(
+            "maps/test.map",
+            &Some(vec![
+                fixture("wad/test_data"),
+                fixture("vfs/test_data/id1.pak"),
+            ]),
+            &[],
+            &TextureInfo(explicit),
+            &entities("C:\\quake\\id1\\gfx\\base.wad;/missing/other.wad"),
+            &diagnostics,
+        );
+
+        let sizes: Vec<(u32, u32)> = ["explicit", "first", "second"]
+            .iter()
+            .map(|texture| (textures[*texture].width, textures[*texture].height))
+            .collect();
+
+        // Explicit sizes are never overridden
+        assert!(
+            sizes == vec![(8, 8), (1, 1), (16, 16)],
+            "Texture sizes {:?}",
+            sizes
+        );
+
+        // The WAD was found inside the PAK, so the PAK is what changes
+        assert!(
+            source_files == vec![PathBuf::from(fixture("vfs/test_data/id1.pak"))],
+            "Source files {:?}",
+            source_files
+        );
+
+        let warnings: Vec<DiagnosticKind> = diagnostics
+            .report()
+            .diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.level == Level::Warning)
+            .map(|diagnostic| diagnostic.kind)
+            .collect();
+
+        assert!(
+            warnings
+                == vec![
+                    DiagnosticKind::MissingWad {
+                        wad: "/missing/other.wad".into()
+                    },
+                    DiagnosticKind::MissingTexture {
+                        texture: "missing".into()
+                    },
+                ],
+            "Warnings {:?}",
+            warnings
+        );
+    }
+
+    #[test]
+    fn merge_texture_roots() {
+        let diagnostics = Diagnostics::default();
+        let (TextureInfo(textures), _source_files) = run(
+            "maps/test.map",
+            &None,
+            &[
+                fixture("texture_source/test_data/textures"),
+                fixture("texture_source/test_data/missing"),
+            ],
+            &TextureInfo(HashMap::new()),
+            &entities(""),
+            &diagnostics,
+        );
+
+        let wall = &textures["base/wall"];
+        assert!(
+            (wall.width, wall.height) == (64, 32),
+            "Wall size {:?}",
+            wall
+        );
+
+        let warnings = diagnostics
+            .report()
+            .diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.level == Level::Warning)
+            .count();
+
+        // The missing root, then "first", "second", "explicit" and "missing"
+        assert!(warnings == 5, "Warning count {}", warnings);
+    }
+}
@@ -1,6 +1,8 @@
 mod color;
 mod entity;
 mod texture;
+mod texture_patterns;
+mod texture_semantics;
 mod vertex;
 
 pub type Vector2 = glam::Vec2;
@@ -12,4 +14,25 @@ pub use color::Color;
 pub use texture::Texture;
 pub use texture::TextureBlacklist;
 pub use texture::TextureInfo;
+pub use texture::TexturePreset;
+pub use texture_patterns::TexturePatterns;
+pub use texture_semantics::AnimationChain;
+pub use texture_semantics::AnimationChains;
+pub use texture_semantics::Liquid;
+pub use texture_semantics::SurfaceKind;
+pub use texture_semantics::TextureAnimation;
+pub use texture_semantics::TextureSemantics;
 pub use vertex::Vertex;
+
+// Hash maps serialize in a random order, which would give equal build inputs different cache keys
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_sorted<S, V>(
+    map: &std::collections::HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    V: serde::Serialize,
+{
+    serializer.collect_map(map.iter().collect::<std::collections::BTreeMap<_, _>>())
+}
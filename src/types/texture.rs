@@ -1,10 +1,19 @@
+use crate::TexturePatterns;
 use crate::Vector2;
 use std::collections::HashMap;
 
-#[derive(Debug)]
-pub struct TextureInfo(pub HashMap<String, Texture>);
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureInfo(
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::types::serialize_sorted")
+    )]
+    pub HashMap<String, Texture>,
+);
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Texture {
     pub width: u32,
     pub height: u32,
@@ -20,42 +29,112 @@ impl Texture {
     }
 }
 
-#[derive(Debug)]
-pub struct TextureBlacklist {
-    pub brush: Vec<String>,
-    pub plane: Vec<String>,
+// Special textures of common games and editors
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TexturePreset {
+    Quake,
+    Quake2,
+    HalfLife,
+    TrenchBroom,
 }
 
-impl Default for TextureBlacklist {
-    fn default() -> TextureBlacklist {
-        let brush = Vec::new();
-        let plane = Vec::new();
-        TextureBlacklist { brush, plane }
-    }
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureBlacklist {
+    pub brush: TexturePatterns,
+    pub plane: TexturePatterns,
+    pub smoothing: TexturePatterns,
+    // Blacklisted brushes and faces are left out of visuals but still collide, like clip, unless
+    // they also match one of these, like hint
+    pub no_collision: TexturePatterns,
 }
 
 impl TextureBlacklist {
     pub fn new(brush: Vec<String>, plane: Vec<String>) -> TextureBlacklist {
-        TextureBlacklist { brush, plane }
+        TextureBlacklist {
+            brush: brush.into(),
+            plane: plane.into(),
+            ..TextureBlacklist::default()
+        }
     }
 
-    pub fn is_blacklisted_brush(&self, texture: &str) -> bool {
-        for brush_texture in self.brush.iter() {
-            if texture == brush_texture {
-                return true;
-            }
-        }
+    pub fn preset(preset: TexturePreset) -> TextureBlacklist {
+        TextureBlacklist::default().with_preset(preset)
+    }
+
+    pub fn with_preset(mut self, preset: TexturePreset) -> TextureBlacklist {
+        let (brush, plane, no_collision): (&[&str], &[&str], &[&str]) = match preset {
+            TexturePreset::Quake => (
+                &["clip*", "*trigger", "hint*", "origin"],
+                &["skip"],
+                &["hint*", "origin"],
+            ),
+            // Quake 2 textures are named by their directory, e.g. e1u1/clip
+            TexturePreset::Quake2 => (
+                &["*/clip", "*/trigger", "*/hint", "*/origin"],
+                &["*/skip"],
+                &["*/hint", "*/origin"],
+            ),
+            TexturePreset::HalfLife => (
+                &["clip*", "*trigger", "hint", "origin", "bevel"],
+                &["skip", "null"],
+                &["hint", "origin"],
+            ),
+            TexturePreset::TrenchBroom => (&[], &["__TB_empty"], &[]),
+        };
+
+        self.brush.extend(brush.iter().copied());
+        self.plane.extend(plane.iter().copied());
+        self.no_collision.extend(no_collision.iter().copied());
+        self
+    }
 
-        false
+    pub fn is_blacklisted_brush(&self, texture: &str) -> bool {
+        self.brush.is_match(texture)
     }
 
     pub fn is_blacklisted_plane(&self, texture: &str) -> bool {
-        for plane_texture in self.plane.iter() {
-            if texture == plane_texture {
-                return true;
-            }
-        }
+        self.plane.is_match(texture)
+    }
+
+    pub fn is_blacklisted_smoothing(&self, texture: &str) -> bool {
+        self.smoothing.is_match(texture)
+    }
+
+    pub fn is_collision_blacklisted_brush(&self, texture: &str) -> bool {
+        self.is_blacklisted_brush(texture) && self.no_collision.is_match(texture)
+    }
+
+    pub fn is_collision_blacklisted_plane(&self, texture: &str) -> bool {
+        self.is_blacklisted_plane(texture) && self.no_collision.is_match(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        let blacklist =
+            TextureBlacklist::preset(TexturePreset::Quake).with_preset(TexturePreset::TrenchBroom);
+
+        assert!(
+            blacklist.is_blacklisted_brush("CLIP") && blacklist.is_blacklisted_brush("hintskip")
+        );
+        assert!(
+            blacklist.is_blacklisted_plane("skip") && blacklist.is_blacklisted_plane("__TB_empty")
+        );
+        assert!(!blacklist.is_blacklisted_plane("skip_wall"));
+
+        // Clip keeps its collision, hint loses it
+        assert!(!blacklist.is_collision_blacklisted_brush("clip"));
+        assert!(blacklist.is_collision_blacklisted_brush("hint"));
 
-        false
+        let blacklist = TextureBlacklist::preset(TexturePreset::Quake2);
+        assert!(
+            blacklist.is_blacklisted_brush("e1u1/clip") && !blacklist.is_blacklisted_brush("clip")
+        );
     }
 }
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+// Texture name patterns, where '*' matches any run of characters and '?' any single one. Quake
+// engines ignore the case of texture names, so matching does too.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<String>", into = "Vec<String>")
+)]
+pub struct TexturePatterns {
+    patterns: HashSet<String>,
+    matcher: PatternMatcher,
+}
+
+// Names without wildcards are looked up directly, so only real globs are matched one by one
+#[derive(Debug, Clone, Default)]
+struct PatternMatcher {
+    exact: HashSet<String>,
+    globs: Vec<Vec<u8>>,
+}
+
+impl TexturePatterns {
+    pub fn new<S: Into<String>>(patterns: impl IntoIterator<Item = S>) -> TexturePatterns {
+        let mut texture_patterns = TexturePatterns::default();
+        texture_patterns.extend(patterns);
+        texture_patterns
+    }
+
+    pub fn insert(&mut self, pattern: impl Into<String>) -> bool {
+        let pattern = pattern.into();
+        let key = pattern.to_lowercase();
+
+        if !self.patterns.insert(pattern) {
+            return false;
+        }
+
+        if key.contains(['*', '?']) {
+            if !self.matcher.globs.iter().any(|glob| glob == key.as_bytes()) {
+                self.matcher.globs.push(key.into_bytes());
+            }
+        } else {
+            self.matcher.exact.insert(key);
+        }
+
+        true
+    }
+
+    pub fn extend<S: Into<String>>(&mut self, patterns: impl IntoIterator<Item = S>) {
+        for pattern in patterns {
+            self.insert(pattern);
+        }
+    }
+
+    pub fn patterns(&self) -> &HashSet<String> {
+        &self.patterns
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn is_match(&self, texture: &str) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+
+        let texture = texture.to_lowercase();
+        self.matcher.exact.contains(&texture)
+            || self
+                .matcher
+                .globs
+                .iter()
+                .any(|glob| glob_match(glob, texture.as_bytes()))
+    }
+}
+
+impl From<Vec<String>> for TexturePatterns {
+    fn from(patterns: Vec<String>) -> Self {
+        TexturePatterns::new(patterns)
+    }
+}
+
+// Sorted, so serializing the same patterns always gives the same output
+impl From<TexturePatterns> for Vec<String> {
+    fn from(texture_patterns: TexturePatterns) -> Self {
+        let mut patterns: Vec<String> = texture_patterns.patterns.into_iter().collect();
+        patterns.sort();
+        patterns
+    }
+}
+
+// Backtracks only to the most recent '*', which is enough since a later '*' can absorb anything an
+// earlier one could have
+fn glob_match(glob: &[u8], text: &[u8]) -> bool {
+    let (mut g, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match glob.get(g) {
+            Some(b'*') => {
+                star = Some((g, t));
+                g += 1;
+            }
+            Some(c) if *c == b'?' || *c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_g, star_t)) => {
+                    g = star_g + 1;
+                    t = star_t + 1;
+                    star = Some((star_g, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[g..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        let patterns = TexturePatterns::new(vec!["clip*", "*trigger", "skip", "h?nt", "*/origin"]);
+
+        let matches: Vec<&str> = [
+            "clip",
+            "CLIPMONSTER",
+            "trigger",
+            "aaatrigger",
+            "triggers",
+            "skip",
+            "skipper",
+            "hint",
+            "hunt",
+            "hnt",
+            "e1u1/origin",
+            "origin",
+        ]
+        .iter()
+        .copied()
+        .filter(|texture| patterns.is_match(texture))
+        .collect();
+
+        assert!(
+            matches
+                == vec![
+                    "clip",
+                    "CLIPMONSTER",
+                    "trigger",
+                    "aaatrigger",
+                    "skip",
+                    "hint",
+                    "hunt",
+                    "e1u1/origin"
+                ],
+            "Matches {:?}",
+            matches
+        );
+
+        assert!(glob_match(b"*a*b", b"xaxxab"));
+        assert!(!glob_match(b"*a*b", b"xaxxa"));
+        assert!(!TexturePatterns::default().is_match(""));
+    }
+}
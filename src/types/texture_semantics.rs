@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+// Quake engines allow ten frames per chain, +0 to +9 and +a to +j
+const MAX_FRAMES: u8 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Liquid {
+    Water,
+    Slime,
+    Lava,
+    Teleport,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SurfaceKind {
+    Solid,
+    // '*' in Quake, '!' in Half-Life
+    Liquid(Liquid),
+    Sky,
+    // '{', where palette index 255 is see-through
+    Transparent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureAnimation {
+    // Lowercase name after the "+N" prefix, shared by every frame of both chains
+    pub base: String,
+    pub frame: u8,
+    // Whether this frame is from the +a to +j chain, shown while the entity is toggled
+    pub alternate: bool,
+    // Every frame of the +0 to +9 chain in order, empty if unknown
+    pub frames: Vec<String>,
+    // Every frame of the +a to +j chain in order, empty if there is none
+    pub alternate_frames: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextureSemantics {
+    pub kind: SurfaceKind,
+    pub animation: Option<TextureAnimation>,
+}
+
+impl TextureSemantics {
+    pub fn new(texture: &str, animation_chains: &AnimationChains) -> TextureSemantics {
+        let (name, animation) = match animation_frame(texture) {
+            Some((base, frame, alternate)) => {
+                let (frames, alternate_frames) = match animation_chains.0.get(&base) {
+                    Some(chain) => (chain.frames.clone(), chain.alternate_frames.clone()),
+                    None => (Vec::new(), Vec::new()),
+                };
+
+                let animation = TextureAnimation {
+                    base,
+                    frame,
+                    alternate,
+                    frames,
+                    alternate_frames,
+                };
+
+                (&texture[2..], Some(animation))
+            }
+            None => (texture, None),
+        };
+
+        TextureSemantics {
+            kind: surface_kind(name),
+            animation,
+        }
+    }
+
+    // Classify by name alone, without the frames of any animation chain
+    pub fn from_name(texture: &str) -> TextureSemantics {
+        TextureSemantics::new(texture, &AnimationChains::default())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationChain {
+    pub frames: Vec<String>,
+    pub alternate_frames: Vec<String>,
+}
+
+// Animation chains keyed by lowercase base name
+#[derive(Debug, Clone, Default)]
+pub struct AnimationChains(pub HashMap<String, AnimationChain>);
+
+impl AnimationChains {
+    // Group "+N" textures into chains. Frames must count up from 0 or a, and the engine stops at
+    // the first missing one, so later frames are left out.
+    pub fn new<'a>(textures: impl IntoIterator<Item = &'a str>) -> AnimationChains {
+        let mut found: HashMap<String, [[Option<&str>; MAX_FRAMES as usize]; 2]> = HashMap::new();
+
+        for texture in textures {
+            if let Some((base, frame, alternate)) = animation_frame(texture) {
+                let frames = found.entry(base).or_default();
+                let slot = &mut frames[alternate as usize][frame as usize];
+                if slot.is_none() {
+                    *slot = Some(texture);
+                }
+            }
+        }
+
+        let contiguous = |frames: &[Option<&str>]| -> Vec<String> {
+            frames
+                .iter()
+                .map_while(|frame| frame.map(String::from))
+                .collect()
+        };
+
+        AnimationChains(
+            found
+                .into_iter()
+                .map(|(base, [frames, alternate_frames])| {
+                    let chain = AnimationChain {
+                        frames: contiguous(&frames),
+                        alternate_frames: contiguous(&alternate_frames),
+                    };
+                    (base, chain)
+                })
+                .collect(),
+        )
+    }
+}
+
+// Lowercase base name, frame number and whether it is an alternate frame, for "+0lava" style names
+fn animation_frame(texture: &str) -> Option<(String, u8, bool)> {
+    let mut chars = texture.chars();
+    if chars.next() != Some('+') {
+        return None;
+    }
+
+    let (frame, alternate) = match chars.next()?.to_ascii_lowercase() {
+        c @ '0'..='9' => (c as u8 - b'0', false),
+        c @ 'a'..='j' => (c as u8 - b'a', true),
+        _ => return None,
+    };
+
+    let base = chars.as_str();
+    if base.is_empty() {
+        return None;
+    }
+
+    Some((base.to_lowercase(), frame, alternate))
+}
+
+// Matches qbsp, which checks the name after '*' for lava and slime and treats the rest as water
+fn surface_kind(texture: &str) -> SurfaceKind {
+    let texture = texture.to_lowercase();
+
+    if let Some(liquid) = texture
+        .strip_prefix('*')
+        .or_else(|| texture.strip_prefix('!'))
+    {
+        let liquid = if liquid.starts_with("lava") {
+            Liquid::Lava
+        } else if liquid.starts_with("slime") {
+            Liquid::Slime
+        } else if liquid.starts_with("tele") {
+            Liquid::Teleport
+        } else {
+            Liquid::Water
+        };
+
+        return SurfaceKind::Liquid(liquid);
+    }
+
+    if texture.starts_with("sky") {
+        SurfaceKind::Sky
+    } else if texture.starts_with('{') {
+        SurfaceKind::Transparent
+    } else {
+        SurfaceKind::Solid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_names() {
+        let kinds: Vec<SurfaceKind> = [
+            "*lava1",
+            "*04mwat1",
+            "!slime",
+            "*teleport",
+            "SKY4",
+            "{grate",
+            "+0{fence",
+            "brick",
+        ]
+        .iter()
+        .map(|texture| TextureSemantics::from_name(texture).kind)
+        .collect();
+
+        assert!(
+            kinds
+                == vec![
+                    SurfaceKind::Liquid(Liquid::Lava),
+                    SurfaceKind::Liquid(Liquid::Water),
+                    SurfaceKind::Liquid(Liquid::Slime),
+                    SurfaceKind::Liquid(Liquid::Teleport),
+                    SurfaceKind::Sky,
+                    SurfaceKind::Transparent,
+                    SurfaceKind::Transparent,
+                    SurfaceKind::Solid,
+                ],
+            "Surface kinds {:?}",
+            kinds
+        );
+
+        assert!(TextureSemantics::from_name("+").animation.is_none());
+        assert!(TextureSemantics::from_name("+k_button").animation.is_none());
+    }
+
+    #[test]
+    fn group_animation_chains() {
+        let chains = AnimationChains::new(vec![
+            "+1button", "+0button", "+ABUTTON", "+3button", "+0button", "+bbutton", "+0lava",
+        ]);
+
+        let animation = TextureSemantics::new("+ABUTTON", &chains)
+            .animation
+            .expect("Animation");
+
+        // +3 is unreachable without a +2
+        assert!(
+            animation.base == "button"
+                && animation.frame == 0
+                && animation.alternate
+                && animation.frames == vec!["+0button", "+1button"]
+                && animation.alternate_frames == vec!["+ABUTTON", "+bbutton"],
+            "Animation {:?}",
+            animation
+        );
+
+        let animation = TextureSemantics::new("+0lava", &chains)
+            .animation
+            .expect("Animation");
+        assert!(
+            animation.frames == vec!["+0lava"] && animation.alternate_frames.is_empty(),
+            "Animation {:?}",
+            animation
+        );
+    }
+}
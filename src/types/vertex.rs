@@ -8,6 +8,7 @@ pub struct Vertex {
     pub normal: Vector3,
     pub tangent: (Vector3, f32),
     pub uv: Option<Vector2>,
+    pub uv2: Option<Vector2>,
     pub color: Option<Color>,
 }
 
@@ -17,6 +18,7 @@ impl Vertex {
         normal: Vector3,
         tangent: (Vector3, f32),
         uv: Option<Vector2>,
+        uv2: Option<Vector2>,
         color: Option<Color>,
     ) -> Vertex {
         Vertex {
@@ -24,6 +26,7 @@ impl Vertex {
             normal,
             tangent,
             uv,
+            uv2,
             color,
         }
     }
@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use crate::geo_builder::entity;
+use crate::{Vector2, Vector3};
+
+mod pack;
+
+const NORMAL_PRECISION: f32 = 1000.0;
+const DIST_PRECISION: f32 = 100.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnwrapSettings {
+    pub texel_density: f32,
+    pub padding: u32,
+}
+
+impl UnwrapSettings {
+    pub fn new(texel_density: f32, padding: u32) -> UnwrapSettings {
+        UnwrapSettings {
+            texel_density,
+            padding,
+        }
+    }
+}
+
+impl Default for UnwrapSettings {
+    fn default() -> Self {
+        // One texel per 16 units, matching Quake lightmaps
+        let texel_density = 1.0 / 16.0;
+        let padding = 2;
+
+        UnwrapSettings {
+            texel_density,
+            padding,
+        }
+    }
+}
+
+// Faces sharing a plane, projected into that plane's 2D basis
+struct Chart {
+    faces: Vec<(usize, usize)>,
+    u_axis: Vector3,
+    v_axis: Vector3,
+    min: Vector2,
+    max: Vector2,
+}
+
+type PlaneKey = (i64, i64, i64, i64);
+
+fn plane_key(normal: Vector3, dist: f32) -> PlaneKey {
+    (
+        (normal.x() * NORMAL_PRECISION).round() as i64,
+        (normal.y() * NORMAL_PRECISION).round() as i64,
+        (normal.z() * NORMAL_PRECISION).round() as i64,
+        (dist * DIST_PRECISION).round() as i64,
+    )
+}
+
+fn plane_axes(normal: Vector3) -> (Vector3, Vector3) {
+    let up = if normal.z().abs() > 0.9 {
+        Vector3::new(0.0, 1.0, 0.0)
+    } else {
+        Vector3::new(0.0, 0.0, 1.0)
+    };
+
+    let u_axis = up.cross(normal).normalize();
+    let v_axis = normal.cross(u_axis);
+
+    (u_axis, v_axis)
+}
+
+pub fn run(settings: &UnwrapSettings, entity_geometry: &mut [entity::Geometry]) {
+    for geometry in entity_geometry.iter_mut() {
+        unwrap_entity(settings, geometry);
+    }
+}
+
+fn unwrap_entity(settings: &UnwrapSettings, entity_geometry: &mut entity::Geometry) {
+    let charts = build_charts(entity_geometry);
+
+    let chart_sizes: Vec<(u32, u32)> = charts
+        .iter()
+        .map(|chart| {
+            let extent = (chart.max - chart.min) * settings.texel_density;
+            (
+                extent.x().ceil() as u32 + 1 + settings.padding * 2,
+                extent.y().ceil() as u32 + 1 + settings.padding * 2,
+            )
+        })
+        .collect();
+
+    // Faces too small for a chart still need uv2, or their whole surface would go without
+    for brush_geometry in entity_geometry.brush_geometry.iter_mut() {
+        for plane_geometry in brush_geometry.plane_geometry.iter_mut() {
+            if plane_geometry.vertices.len() < 3 {
+                for vertex in plane_geometry.vertices.iter_mut() {
+                    vertex.uv2 = Some(Vector2::zero());
+                }
+            }
+        }
+    }
+
+    let (atlas_size, rects) = pack::run(&chart_sizes);
+    if atlas_size == 0 {
+        return;
+    }
+
+    let atlas_size = atlas_size as f32;
+
+    for (chart, rect) in charts.iter().zip(rects.iter()) {
+        let offset = Vector2::new(
+            (rect.x + settings.padding) as f32,
+            (rect.y + settings.padding) as f32,
+        );
+
+        for (brush_idx, plane_idx) in &chart.faces {
+            let plane_geometry =
+                &mut entity_geometry.brush_geometry[*brush_idx].plane_geometry[*plane_idx];
+
+            for vertex in plane_geometry.vertices.iter_mut() {
+                let projected = Vector2::new(
+                    vertex.vertex.dot(chart.u_axis),
+                    vertex.vertex.dot(chart.v_axis),
+                );
+                let texel = offset + (projected - chart.min) * settings.texel_density;
+                vertex.uv2 = Some(texel / atlas_size);
+            }
+        }
+    }
+}
+
+fn build_charts(entity_geometry: &entity::Geometry) -> Vec<Chart> {
+    let mut chart_indices: HashMap<PlaneKey, usize> = HashMap::new();
+    let mut charts: Vec<Chart> = Vec::new();
+
+    for (brush_idx, brush_geometry) in entity_geometry.brush_geometry.iter().enumerate() {
+        for (plane_idx, plane_geometry) in brush_geometry.plane_geometry.iter().enumerate() {
+            if plane_geometry.vertices.len() < 3 {
+                continue;
+            }
+
+            let normal = plane_geometry.normal;
+            let dist = normal.dot(plane_geometry.center);
+
+            let chart_idx = *chart_indices
+                .entry(plane_key(normal, dist))
+                .or_insert_with(|| {
+                    let (u_axis, v_axis) = plane_axes(normal);
+                    charts.push(Chart {
+                        faces: Vec::new(),
+                        u_axis,
+                        v_axis,
+                        min: Vector2::new(f32::MAX, f32::MAX),
+                        max: Vector2::new(f32::MIN, f32::MIN),
+                    });
+                    charts.len() - 1
+                });
+
+            let chart = &mut charts[chart_idx];
+            chart.faces.push((brush_idx, plane_idx));
+
+            for vertex in &plane_geometry.vertices {
+                let projected = Vector2::new(
+                    vertex.vertex.dot(chart.u_axis),
+                    vertex.vertex.dot(chart.v_axis),
+                );
+                chart.min = chart.min.min(projected);
+                chart.max = chart.max.max(projected);
+            }
+        }
+    }
+
+    charts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::{
+        CollisionType, ComponentType, Entity, EntityType, GameData, Properties,
+        PropertyApplicationType, VisualType,
+    };
+    use crate::scene_tree::{SceneTreeNode, SceneTreeType, VisualGeometry};
+    use crate::{Config, TextureBlacklist, TextureInfo};
+
+    // Two cubes, the first with an extra plane that only touches its +x +y edge
+    const MAP: &str = r#"{
+"classname" "worldspawn"
+{
+( 0 0 0 ) ( 0 1 0 ) ( 0 0 1 ) wall 0 0 0 1 1
+( 0 0 0 ) ( 0 0 1 ) ( 1 0 0 ) wall 0 0 0 1 1
+( 0 0 0 ) ( 1 0 0 ) ( 0 1 0 ) wall 0 0 0 1 1
+( 16 16 16 ) ( 16 17 16 ) ( 17 16 16 ) wall 0 0 0 1 1
+( 16 16 16 ) ( 17 16 16 ) ( 16 16 17 ) wall 0 0 0 1 1
+( 16 16 16 ) ( 16 16 17 ) ( 16 17 16 ) wall 0 0 0 1 1
+( 16 16 0 ) ( 17 15 0 ) ( 16 16 1 ) wall 0 0 0 1 1
+}
+{
+( 32 0 0 ) ( 32 1 0 ) ( 32 0 1 ) floor 0 0 0 1 1
+( 32 0 0 ) ( 32 0 1 ) ( 33 0 0 ) floor 0 0 0 1 1
+( 32 0 0 ) ( 33 0 0 ) ( 32 1 0 ) floor 0 0 0 1 1
+( 96 32 8 ) ( 96 33 8 ) ( 97 32 8 ) floor 0 0 0 1 1
+( 96 32 8 ) ( 97 32 8 ) ( 96 32 9 ) floor 0 0 0 1 1
+( 96 32 8 ) ( 96 32 9 ) ( 96 33 8 ) wall 0 0 0 1 1
+}
+}
+"#;
+
+    fn surfaces(scene_tree: &[SceneTreeNode]) -> Vec<&crate::scene_tree::MeshSurface> {
+        scene_tree
+            .iter()
+            .flat_map(|node| match &node.data {
+                SceneTreeType::Actor(_, children) => surfaces(children),
+                SceneTreeType::VisualGeometry(VisualGeometry::Mesh(mesh)) => {
+                    mesh.surfaces.iter().collect()
+                }
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    // Whether two triangles share more than an edge or a corner
+    fn overlap(a: &[Vector2; 3], b: &[Vector2; 3]) -> bool {
+        let separated = |edges: &[Vector2; 3]| {
+            (0..3).any(|i| {
+                let edge = edges[(i + 1) % 3] - edges[i];
+                let axis = Vector2::new(-edge.y(), edge.x());
+                let project = |points: &[Vector2; 3]| {
+                    let dots: Vec<f32> = points.iter().map(|point| point.dot(axis)).collect();
+                    let min = dots.iter().cloned().fold(f32::MAX, f32::min);
+                    let max = dots.iter().cloned().fold(f32::MIN, f32::max);
+                    (min, max)
+                };
+                let ((a_min, a_max), (b_min, b_max)) = (project(a), project(b));
+                a_max <= b_min + 1e-6 || b_max <= a_min + 1e-6
+            })
+        };
+
+        !separated(a) && !separated(b)
+    }
+
+    #[test]
+    fn surfaces_get_uv2() {
+        let map_file =
+            std::env::temp_dir().join(format!("quarchitect-unwrap-{}.map", std::process::id()));
+        std::fs::write(&map_file, MAP).unwrap();
+
+        let mut textures = HashMap::new();
+        textures.insert("wall".to_string(), crate::Texture::new(16, 16));
+        textures.insert("floor".to_string(), crate::Texture::new(16, 16));
+
+        let game_data = GameData::new(
+            vec![Entity::brush(
+                "worldspawn".into(),
+                EntityType::class("StaticBody"),
+                ComponentType::None,
+                PropertyApplicationType::Properties,
+                Properties::default(),
+                VisualType::Mesh,
+                CollisionType::None,
+            )],
+            vec![],
+        );
+
+        let config = Config::new(
+            &map_file.to_string_lossy(),
+            TextureInfo(textures),
+            TextureBlacklist::default(),
+            crate::game_data::forge::GameData::default(),
+            game_data,
+        )
+        .with_uv_unwrap(UnwrapSettings::default());
+
+        let scene_tree = crate::run(config);
+        std::fs::remove_file(&map_file).ok();
+        let scene_tree = scene_tree.unwrap();
+
+        let surfaces = surfaces(&scene_tree);
+        assert!(surfaces.len() == 2, "Surface count {}", surfaces.len());
+
+        let mut triangles: Vec<[Vector2; 3]> = Vec::new();
+        for surface in surfaces {
+            let uv2 = match &surface.uv2 {
+                Some(uv2) => uv2,
+                None => panic!("No uv2 on surface {:?}", surface.texture),
+            };
+
+            assert!(
+                uv2.len() == surface.vertices.len()
+                    && uv2
+                        .iter()
+                        .all(|uv| (0.0..=1.0).contains(&uv.x()) && (0.0..=1.0).contains(&uv.y())),
+                "uv2 {:?}",
+                uv2
+            );
+
+            for triangle in surface.indices.chunks(3) {
+                triangles.push([uv2[triangle[0]], uv2[triangle[1]], uv2[triangle[2]]]);
+            }
+        }
+
+        for (i, a) in triangles.iter().enumerate() {
+            for b in &triangles[i + 1..] {
+                assert!(!overlap(a, b), "Overlapping uv2 triangles {:?} {:?}", a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+// Shelf-pack rectangles into the smallest square power-of-two atlas that fits them
+pub fn run(sizes: &[(u32, u32)]) -> (u32, Vec<Rect>) {
+    if sizes.is_empty() {
+        return (0, Vec::new());
+    }
+
+    let total_area: u64 = sizes
+        .iter()
+        .map(|(width, height)| *width as u64 * *height as u64)
+        .sum();
+
+    let widest = sizes.iter().map(|(width, _)| *width).max().unwrap_or(1);
+
+    let mut atlas_size = ((total_area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .max(1)
+        .next_power_of_two();
+
+    loop {
+        if let Some(rects) = pack_shelves(sizes, atlas_size) {
+            return (atlas_size, rects);
+        }
+        atlas_size *= 2;
+    }
+}
+
+fn pack_shelves(sizes: &[(u32, u32)], atlas_size: u32) -> Option<Vec<Rect>> {
+    // Place tallest rectangles first to keep shelves tight
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| {
+        sizes[*b]
+            .1
+            .cmp(&sizes[*a].1)
+            .then(sizes[*b].0.cmp(&sizes[*a].0))
+    });
+
+    let mut rects = vec![Rect::new(0, 0, 0, 0); sizes.len()];
+
+    let mut shelf_x = 0;
+    let mut shelf_y = 0;
+    let mut shelf_height = 0;
+
+    for i in order {
+        let (width, height) = sizes[i];
+
+        if shelf_x + width > atlas_size {
+            shelf_y += shelf_height;
+            shelf_x = 0;
+            shelf_height = 0;
+        }
+
+        if shelf_y + height > atlas_size {
+            return None;
+        }
+
+        rects[i] = Rect::new(shelf_x, shelf_y, width, height);
+
+        shelf_x += width;
+        shelf_height = shelf_height.max(height);
+    }
+
+    Some(rects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_overlap() {
+        let sizes = vec![(10, 4), (3, 9), (16, 16), (1, 1), (7, 7), (12, 2)];
+        let (atlas_size, rects) = run(&sizes);
+
+        for (i, a) in rects.iter().enumerate() {
+            assert!(
+                a.x + a.width <= atlas_size && a.y + a.height <= atlas_size,
+                "Rect {:?} exceeds atlas size {}",
+                a,
+                atlas_size
+            );
+
+            for b in rects.iter().skip(i + 1) {
+                let overlap = a.x < b.x + b.width
+                    && b.x < a.x + a.width
+                    && a.y < b.y + b.height
+                    && b.y < a.y + a.height;
+                assert!(!overlap, "Rect {:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::Vector3;
+
+const LEAF_SIZE: usize = 4;
+const RAY_EPSILON: f32 = 0.000_1;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Triangle {
+    pub v0: Vector3,
+    pub v1: Vector3,
+    pub v2: Vector3,
+}
+
+impl Triangle {
+    pub fn new(v0: Vector3, v1: Vector3, v2: Vector3) -> Triangle {
+        Triangle { v0, v1, v2 }
+    }
+
+    fn center(&self) -> Vector3 {
+        (self.v0 + self.v1 + self.v2) / 3.0
+    }
+
+    // Möller–Trumbore intersection, returning the hit distance along the ray
+    fn intersect(&self, origin: Vector3, direction: Vector3) -> Option<f32> {
+        let edge1 = self.v1 - self.v0;
+        let edge2 = self.v2 - self.v0;
+        let p = direction.cross(edge2);
+        let det = edge1.dot(p);
+
+        if det.abs() < RAY_EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let t_vec = origin - self.v0;
+        let u = t_vec.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t_vec.cross(edge1);
+        let v = direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        if t > RAY_EPSILON {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Bounds {
+    min: Vector3,
+    max: Vector3,
+}
+
+impl Bounds {
+    fn empty() -> Bounds {
+        Bounds {
+            min: Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Vector3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    fn grow(self, triangle: &Triangle) -> Bounds {
+        Bounds {
+            min: self.min.min(triangle.v0).min(triangle.v1).min(triangle.v2),
+            max: self.max.max(triangle.v0).max(triangle.v1).max(triangle.v2),
+        }
+    }
+
+    // Slab test against the ray segment [0, max_distance]
+    fn hit(&self, origin: Vector3, inv_direction: Vector3, max_distance: f32) -> bool {
+        let t0 = (self.min - origin) * inv_direction;
+        let t1 = (self.max - origin) * inv_direction;
+
+        let t_near = t0.min(t1);
+        let t_far = t0.max(t1);
+
+        let t_enter = t_near.x().max(t_near.y()).max(t_near.z()).max(0.0);
+        let t_exit = t_far.x().min(t_far.y()).min(t_far.z()).min(max_distance);
+
+        t_enter <= t_exit
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    Leaf(Bounds, Vec<Triangle>),
+    Branch(Bounds, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug)]
+pub struct Bvh {
+    root: Option<Node>,
+}
+
+impl Bvh {
+    pub fn new(triangles: Vec<Triangle>) -> Bvh {
+        let root = if triangles.is_empty() {
+            None
+        } else {
+            Some(build_node(triangles))
+        };
+
+        Bvh { root }
+    }
+
+    // Whether anything lies between the origin and max_distance along the direction
+    pub fn occluded(&self, origin: Vector3, direction: Vector3, max_distance: f32) -> bool {
+        let inv_direction = Vector3::new(
+            1.0 / direction.x(),
+            1.0 / direction.y(),
+            1.0 / direction.z(),
+        );
+
+        match &self.root {
+            Some(root) => node_occluded(root, origin, direction, inv_direction, max_distance),
+            None => false,
+        }
+    }
+}
+
+fn build_node(mut triangles: Vec<Triangle>) -> Node {
+    let bounds = triangles.iter().fold(Bounds::empty(), Bounds::grow);
+
+    if triangles.len() <= LEAF_SIZE {
+        return Node::Leaf(bounds, triangles);
+    }
+
+    // Split at the median along the longest axis
+    let extent = bounds.max - bounds.min;
+    let axis = if extent.x() >= extent.y() && extent.x() >= extent.z() {
+        0
+    } else if extent.y() >= extent.z() {
+        1
+    } else {
+        2
+    };
+
+    let key = |triangle: &Triangle| {
+        let center = triangle.center();
+        match axis {
+            0 => center.x(),
+            1 => center.y(),
+            _ => center.z(),
+        }
+    };
+
+    triangles.sort_by(|a, b| {
+        key(a)
+            .partial_cmp(&key(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let right = triangles.split_off(triangles.len() / 2);
+
+    Node::Branch(
+        bounds,
+        Box::new(build_node(triangles)),
+        Box::new(build_node(right)),
+    )
+}
+
+fn node_occluded(
+    node: &Node,
+    origin: Vector3,
+    direction: Vector3,
+    inv_direction: Vector3,
+    max_distance: f32,
+) -> bool {
+    match node {
+        Node::Leaf(bounds, triangles) => {
+            bounds.hit(origin, inv_direction, max_distance)
+                && triangles
+                    .iter()
+                    .any(|triangle| match triangle.intersect(origin, direction) {
+                        Some(t) => t < max_distance,
+                        None => false,
+                    })
+        }
+        Node::Branch(bounds, left, right) => {
+            bounds.hit(origin, inv_direction, max_distance)
+                && (node_occluded(left, origin, direction, inv_direction, max_distance)
+                    || node_occluded(right, origin, direction, inv_direction, max_distance))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad_bvh() -> Bvh {
+        let v0 = Vector3::new(-1.0, -1.0, 1.0);
+        let v1 = Vector3::new(1.0, -1.0, 1.0);
+        let v2 = Vector3::new(1.0, 1.0, 1.0);
+        let v3 = Vector3::new(-1.0, 1.0, 1.0);
+
+        let mut triangles = vec![Triangle::new(v0, v1, v2), Triangle::new(v0, v2, v3)];
+
+        // Pad with distant geometry so the tree has branches
+        for i in 0..16 {
+            let offset = Vector3::new(100.0 + i as f32 * 4.0, 0.0, 0.0);
+            triangles.push(Triangle::new(v0 + offset, v1 + offset, v2 + offset));
+        }
+
+        Bvh::new(triangles)
+    }
+
+    #[test]
+    fn occluded() {
+        let bvh = quad_bvh();
+        let origin = Vector3::new(0.0, 0.0, 0.0);
+        let up = Vector3::new(0.0, 0.0, 1.0);
+
+        assert!(bvh.occluded(origin, up, 10.0), "Ray should hit quad");
+        assert!(
+            !bvh.occluded(origin, up, 0.5),
+            "Ray should stop short of quad"
+        );
+        assert!(!bvh.occluded(origin, -up, 10.0), "Ray should miss quad");
+    }
+}
@@ -0,0 +1,89 @@
+use crate::map::quake::Entity;
+use crate::{Color, Vector3};
+
+const DEFAULT_LIGHT: f32 = 300.0;
+const DEFAULT_WAIT: f32 = 1.0;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Light {
+    pub origin: Vector3,
+    pub light: f32,
+    pub color: Color,
+    pub wait: f32,
+}
+
+impl Light {
+    // Quake linear falloff, where 255 is full brightness
+    pub fn intensity(&self, distance: f32) -> f32 {
+        ((self.light - distance * self.wait) / 255.0).max(0.0)
+    }
+
+    // Distance past which the light contributes nothing
+    pub fn range(&self) -> f32 {
+        if self.wait > 0.0 {
+            self.light / self.wait
+        } else {
+            f32::MAX
+        }
+    }
+}
+
+fn parse_vector3(value: &str) -> Option<Vector3> {
+    let comps: Vec<f32> = value
+        .split_whitespace()
+        .map(|comp| comp.parse::<f32>().ok())
+        .collect::<Option<Vec<f32>>>()?;
+
+    match comps.as_slice() {
+        [x, y, z] => Some(Vector3::new(*x, *y, *z)),
+        _ => None,
+    }
+}
+
+// '_color' may be normalized or 0-255, as in ericw-tools
+fn parse_color(value: &str) -> Option<Color> {
+    let color = parse_vector3(value)?;
+    let color = if color.x() > 1.0 || color.y() > 1.0 || color.z() > 1.0 {
+        color / 255.0
+    } else {
+        color
+    };
+
+    Some(Color::new(color.x(), color.y(), color.z()))
+}
+
+pub fn gather(entities: &[Entity]) -> Vec<Light> {
+    entities
+        .iter()
+        .filter(|entity| match entity.get_property("classname") {
+            Some(classname) => classname.starts_with("light"),
+            None => false,
+        })
+        .filter(|entity| entity.brushes.is_empty())
+        .flat_map(|entity| {
+            let origin = parse_vector3(entity.get_property("origin")?)?;
+
+            let light = entity
+                .get_property("light")
+                .and_then(|light| light.parse::<f32>().ok())
+                .unwrap_or(DEFAULT_LIGHT);
+
+            let color = entity
+                .get_property("_color")
+                .and_then(parse_color)
+                .unwrap_or_default();
+
+            let wait = entity
+                .get_property("wait")
+                .and_then(|wait| wait.parse::<f32>().ok())
+                .unwrap_or(DEFAULT_WAIT);
+
+            Some(Light {
+                origin,
+                light,
+                color,
+                wait,
+            })
+        })
+        .collect()
+}
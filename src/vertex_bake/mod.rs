@@ -0,0 +1,419 @@
+use rayon::prelude::*;
+
+use crate::game_data::{EntityData, GameData, VisualType};
+use crate::geo_builder::entity;
+use crate::map::quake::Entity;
+use crate::{Color, TextureBlacklist, Vector3};
+
+mod bvh;
+mod lights;
+
+use bvh::{Bvh, Triangle};
+use lights::Light;
+
+const SURFACE_OFFSET: f32 = 0.01;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BakeSettings {
+    pub ao_samples: u32,
+    pub ao_distance: f32,
+    pub ambient: Color,
+    pub direct_light: bool,
+}
+
+impl BakeSettings {
+    pub fn new(
+        ao_samples: u32,
+        ao_distance: f32,
+        ambient: Color,
+        direct_light: bool,
+    ) -> BakeSettings {
+        BakeSettings {
+            ao_samples,
+            ao_distance,
+            ambient,
+            direct_light,
+        }
+    }
+}
+
+impl Default for BakeSettings {
+    fn default() -> Self {
+        let ao_samples = 32;
+        let ao_distance = 64.0;
+        let ambient = Color::default();
+        let direct_light = false;
+
+        BakeSettings {
+            ao_samples,
+            ao_distance,
+            ambient,
+            direct_light,
+        }
+    }
+}
+
+pub fn run(
+    settings: &BakeSettings,
+    texture_blacklist: &TextureBlacklist,
+    quarchitect_game_data: &GameData,
+    entities: &[Entity],
+    entity_geometry: &mut [entity::Geometry],
+) {
+    let bvh = Bvh::new(gather_occluders(
+        texture_blacklist,
+        quarchitect_game_data,
+        entities,
+        entity_geometry,
+    ));
+
+    let lights = if settings.direct_light {
+        lights::gather(entities)
+    } else {
+        Vec::new()
+    };
+
+    let directions = hemisphere_samples(settings.ao_samples);
+
+    entity_geometry.par_iter_mut().for_each(|geometry| {
+        for brush_geometry in geometry.brush_geometry.iter_mut() {
+            for plane_geometry in brush_geometry.plane_geometry.iter_mut() {
+                let face_normal = plane_geometry.normal;
+                for vertex in plane_geometry.vertices.iter_mut() {
+                    let origin = vertex.vertex + face_normal * SURFACE_OFFSET;
+                    let baked =
+                        bake_vertex(settings, &bvh, &lights, &directions, origin, vertex.normal);
+
+                    vertex.color = Some(match vertex.color {
+                        Some(color) => {
+                            Color::new(color.r * baked.r, color.g * baked.g, color.b * baked.b)
+                        }
+                        None => baked,
+                    });
+                }
+            }
+        }
+    });
+}
+
+// Only brushes that are drawn block light, so triggers and other invisible classes don't
+fn casts_shadows(quarchitect_game_data: &GameData, entity: &Entity) -> bool {
+    let classname = entity.get_property("classname").unwrap_or_default();
+
+    quarchitect_game_data
+        .entities
+        .iter()
+        .find(|entity_definition| entity_definition.classname == classname)
+        .is_some_and(|entity_definition| match &entity_definition.data {
+            EntityData::Brush(_, brush_data) => brush_data.visual_type == VisualType::Mesh,
+            EntityData::Point(_) => false,
+        })
+}
+
+// Triangulate every face that should cast shadows
+fn gather_occluders(
+    texture_blacklist: &TextureBlacklist,
+    quarchitect_game_data: &GameData,
+    entities: &[Entity],
+    entity_geometry: &[entity::Geometry],
+) -> Vec<Triangle> {
+    entities
+        .iter()
+        .zip(entity_geometry.iter())
+        .filter(|(entity, _)| casts_shadows(quarchitect_game_data, entity))
+        .flat_map(|(entity, geometry)| {
+            entity
+                .brushes
+                .iter()
+                .zip(geometry.brush_geometry.iter())
+                .filter(|(brush, _)| {
+                    !brush
+                        .planes
+                        .iter()
+                        .any(|plane| texture_blacklist.is_blacklisted_brush(&plane.texture))
+                })
+                .flat_map(|(brush, brush_geometry)| {
+                    brush
+                        .planes
+                        .iter()
+                        .zip(brush_geometry.plane_geometry.iter())
+                        .filter(|(plane, _)| {
+                            !texture_blacklist.is_blacklisted_plane(&plane.texture)
+                        })
+                        .flat_map(|(_, plane_geometry)| {
+                            plane_geometry.indices.chunks(3).map(move |triangle| {
+                                Triangle::new(
+                                    plane_geometry.vertices[triangle[0]].vertex,
+                                    plane_geometry.vertices[triangle[1]].vertex,
+                                    plane_geometry.vertices[triangle[2]].vertex,
+                                )
+                            })
+                        })
+                })
+        })
+        .collect()
+}
+
+fn bake_vertex(
+    settings: &BakeSettings,
+    bvh: &Bvh,
+    lights: &[Light],
+    directions: &[Vector3],
+    origin: Vector3,
+    normal: Vector3,
+) -> Color {
+    let ao = ambient_occlusion(settings, bvh, directions, origin, normal);
+
+    let direct = lights
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |acc, light| {
+            acc + direct_light(bvh, light, origin, normal)
+        });
+
+    Color::new(
+        (settings.ambient.r * ao + direct.x()).min(1.0),
+        (settings.ambient.g * ao + direct.y()).min(1.0),
+        (settings.ambient.b * ao + direct.z()).min(1.0),
+    )
+}
+
+fn ambient_occlusion(
+    settings: &BakeSettings,
+    bvh: &Bvh,
+    directions: &[Vector3],
+    origin: Vector3,
+    normal: Vector3,
+) -> f32 {
+    if directions.is_empty() {
+        return 1.0;
+    }
+
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    let unoccluded = directions
+        .iter()
+        .filter(|direction| {
+            let direction =
+                tangent * direction.x() + bitangent * direction.y() + normal * direction.z();
+            !bvh.occluded(origin, direction, settings.ao_distance)
+        })
+        .count();
+
+    unoccluded as f32 / directions.len() as f32
+}
+
+fn direct_light(bvh: &Bvh, light: &Light, origin: Vector3, normal: Vector3) -> Vector3 {
+    let to_light = light.origin - origin;
+    let distance = to_light.length();
+
+    if distance == 0.0 || distance > light.range() {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    let direction = to_light / distance;
+    let lambert = normal.dot(direction);
+    if lambert <= 0.0 || bvh.occluded(origin, direction, distance) {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    let intensity = light.intensity(distance) * lambert;
+    Vector3::new(light.color.r, light.color.g, light.color.b) * intensity
+}
+
+fn orthonormal_basis(normal: Vector3) -> (Vector3, Vector3) {
+    let up = if normal.z().abs() > 0.9 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        Vector3::new(0.0, 0.0, 1.0)
+    };
+
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+
+    (tangent, bitangent)
+}
+
+// Deterministic cosine-weighted hemisphere directions around +Z, from a Hammersley set
+fn hemisphere_samples(count: u32) -> Vec<Vector3> {
+    (0..count)
+        .map(|i| {
+            let u = (i as f32 + 0.5) / count as f32;
+            let v = i.reverse_bits() as f32 / 4_294_967_296.0;
+
+            let radius = u.sqrt();
+            let theta = 2.0 * std::f32::consts::PI * v;
+
+            Vector3::new(
+                radius * theta.cos(),
+                radius * theta.sin(),
+                (1.0 - u).max(0.0).sqrt(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_data::{
+        CollisionType, ComponentType, EntityType, Properties, PropertyApplicationType,
+    };
+    use std::collections::HashMap;
+
+    // Axis-aligned box brush from min to max
+    fn cube(min: [i32; 3], max: [i32; 3]) -> String {
+        let [a, b, c] = min;
+        let [x, y, z] = max;
+        format!(
+            "{{\n\
+             ( {a} {b} {c} ) ( {a} {b1} {c} ) ( {a} {b} {c1} ) floor 0 0 0 1 1\n\
+             ( {a} {b} {c} ) ( {a} {b} {c1} ) ( {a1} {b} {c} ) floor 0 0 0 1 1\n\
+             ( {a} {b} {c} ) ( {a1} {b} {c} ) ( {a} {b1} {c} ) floor 0 0 0 1 1\n\
+             ( {x} {y} {z} ) ( {x} {y1} {z} ) ( {x1} {y} {z} ) floor 0 0 0 1 1\n\
+             ( {x} {y} {z} ) ( {x1} {y} {z} ) ( {x} {y} {z1} ) floor 0 0 0 1 1\n\
+             ( {x} {y} {z} ) ( {x} {y} {z1} ) ( {x} {y1} {z} ) floor 0 0 0 1 1\n\
+             }}\n",
+            a = a,
+            b = b,
+            c = c,
+            a1 = a + 1,
+            b1 = b + 1,
+            c1 = c + 1,
+            x = x,
+            y = y,
+            z = z,
+            x1 = x + 1,
+            y1 = y + 1,
+            z1 = z + 1,
+        )
+    }
+
+    fn brush_class(classname: &str, visual_type: VisualType) -> crate::game_data::Entity {
+        crate::game_data::Entity::brush(
+            classname.into(),
+            EntityType::Placeholder,
+            ComponentType::None,
+            PropertyApplicationType::Properties,
+            Properties::default(),
+            visual_type,
+            CollisionType::None,
+        )
+    }
+
+    // Baked colors of the upward faces of worldspawn's first brush, by vertex position
+    fn bake_floor(map: &str, settings: &BakeSettings) -> Vec<(Vector3, Color)> {
+        let tokens = crate::map::quake::tokenizer::run(map.into());
+        let (_token_paths, entities) = crate::map::quake::parser::run(&tokens).unwrap();
+
+        let mut textures = HashMap::new();
+        textures.insert("floor".to_string(), crate::Texture::new(16, 16));
+
+        let mut entity_geometry = crate::geo_builder::run(
+            &crate::TextureInfo(textures),
+            &TextureBlacklist::default(),
+            &entities,
+            &crate::Diagnostics::default(),
+        );
+
+        let game_data = GameData::new(
+            vec![
+                brush_class("worldspawn", VisualType::Mesh),
+                brush_class("trigger_multiple", VisualType::None),
+            ],
+            vec![],
+        );
+
+        run(
+            settings,
+            &TextureBlacklist::default(),
+            &game_data,
+            &entities,
+            &mut entity_geometry,
+        );
+
+        entity_geometry[0].brush_geometry[0]
+            .plane_geometry
+            .iter()
+            .filter(|plane_geometry| plane_geometry.normal.z() > 0.9)
+            .flat_map(|plane_geometry| plane_geometry.vertices.iter())
+            .map(|vertex| (vertex.vertex, vertex.color.unwrap()))
+            .collect()
+    }
+
+    fn color_at(colors: &[(Vector3, Color)], position: Vector3) -> Color {
+        colors
+            .iter()
+            .find(|(vertex, _)| (*vertex - position).length() < 0.01)
+            .map(|(_, color)| *color)
+            .unwrap_or_else(|| panic!("No vertex at {:?} in {:?}", position, colors))
+    }
+
+    #[test]
+    fn occluded_corner() {
+        // A floor against a wall, with and without a trigger hanging over the open end
+        let floor = format!(
+            "{{\n\"classname\" \"worldspawn\"\n{}{}}}\n",
+            cube([16, 0, -16], [128, 128, 0]),
+            cube([0, 0, -16], [16, 128, 128]),
+        );
+        let trigger = format!(
+            "{{\n\"classname\" \"trigger_multiple\"\n{}}}\n",
+            cube([96, 96, 8], [128, 128, 16]),
+        );
+
+        let settings = BakeSettings::new(64, 256.0, Color::new(1.0, 1.0, 1.0), false);
+        let colors = bake_floor(&floor, &settings);
+        let triggered_colors = bake_floor(&(floor.clone() + &trigger), &settings);
+
+        let corner = color_at(&colors, Vector3::new(16.0, 0.0, 0.0));
+        let open = color_at(&colors, Vector3::new(128.0, 128.0, 0.0));
+        let triggered = color_at(&triggered_colors, Vector3::new(128.0, 128.0, 0.0));
+
+        assert!(
+            corner.r < 0.8 && open.r > 0.9 && corner.r < open.r,
+            "Corner {:?}, open {:?}",
+            corner,
+            open
+        );
+        assert!(
+            triggered == open,
+            "Trigger shadowed {:?}, open {:?}",
+            triggered,
+            open
+        );
+    }
+
+    #[test]
+    fn point_light() {
+        let map = format!(
+            "{{\n\"classname\" \"worldspawn\"\n{}}}\n\
+             {{\n\"classname\" \"light\"\n\"origin\" \"16 16 32\"\n\"light\" \"200\"\n\"_color\" \"255 128 0\"\n}}\n",
+            cube([0, 0, -16], [64, 256, 0]),
+        );
+
+        let settings = BakeSettings::new(0, 0.0, Color::new(0.0, 0.0, 0.0), true);
+        let colors = bake_floor(&map, &settings);
+
+        let near = color_at(&colors, Vector3::new(0.0, 0.0, 0.0));
+        let far = color_at(&colors, Vector3::new(64.0, 0.0, 0.0));
+        let out_of_range = color_at(&colors, Vector3::new(0.0, 256.0, 0.0));
+
+        assert!(
+            near.r > 0.0 && (near.g / near.r - 128.0 / 255.0).abs() < 0.01 && near.b == 0.0,
+            "Near {:?}",
+            near
+        );
+        assert!(
+            far.r > 0.0 && far.r < near.r,
+            "Near {:?}, far {:?}",
+            near,
+            far
+        );
+        assert!(
+            out_of_range == Color::new(0.0, 0.0, 0.0),
+            "Out of range {:?}",
+            out_of_range
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+mod pak;
+#[cfg(feature = "pk3")]
+mod pk3;
+
+// A file on disk, or the contents of a file stored inside a .pak or .pk3 archive
+#[derive(Debug, Clone)]
+pub enum VfsFile {
+    Disk(PathBuf),
+    Archived(Arc<[u8]>),
+}
+
+// Each reader has its own cursor, so parallel readers of one file never interfere
+pub enum VfsReader {
+    Disk(File),
+    Archived(Cursor<Arc<[u8]>>),
+}
+
+impl VfsFile {
+    pub fn reader(&self) -> Result<VfsReader, String> {
+        match self {
+            VfsFile::Disk(path) => match File::open(path) {
+                Ok(file) => Ok(VfsReader::Disk(file)),
+                Err(err) => Err(format!("Error opening {:?}: {:?}", path, err)),
+            },
+            VfsFile::Archived(data) => Ok(VfsReader::Archived(Cursor::new(data.clone()))),
+        }
+    }
+
+    pub fn read(&self) -> Result<Vec<u8>, String> {
+        match self {
+            VfsFile::Disk(path) => match std::fs::read(path) {
+                Ok(data) => Ok(data),
+                Err(err) => Err(format!("Error reading {:?}: {:?}", path, err)),
+            },
+            VfsFile::Archived(data) => Ok(data.to_vec()),
+        }
+    }
+}
+
+impl Read for VfsReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            VfsReader::Disk(file) => file.read(buf),
+            VfsReader::Archived(cursor) => cursor.read(buf),
+        }
+    }
+}
+
+impl Seek for VfsReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        match self {
+            VfsReader::Disk(file) => file.seek(pos),
+            VfsReader::Archived(cursor) => cursor.seek(pos),
+        }
+    }
+}
+
+// Open a path on disk, or a path that continues inside an archive such as
+// "id1/pak0.pak/gfx.wad" or "baseq3/textures.pk3/textures/base/wall.tga"
+pub fn open(path: &str) -> Result<VfsFile, String> {
+    let path = Path::new(path);
+    if path.is_file() {
+        return Ok(VfsFile::Disk(path.to_path_buf()));
+    }
+
+    for archive in path.ancestors().skip(1) {
+        if !archive.is_file() {
+            continue;
+        }
+
+        let name: Vec<String> = path
+            .strip_prefix(archive)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+
+        let data = read_archived(archive, &name.join("/"))?;
+        return Ok(VfsFile::Archived(data.into()));
+    }
+
+    Err(format!("No such file {:?}", path))
+}
+
+// Directories and archives searched by file name, later mounts taking precedence
+#[derive(Debug, Clone, Default)]
+pub struct Vfs {
+    mounts: Vec<Mount>,
+}
+
+#[derive(Debug, Clone)]
+enum Mount {
+    Directory(PathBuf),
+    // Archive path and the names of every file inside it
+    Archive(PathBuf, Vec<String>),
+}
+
+impl Vfs {
+    pub fn new() -> Vfs {
+        Vfs::default()
+    }
+
+    pub fn mount(&mut self, path: &str) -> Result<(), String> {
+        let path = PathBuf::from(path);
+
+        let mount = if path.is_dir() {
+            Mount::Directory(path)
+        } else if path.is_file() {
+            let names = list_archive(&path)?;
+            Mount::Archive(path, names)
+        } else {
+            return Err(format!("No such directory or archive {:?}", path));
+        };
+
+        self.mounts.push(mount);
+        Ok(())
+    }
+
+    // A path for the named file that `open` and the WAD readers accept
+    pub fn find(&self, name: &str) -> Option<String> {
+        let name = name.replace('\\', "/");
+        let name = name.trim_start_matches('/');
+
+        self.mounts.iter().rev().find_map(|mount| match mount {
+            Mount::Directory(directory) => {
+                let path = directory.join(name);
+                if path.is_file() {
+                    Some(path.to_string_lossy().into_owned())
+                } else {
+                    None
+                }
+            }
+            Mount::Archive(archive, names) => names
+                .iter()
+                .find(|archived| archived.eq_ignore_ascii_case(name))
+                .map(|archived| archive.join(archived).to_string_lossy().into_owned()),
+        })
+    }
+
+    pub fn open(&self, name: &str) -> Result<VfsFile, String> {
+        match self.find(name) {
+            Some(path) => open(&path),
+            None => Err(format!(
+                "{} not found in any mounted directory or archive",
+                name
+            )),
+        }
+    }
+}
+
+fn archive_extension(archive: &Path) -> Option<String> {
+    archive
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+fn list_archive(archive: &Path) -> Result<Vec<String>, String> {
+    match archive_extension(archive).as_deref() {
+        Some("pak") => Ok(pak::read_directory(archive)?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect()),
+        #[cfg(feature = "pk3")]
+        Some("pk3") => pk3::file_names(archive),
+        #[cfg(not(feature = "pk3"))]
+        Some("pk3") => Err(format!("Reading {:?} requires the pk3 feature", archive)),
+        _ => Err(format!("{:?} is not a .pak or .pk3 archive", archive)),
+    }
+}
+
+// Archived names are matched case-insensitively, as Quake engines do
+fn read_archived(archive: &Path, name: &str) -> Result<Vec<u8>, String> {
+    let not_found = || format!("{} not found in {:?}", name, archive);
+
+    match archive_extension(archive).as_deref() {
+        Some("pak") => {
+            let directory = pak::read_directory(archive)?;
+            match directory
+                .iter()
+                .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            {
+                Some(entry) => pak::read(archive, entry),
+                None => Err(not_found()),
+            }
+        }
+        #[cfg(feature = "pk3")]
+        Some("pk3") => {
+            let names = pk3::file_names(archive)?;
+            match names
+                .iter()
+                .find(|archived| archived.eq_ignore_ascii_case(name))
+            {
+                Some(archived) => pk3::read(archive, archived),
+                None => Err(not_found()),
+            }
+        }
+        #[cfg(not(feature = "pk3"))]
+        Some("pk3") => Err(format!("Reading {:?} requires the pk3 feature", archive)),
+        _ => Err(format!("{:?} is not a .pak or .pk3 archive", archive)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wad;
+
+    fn fixture(path: &str) -> String {
+        format!("{}/src/vfs/test_data/{}", env!("CARGO_MANIFEST_DIR"), path)
+    }
+
+    #[test]
+    fn read_through_pak() {
+        let textures = wad::read_textures(&fixture("id1.pak/gfx/base.wad"), None, 4).unwrap();
+        assert!(textures.len() == 2, "Texture count {}", textures.len());
+
+        let headers = wad::read_texture_headers(&fixture("id1.pak/GFX/BASE.WAD"), None).unwrap();
+        let names: Vec<&str> = headers.iter().map(|header| header.name.as_str()).collect();
+        assert!(
+            names == vec!["first", "second"],
+            "Texture names {:?}",
+            names
+        );
+
+        let wad::palette::Palette(palette) =
+            wad::palette::read_palette(&fixture("id1.pak/gfx/palette.lmp")).unwrap();
+        let wad::Color(r, g, b) = palette[3];
+        assert!((r, g, b) == (3, 0, 252), "Palette entry {:?}", (r, g, b));
+
+        let err = open(&fixture("id1.pak/gfx/missing.wad")).unwrap_err();
+        assert!(err.contains("gfx/missing.wad not found"), "Error {:?}", err);
+    }
+
+    #[cfg(feature = "pk3")]
+    #[test]
+    fn read_through_pk3() {
+        let textures =
+            wad::read_textures(&fixture("textures.pk3/textures/base.wad"), None, 1).unwrap();
+        assert!(
+            textures.len() == 2 && textures[0].palette.is_some(),
+            "Texture count {}",
+            textures.len()
+        );
+    }
+
+    #[test]
+    fn find_by_name() {
+        let mut vfs = Vfs::new();
+        vfs.mount(&fixture("")).unwrap();
+        vfs.mount(&fixture("id1.pak")).unwrap();
+
+        let palette = vfs.find("gfx\\Palette.lmp");
+        assert!(
+            palette
+                == Some(
+                    Path::new(&fixture("id1.pak"))
+                        .join("gfx/palette.lmp")
+                        .to_string_lossy()
+                        .into_owned()
+                ),
+            "Palette path {:?}",
+            palette
+        );
+
+        assert!(vfs.find("id1.pak").is_some());
+        assert!(vfs.find("gfx/missing.wad").is_none());
+
+        let readme = vfs.open("readme.txt").unwrap().read().unwrap();
+        assert!(readme == b"pak fixture\n", "Readme {:?}", readme);
+
+        assert!(vfs.mount(&fixture("missing")).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use nom::{
+    character::complete::anychar,
+    number::complete::{le_u32, le_u8},
+    IResult,
+};
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+pub const HEADER_BYTES: usize = 12;
+pub const ENTRY_BYTES: usize = 64;
+
+#[derive(Debug)]
+pub struct Header {
+    pub magic: [char; 4],
+    pub dir_offset: u32,
+    pub dir_size: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+pub fn header_parser(i: &[u8]) -> IResult<&[u8], Header> {
+    let (i, o) = nom::sequence::tuple((nom::multi::count(anychar, 4), le_u32, le_u32))(i)?;
+
+    let (magic, dir_offset, dir_size) = o;
+
+    let mut magic_arr = ['\0'; 4];
+    magic_arr.copy_from_slice(&magic[..]);
+
+    Ok((
+        i,
+        Header {
+            magic: magic_arr,
+            dir_offset,
+            dir_size,
+        },
+    ))
+}
+
+pub fn entry_parser(i: &[u8]) -> IResult<&[u8], Entry> {
+    let (i, o) = nom::sequence::tuple((nom::multi::count(le_u8, 56), le_u32, le_u32))(i)?;
+
+    let (name, offset, size) = o;
+
+    let name: String = name
+        .into_iter()
+        .take_while(|c| *c != 0)
+        .map(char::from)
+        .collect();
+
+    Ok((i, Entry { name, offset, size }))
+}
+
+pub fn read_directory(pak_file: &Path) -> Result<Vec<Entry>, String> {
+    let mut file = match File::open(pak_file) {
+        Ok(pak_file) => pak_file,
+        Err(err) => return Err(format!("Error opening PAK file {:?}: {:?}", pak_file, err)),
+    };
+
+    let mut header_buf = [0u8; HEADER_BYTES];
+    if let Err(err) = file.read_exact(&mut header_buf) {
+        return Err(format!("Error reading PAK header: {:?}", err));
+    }
+
+    let header = match header_parser(&header_buf) {
+        Ok((_, header)) => header,
+        Err(err) => return Err(format!("Error parsing PAK header: {:?}", err)),
+    };
+
+    if header.magic != ['P', 'A', 'C', 'K'] {
+        return Err(format!("Unexpected PAK magic: {:?}", header.magic));
+    }
+
+    if let Err(err) = file.seek(std::io::SeekFrom::Start(header.dir_offset as u64)) {
+        return Err(format!("Error seeking to PAK directory: {:?}", err));
+    }
+
+    let mut directory_buf = vec![0u8; header.dir_size as usize];
+    if let Err(err) = file.read_exact(&mut directory_buf) {
+        return Err(format!("Error reading PAK directory: {:?}", err));
+    }
+
+    let entries =
+        match nom::multi::count(entry_parser, directory_buf.len() / ENTRY_BYTES)(&directory_buf) {
+            Ok((_, entries)) => entries,
+            Err(err) => return Err(format!("Error parsing PAK directory: {:?}", err)),
+        };
+
+    Ok(entries)
+}
+
+pub fn read(pak_file: &Path, entry: &Entry) -> Result<Vec<u8>, String> {
+    let mut file = match File::open(pak_file) {
+        Ok(pak_file) => pak_file,
+        Err(err) => return Err(format!("Error opening PAK file {:?}: {:?}", pak_file, err)),
+    };
+
+    if let Err(err) = file.seek(std::io::SeekFrom::Start(entry.offset as u64)) {
+        return Err(format!("Error seeking to {}: {:?}", entry.name, err));
+    }
+
+    let mut data = vec![0u8; entry.size as usize];
+    if let Err(err) = file.read_exact(&mut data) {
+        return Err(format!("Error reading {}: {:?}", entry.name, err));
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,45 @@
+use std::{fs::File, io::Read, path::Path};
+
+fn open(pk3_file: &Path) -> Result<zip::ZipArchive<File>, String> {
+    let file = match File::open(pk3_file) {
+        Ok(pk3_file) => pk3_file,
+        Err(err) => return Err(format!("Error opening PK3 file {:?}: {:?}", pk3_file, err)),
+    };
+
+    match zip::ZipArchive::new(file) {
+        Ok(archive) => Ok(archive),
+        Err(err) => Err(format!("Error reading PK3 file {:?}: {:?}", pk3_file, err)),
+    }
+}
+
+// Names of every file in the archive, skipping directory records
+pub fn file_names(pk3_file: &Path) -> Result<Vec<String>, String> {
+    let archive = open(pk3_file)?;
+
+    Ok(archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(String::from)
+        .collect())
+}
+
+pub fn read(pk3_file: &Path, name: &str) -> Result<Vec<u8>, String> {
+    let mut archive = open(pk3_file)?;
+
+    let mut file = match archive.by_name(name) {
+        Ok(file) => file,
+        Err(err) => {
+            return Err(format!(
+                "Error finding {} in {:?}: {:?}",
+                name, pk3_file, err
+            ))
+        }
+    };
+
+    let mut data = Vec::with_capacity(file.size() as usize);
+    if let Err(err) = file.read_to_end(&mut data) {
+        return Err(format!("Error reading {}: {:?}", name, err));
+    }
+
+    Ok(data)
+}
@@ -27,6 +27,20 @@ impl Default for Entry {
     }
 }
 
+impl Entry {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(super::ENTRY_BYTES);
+        buf.extend_from_slice(&self.offset.to_le_bytes());
+        buf.extend_from_slice(&self.dsize.to_le_bytes());
+        buf.extend_from_slice(&self.size.to_le_bytes());
+        buf.push(self.entry_type as u8);
+        buf.push(self.cmprs);
+        buf.extend_from_slice(&[0u8; 2]);
+        buf.extend_from_slice(&super::name_bytes(&self.name));
+        buf
+    }
+}
+
 pub fn parser(i: &[u8]) -> IResult<&[u8], Entry> {
     let (i, o) = nom::sequence::tuple((
         le_u32,
@@ -0,0 +1,373 @@
+use std::path::{Path, PathBuf};
+
+use super::color::Color;
+use super::mip_level::MipLevel;
+use super::palette::Palette;
+use super::writer::{Image, FULLBRIGHT_START, TRANSPARENT_INDEX};
+use super::TextureIndexed;
+use crate::Diagnostics;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFileFormat {
+    // Requires the png feature
+    Png,
+    Tga,
+}
+
+impl ImageFileFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFileFormat::Png => "png",
+            ImageFileFormat::Tga => "tga",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ExportSettings {
+    pub format: ImageFileFormat,
+    // Write mip levels 1 to 3 alongside the full size image, as name_mip1 and so on
+    pub all_mips: bool,
+    // Write the fullbright pixels of shared palette textures as name_emissive, black elsewhere
+    pub emissive_mask: bool,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            format: ImageFileFormat::Png,
+            all_mips: false,
+            emissive_mask: false,
+        }
+    }
+}
+
+// One RGBA image per requested mip level, plus emissive masks. Textures without their own palette
+// are WAD2 textures and need the shared one.
+pub fn texture_images(
+    texture: &TextureIndexed,
+    palette: Option<&Palette>,
+    settings: &ExportSettings,
+) -> Result<Vec<Image>, String> {
+    let mip_texture = &texture.mip_texture;
+
+    let (Palette(colors), shared_palette) = match (&texture.palette, palette) {
+        (Some(texture_palette), _) => (texture_palette, false),
+        (None, Some(palette)) => (palette, true),
+        (None, None) => {
+            return Err(format!(
+                "WAD2 texture {} requires a palette to export",
+                mip_texture.name
+            ))
+        }
+    };
+
+    // Quake and Half-Life both draw index 255 of '{' textures as transparent
+    let transparent = mip_texture.name.starts_with('{');
+    let is_transparent = |index: u8| transparent && index == TRANSPARENT_INDEX;
+
+    // Fullbright indices only glow with Quake's shared palette
+    let is_fullbright =
+        |index: u8| shared_palette && index as usize >= FULLBRIGHT_START && !is_transparent(index);
+
+    let mip_data = &texture.mip_data;
+    let mip_levels = [
+        &mip_data.mip0,
+        &mip_data.mip1,
+        &mip_data.mip2,
+        &mip_data.mip3,
+    ];
+    let level_count = if settings.all_mips { 4 } else { 1 };
+
+    let file_stem = file_stem(&mip_texture.name);
+    let mut images = Vec::new();
+
+    for (level, mip_level) in mip_levels.iter().enumerate().take(level_count) {
+        let MipLevel(indices) = match mip_level {
+            Some(mip_level) => mip_level,
+            None => continue,
+        };
+
+        let name = match level {
+            0 => file_stem.clone(),
+            _ => format!("{}_mip{}", file_stem, level),
+        };
+        let width = mip_texture.width >> level;
+        let height = mip_texture.height >> level;
+
+        let pixels = indices
+            .iter()
+            .map(|index| {
+                let Color(r, g, b) = colors[*index as usize];
+                let alpha = if is_transparent(*index) { 0 } else { 255 };
+                [r, g, b, alpha]
+            })
+            .collect();
+        images.push(Image::new(name.clone(), width, height, pixels));
+
+        if settings.emissive_mask && indices.iter().any(|index| is_fullbright(*index)) {
+            let pixels = indices
+                .iter()
+                .map(|index| {
+                    let Color(r, g, b) = colors[*index as usize];
+                    match *index {
+                        index if is_fullbright(index) => [r, g, b, 255],
+                        index if is_transparent(index) => [0, 0, 0, 0],
+                        _ => [0, 0, 0, 255],
+                    }
+                })
+                .collect();
+            images.push(Image::new(
+                format!("{}_emissive", name),
+                width,
+                height,
+                pixels,
+            ));
+        }
+    }
+
+    Ok(images)
+}
+
+// '*' marks liquids in Quake but is not a valid filename character everywhere
+pub fn file_stem(texture_name: &str) -> String {
+    texture_name.replace('*', "#")
+}
+
+pub fn encode_image(image: &Image, format: ImageFileFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ImageFileFormat::Png => encode_png(image),
+        ImageFileFormat::Tga => encode_tga(image),
+    }
+}
+
+// Uncompressed 32-bit, stored top row first
+fn encode_tga(image: &Image) -> Result<Vec<u8>, String> {
+    if image.width > u16::MAX as u32 || image.height > u16::MAX as u32 {
+        return Err(format!(
+            "{} is {}x{}, too large for TGA",
+            image.name, image.width, image.height
+        ));
+    }
+
+    let mut buf = Vec::with_capacity(18 + image.pixels.len() * 4);
+    buf.extend_from_slice(&[0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    buf.extend_from_slice(&(image.width as u16).to_le_bytes());
+    buf.extend_from_slice(&(image.height as u16).to_le_bytes());
+    // 32 bits per pixel, 8 of them alpha, top-left origin
+    buf.extend_from_slice(&[32, 0x28]);
+
+    for [r, g, b, a] in &image.pixels {
+        buf.extend_from_slice(&[*b, *g, *r, *a]);
+    }
+
+    Ok(buf)
+}
+
+#[cfg(feature = "png")]
+fn encode_png(image: &Image) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut buf, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = image.pixels.iter().flatten().copied().collect();
+    let result = encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&data));
+
+    match result {
+        Ok(()) => Ok(buf),
+        Err(err) => Err(format!("Error encoding {} as PNG: {:?}", image.name, err)),
+    }
+}
+
+#[cfg(not(feature = "png"))]
+fn encode_png(image: &Image) -> Result<Vec<u8>, String> {
+    Err(format!(
+        "Writing {} as PNG requires the png feature",
+        image.name
+    ))
+}
+
+// Write every texture in a WAD, or only the whitelisted ones, into output_directory
+pub fn export_textures(
+    wad_file: &str,
+    whitelist: Option<Vec<String>>,
+    output_directory: &str,
+    palette: Option<&Palette>,
+    settings: &ExportSettings,
+    diagnostics: &Diagnostics,
+) -> Result<Vec<PathBuf>, String> {
+    let mip_levels = if settings.all_mips { 4 } else { 1 };
+    let textures =
+        super::read_textures_with_diagnostics(wad_file, whitelist, mip_levels, diagnostics)?;
+
+    if let Err(err) = std::fs::create_dir_all(output_directory) {
+        return Err(format!("Error creating {:?}: {:?}", output_directory, err));
+    }
+
+    let mut files = Vec::new();
+    for texture in &textures {
+        for image in texture_images(texture, palette, settings)? {
+            let file_name = format!("{}.{}", image.name, settings.format.extension());
+            let file = Path::new(output_directory).join(file_name);
+
+            diagnostics.debug(&format!("Writing {:?}", file));
+
+            let buf = encode_image(&image, settings.format)?;
+            if let Err(err) = std::fs::write(&file, buf) {
+                return Err(format!("Error writing {:?}: {:?}", file, err));
+            }
+
+            files.push(file);
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{MipDataIndexed, MipTexture};
+    use super::*;
+
+    // 16x16 at mip 0, a fullbright pixel first, then a transparent one, then plain pixels
+    fn texture(name: &str, palette: Option<Palette>) -> TextureIndexed {
+        let mip_level = |size: usize| {
+            let mut indices = vec![1u8; size * size];
+            indices[0] = 230;
+            indices[1] = TRANSPARENT_INDEX;
+            Some(MipLevel(indices))
+        };
+
+        TextureIndexed::new(
+            MipTexture {
+                name: name.into(),
+                width: 16,
+                height: 16,
+                offset1: 0,
+                offset2: 0,
+                offset4: 0,
+                offset8: 0,
+            },
+            MipDataIndexed {
+                mip0: mip_level(16),
+                mip1: mip_level(8),
+                mip2: mip_level(4),
+                mip3: mip_level(2),
+            },
+            palette,
+        )
+    }
+
+    fn palette() -> Palette {
+        let mut palette = Palette([Color(0, 0, 0); 256]);
+        palette.0[1] = Color(10, 20, 30);
+        palette.0[230] = Color(250, 200, 0);
+        palette.0[255] = Color(0, 0, 255);
+        palette
+    }
+
+    #[test]
+    fn fullbright_and_transparency() {
+        let settings = ExportSettings {
+            format: ImageFileFormat::Tga,
+            all_mips: true,
+            emissive_mask: true,
+        };
+
+        let images = texture_images(&texture("{*lava", None), Some(&palette()), &settings).unwrap();
+        let names: Vec<&str> = images.iter().map(|image| image.name.as_str()).collect();
+        assert!(
+            names
+                == vec![
+                    "{#lava",
+                    "{#lava_emissive",
+                    "{#lava_mip1",
+                    "{#lava_mip1_emissive",
+                    "{#lava_mip2",
+                    "{#lava_mip2_emissive",
+                    "{#lava_mip3",
+                    "{#lava_mip3_emissive",
+                ],
+            "Image names {:?}",
+            names
+        );
+
+        let sizes: Vec<(u32, u32)> = images.iter().map(|i| (i.width, i.height)).collect();
+        assert!(sizes[6] == (2, 2), "Image sizes {:?}", sizes);
+
+        let color = &images[0].pixels[..3];
+        assert!(
+            color == [[250, 200, 0, 255], [0, 0, 255, 0], [10, 20, 30, 255]],
+            "Colour pixels {:?}",
+            color
+        );
+
+        let emissive = &images[1].pixels[..3];
+        assert!(
+            emissive == [[250, 200, 0, 255], [0, 0, 0, 0], [0, 0, 0, 255]],
+            "Emissive pixels {:?}",
+            emissive
+        );
+
+        // Opaque textures keep index 255, and their own palette has no fullbrights
+        let images = texture_images(&texture("lava", Some(palette())), None, &settings).unwrap();
+        assert!(images.len() == 4, "Image count {}", images.len());
+        assert!(
+            images[0].pixels[1] == [0, 0, 255, 255],
+            "Pixel {:?}",
+            images[0].pixels[1]
+        );
+
+        assert!(texture_images(&texture("lava", None), None, &settings).is_err());
+    }
+
+    #[test]
+    fn tga_encoding() {
+        let image = Image::new("pixel".into(), 2, 1, vec![[1, 2, 3, 4], [5, 6, 7, 8]]);
+        let buf = encode_image(&image, ImageFileFormat::Tga).unwrap();
+
+        assert!(
+            buf == [
+                0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 1, 0, 32, 0x28, 3, 2, 1, 4, 7, 6, 5, 8
+            ],
+            "TGA bytes {:?}",
+            buf
+        );
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn export_wad() {
+        let output =
+            std::env::temp_dir().join(format!("quarchitect-export-{}", std::process::id()));
+        let wad_file = format!("{}/src/wad/test_data/wad3.wad", env!("CARGO_MANIFEST_DIR"));
+
+        let files = export_textures(
+            &wad_file,
+            None,
+            output.to_str().unwrap(),
+            None,
+            &ExportSettings::default(),
+            &Diagnostics::default(),
+        );
+        let signature = files
+            .as_ref()
+            .ok()
+            .and_then(|files| files.first())
+            .and_then(|file| std::fs::read(file).ok())
+            .map(|buf| buf[..8].to_vec());
+        std::fs::remove_dir_all(&output).ok();
+
+        let files = files.unwrap();
+        assert!(files.len() == 2, "Files {:?}", files);
+        assert!(
+            signature.as_deref() == Some(b"\x89PNG\r\n\x1a\n"),
+            "PNG signature {:?}",
+            signature
+        );
+    }
+}
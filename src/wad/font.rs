@@ -0,0 +1,59 @@
+use nom::{
+    number::complete::{le_u16, le_u32},
+    IResult,
+};
+
+use super::mip_level::{self, MipLevel};
+use super::palette::{self, Palette};
+
+// Where a glyph starts in the font image, and how many pixels wide it is
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CharInfo {
+    pub offset: u16,
+    pub width: u16,
+}
+
+// WAD3 'F' lump, 256 glyphs packed into rows of a single image
+#[derive(Debug)]
+pub struct Font {
+    pub width: u32,
+    pub height: u32,
+    pub row_count: u32,
+    pub row_height: u32,
+    pub chars: Vec<CharInfo>,
+    pub data: MipLevel,
+    pub palette: Palette,
+}
+
+fn char_info(i: &[u8]) -> IResult<&[u8], CharInfo> {
+    let (i, (offset, width)) = nom::sequence::tuple((le_u16, le_u16))(i)?;
+    Ok((i, CharInfo { offset, width }))
+}
+
+pub fn parser(i: &[u8]) -> IResult<&[u8], Font> {
+    let (i, o) = nom::sequence::tuple((
+        le_u32,
+        le_u32,
+        le_u32,
+        le_u32,
+        nom::multi::count(char_info, 256),
+    ))(i)?;
+
+    let (width, height, row_count, row_height, chars) = o;
+
+    let (i, data) = mip_level::parser((width * height) as usize)(i)?;
+    let (i, palette) = palette::counted_parser(i)?;
+
+    Ok((
+        i,
+        Font {
+            width,
+            height,
+            row_count,
+            row_height,
+            chars,
+            data,
+            palette,
+        },
+    ))
+}
@@ -17,6 +17,16 @@ impl Default for Header {
     }
 }
 
+impl Header {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(super::HEADER_BYTES);
+        buf.extend(self.magic.iter().map(|c| *c as u8));
+        buf.extend_from_slice(&self.num_entries.to_le_bytes());
+        buf.extend_from_slice(&self.dir_offset.to_le_bytes());
+        buf
+    }
+}
+
 fn wad_magic(i: &[u8]) -> IResult<&[u8], Vec<char>> {
     nom::multi::count(anychar, 4)(i)
 }
@@ -5,7 +5,7 @@ use super::mip_level::MipLevel;
 
 #[derive(Debug)]
 pub struct MipDataIndexed {
-    pub mip0: MipLevel,
+    pub mip0: Option<MipLevel>,
     pub mip1: Option<MipLevel>,
     pub mip2: Option<MipLevel>,
     pub mip3: Option<MipLevel>,
@@ -15,11 +15,15 @@ impl MipDataIndexed {
     pub fn into_rgb(self, palette: &Palette) -> MipDataRGB {
         MipDataRGB::from_indexed(self, palette)
     }
+
+    pub fn into_decal(self, palette: &Palette) -> MipDataRGBA {
+        MipDataRGBA::from_decal(self, palette)
+    }
 }
 
 #[derive(Debug)]
 pub struct MipDataRGB {
-    pub mip0: Vec<Color>,
+    pub mip0: Option<Vec<Color>>,
     pub mip1: Option<Vec<Color>>,
     pub mip2: Option<Vec<Color>>,
     pub mip3: Option<Vec<Color>>,
@@ -29,7 +33,7 @@ impl MipDataRGB {
     pub fn from_indexed(mip_data: MipDataIndexed, Palette(palette): &Palette) -> Self {
         let to_color = |MipLevel(mip_level)| mip_level.iter().map(|idx| palette[*idx as usize]).collect();
 
-        let mip0 = to_color(mip_data.mip0);
+        let mip0 = mip_data.mip0.map(to_color);
         let mip1 = mip_data.mip1.map(to_color);
         let mip2 = mip_data.mip2.map(to_color);
         let mip3 = mip_data.mip3.map(to_color);
@@ -42,3 +46,27 @@ impl MipDataRGB {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct MipDataRGBA {
+    pub mip0: Option<Vec<[u8; 4]>>,
+    pub mip1: Option<Vec<[u8; 4]>>,
+    pub mip2: Option<Vec<[u8; 4]>>,
+    pub mip3: Option<Vec<[u8; 4]>>,
+}
+
+impl MipDataRGBA {
+    // Decals are tinted by the last palette entry, with each index acting as that pixel's alpha
+    pub fn from_decal(mip_data: MipDataIndexed, Palette(palette): &Palette) -> Self {
+        let Color(r, g, b) = palette[255];
+        let to_rgba =
+            |MipLevel(mip_level): MipLevel| mip_level.iter().map(|idx| [r, g, b, *idx]).collect();
+
+        MipDataRGBA {
+            mip0: mip_data.mip0.map(to_rgba),
+            mip1: mip_data.mip1.map(to_rgba),
+            mip2: mip_data.mip2.map(to_rgba),
+            mip3: mip_data.mip3.map(to_rgba),
+        }
+    }
+}
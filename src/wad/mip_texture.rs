@@ -11,6 +11,24 @@ pub struct MipTexture {
     pub offset8: u32,
 }
 
+impl MipTexture {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(super::MIPTEX_BYTES);
+        buf.extend_from_slice(&super::name_bytes(&self.name));
+        for value in &[
+            self.width,
+            self.height,
+            self.offset1,
+            self.offset2,
+            self.offset4,
+            self.offset8,
+        ] {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+        buf
+    }
+}
+
 pub fn parser(i: &[u8]) -> IResult<&[u8], MipTexture> {
     let (i, o) = nom::sequence::tuple((
         nom::multi::count(anychar, 16),
@@ -5,19 +5,30 @@ pub mod mip_level;
 pub mod palette;
 
 mod entry;
+mod export;
+mod font;
 mod header;
 mod mip_data;
 mod mip_texture;
+mod pic;
+mod writer;
 
 pub use color::Color;
-pub use mip_data::{MipDataIndexed, MipDataRGB};
+pub use entry::Entry;
+pub use export::{
+    encode_image, export_textures, file_stem, texture_images, ExportSettings, ImageFileFormat,
+};
+pub use font::{CharInfo, Font};
+pub use mip_data::{MipDataIndexed, MipDataRGB, MipDataRGBA};
+pub use mip_level::MipLevel;
 pub use mip_texture::MipTexture;
+pub use pic::Pic;
+pub use writer::{to_wad, write_wad, Image, WadFormat};
 
-use entry::Entry;
-
+use crate::vfs::{self, VfsFile, VfsReader};
+use crate::Diagnostics;
 use palette::Palette;
 use std::{
-    fs::File,
     io::{Read, Seek},
     time::Instant,
 };
@@ -53,12 +64,8 @@ impl TextureIndexed {
 
     pub fn into_rgb(self, palette: Option<Palette>) -> Result<TextureRGB, String> {
         let palette = match self.palette {
-            Some(texture_palette) => {
-                if palette.is_some() {
-                    println!("Skipping supplied palette: Not necessary for WAD3 RGB conversion");
-                }
-                texture_palette
-            }
+            // WAD3 textures carry their own palette, so any supplied one is ignored
+            Some(texture_palette) => texture_palette,
             None => match palette {
                 Some(palette) => palette,
                 None => return Err("WAD2 RGB conversion requires a palette".into()),
@@ -67,6 +74,35 @@ impl TextureIndexed {
 
         Ok(TextureRGB::from_indexed(self, &palette))
     }
+
+    // Decal WADs are WAD3 files whose textures are alpha masks of a single colour
+    pub fn into_decal(self) -> Result<TextureDecal, String> {
+        let palette = match self.palette {
+            Some(palette) => palette,
+            None => {
+                return Err(format!(
+                    "Decal {} has no palette, decals must come from a WAD3",
+                    self.mip_texture.name
+                ))
+            }
+        };
+
+        Ok(TextureDecal {
+            mip_data: self.mip_data.into_decal(&palette),
+            mip_texture: self.mip_texture,
+        })
+    }
+}
+
+// Decoded '@' palette, 'B'/'E' pic, 'C'/'D' miptex or WAD3 'F' font lump
+#[derive(Debug)]
+pub enum Lump {
+    Palette(Palette),
+    Pic(Pic),
+    // Pixel data is left to read_textures, which reads mip levels in parallel
+    MipTexture(MipTexture),
+    Font(Font),
+    Unknown(Vec<u8>),
 }
 
 pub struct TextureRGB {
@@ -86,40 +122,87 @@ impl TextureRGB {
     }
 }
 
+pub struct TextureDecal {
+    pub mip_texture: MipTexture,
+    pub mip_data: MipDataRGBA,
+}
+
+// The first `mip_levels` levels of every texture, starting at full size
 pub fn read_textures(
     wad_file: &str,
     whitelist: Option<Vec<String>>,
-    mip_levels: usize
+    mip_levels: usize,
+) -> Result<Vec<TextureIndexed>, String> {
+    read_textures_with_diagnostics(wad_file, whitelist, mip_levels, &Diagnostics::default())
+}
+
+pub fn read_textures_with_diagnostics(
+    wad_file: &str,
+    whitelist: Option<Vec<String>>,
+    mip_levels: usize,
+    diagnostics: &Diagnostics,
 ) -> Result<Vec<TextureIndexed>, String> {
     assert!(mip_levels >= 1 && mip_levels <= 4);
 
-    let mut file = match File::open(wad_file) {
-        Ok(wad_file) => wad_file,
-        Err(err) => return Err(format!("Error opening WAD file {:?}: {:?}", wad_file, err)),
+    let levels: Vec<usize> = (0..mip_levels).collect();
+    read_texture_mips(wad_file, whitelist, &levels, diagnostics)
+}
+
+// Only the requested mip levels of every texture, 0 being full size and 3 being 1/8 size
+pub fn read_texture_mips(
+    wad_file: &str,
+    whitelist: Option<Vec<String>>,
+    levels: &[usize],
+    diagnostics: &Diagnostics,
+) -> Result<Vec<TextureIndexed>, String> {
+    if let Some(level) = levels.iter().find(|level| **level > 3) {
+        return Err(format!("Invalid mip level {}, expected 0 to 3", level));
+    }
+
+    let wad = match vfs::open(wad_file) {
+        Ok(wad) => wad,
+        Err(err) => return Err(format!("Error opening WAD file {:?}: {}", wad_file, err)),
     };
 
+    let mut file = wad.reader()?;
+
     let now = Instant::now();
     let header = read_header(&mut file)?;
-    println!("Read header took {}", now.elapsed().as_millis());
+    diagnostics.trace(&format!("Read header took {}ms", now.elapsed().as_millis()));
 
     let wad_type = match header.magic {
         ['W', 'A', 'D', '2'] => WadType::WAD2,
         ['W', 'A', 'D', '3'] => WadType::WAD3,
-        _ => panic!("Unexpected WAD magic: {:?}", header.magic),
+        _ => return Err(format!("Unexpected WAD magic: {:?}", header.magic)),
     };
 
     let now = Instant::now();
     let directory: Vec<Entry> = read_directory(&mut file, &header, &whitelist)?;
-    println!("Read directory took {}", now.elapsed().as_millis());
+    diagnostics.trace(&format!(
+        "Read directory took {}ms",
+        now.elapsed().as_millis()
+    ));
 
     let now = Instant::now();
     let mip_textures: Vec<MipTexture> = read_mip_textures(&mut file, &directory)?;
-    println!("Read mip textures took {}", now.elapsed().as_millis());
+    diagnostics.trace(&format!(
+        "Read mip textures took {}ms",
+        now.elapsed().as_millis()
+    ));
 
     let now = Instant::now();
-    let mip_data: Vec<(MipDataIndexed, Option<Palette>)> =
-        read_mip_data(wad_file, wad_type, &directory, &mip_textures, mip_levels)?;
-    println!("Read mip data took {}", now.elapsed().as_millis());
+    let mip_data: Vec<(MipDataIndexed, Option<Palette>)> = read_mip_data(
+        &wad,
+        wad_type,
+        &directory,
+        &mip_textures,
+        levels,
+        diagnostics,
+    )?;
+    diagnostics.trace(&format!(
+        "Read mip data took {}ms",
+        now.elapsed().as_millis()
+    ));
 
     let result: Vec<TextureIndexed> = mip_textures
         .into_iter()
@@ -132,7 +215,137 @@ pub fn read_textures(
     Ok(result)
 }
 
-fn read_header(wad_file: &mut File) -> Result<header::Header, String> {
+// Every lump matching the whitelist, decoded according to its type
+pub fn read_lumps(
+    wad_file: &str,
+    whitelist: Option<Vec<String>>,
+) -> Result<Vec<(Entry, Lump)>, String> {
+    let mut file = match vfs::open(wad_file).and_then(|wad_file| wad_file.reader()) {
+        Ok(wad_file) => wad_file,
+        Err(err) => return Err(format!("Error opening WAD file {:?}: {}", wad_file, err)),
+    };
+
+    let header = read_header(&mut file)?;
+
+    let wad_type = match header.magic {
+        ['W', 'A', 'D', '2'] => WadType::WAD2,
+        ['W', 'A', 'D', '3'] => WadType::WAD3,
+        _ => return Err(format!("Unexpected WAD magic: {:?}", header.magic)),
+    };
+
+    let mut lumps = Vec::new();
+
+    for i in 0..header.num_entries {
+        let entry = read_entry(&mut file, &header, i)?;
+
+        if let Some(whitelist) = &whitelist {
+            if !whitelist.iter().any(|name| name == &entry.name) {
+                continue;
+            }
+        }
+
+        check_compression(&entry)?;
+        let data = read_lump_data(&mut file, &entry)?;
+        let lump = parse_lump(&wad_type, &entry, &data)?;
+        lumps.push((entry, lump));
+    }
+
+    Ok(lumps)
+}
+
+// Every lump in the WAD directory, regardless of type
+pub fn read_entries(wad_file: &str) -> Result<Vec<Entry>, String> {
+    let mut file = match vfs::open(wad_file).and_then(|wad_file| wad_file.reader()) {
+        Ok(wad_file) => wad_file,
+        Err(err) => return Err(format!("Error opening WAD file {:?}: {}", wad_file, err)),
+    };
+
+    let header = read_header(&mut file)?;
+
+    (0..header.num_entries)
+        .map(|i| read_entry(&mut file, &header, i))
+        .collect()
+}
+
+// Raw bytes of a single lump as stored on disk
+pub fn read_entry_data(wad_file: &str, entry: &Entry) -> Result<Vec<u8>, String> {
+    let mut file = match vfs::open(wad_file).and_then(|wad_file| wad_file.reader()) {
+        Ok(wad_file) => wad_file,
+        Err(err) => return Err(format!("Error opening WAD file {:?}: {}", wad_file, err)),
+    };
+
+    read_lump_data(&mut file, entry)
+}
+
+// Texture names and dimensions without reading any pixel data
+pub fn read_texture_headers(
+    wad_file: &str,
+    whitelist: Option<Vec<String>>,
+) -> Result<Vec<MipTexture>, String> {
+    let mut file = match vfs::open(wad_file).and_then(|wad_file| wad_file.reader()) {
+        Ok(wad_file) => wad_file,
+        Err(err) => return Err(format!("Error opening WAD file {:?}: {}", wad_file, err)),
+    };
+
+    let header = read_header(&mut file)?;
+    let directory = read_directory(&mut file, &header, &whitelist)?;
+    read_mip_textures(&mut file, &directory)
+}
+
+// Lump names are NUL-padded to 16 bytes
+fn name_bytes(name: &str) -> [u8; 16] {
+    let mut buf = [0u8; 16];
+    for (dst, src) in buf.iter_mut().zip(name.bytes().take(15)) {
+        *dst = src;
+    }
+    buf
+}
+
+fn read_lump_data(wad_file: &mut VfsReader, entry: &Entry) -> Result<Vec<u8>, String> {
+    if let Err(err) = wad_file.seek(std::io::SeekFrom::Start(entry.offset as u64)) {
+        return Err(format!("Error seeking to entry {}: {:?}", entry.name, err));
+    }
+
+    let mut data = vec![0u8; entry.dsize as usize];
+    if let Err(err) = wad_file.read_exact(&mut data) {
+        return Err(format!("Error reading entry {}: {:?}", entry.name, err));
+    }
+
+    Ok(data)
+}
+
+fn check_compression(entry: &Entry) -> Result<(), String> {
+    if entry.cmprs != 0 {
+        return Err(format!(
+            "Entry {} is compressed (method {}), compressed lumps are not supported",
+            entry.name, entry.cmprs
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_lump(wad_type: &WadType, entry: &Entry, data: &[u8]) -> Result<Lump, String> {
+    let lump = match entry.entry_type {
+        '@' => palette::parser(data).map(|(_, palette)| Lump::Palette(palette)),
+        'B' | 'E' => pic::parser(*wad_type == WadType::WAD3)(data).map(|(_, pic)| Lump::Pic(pic)),
+        'C' | 'D' => {
+            mip_texture::parser(data).map(|(_, mip_texture)| Lump::MipTexture(mip_texture))
+        }
+        'F' => font::parser(data).map(|(_, font)| Lump::Font(font)),
+        _ => return Ok(Lump::Unknown(data.to_vec())),
+    };
+
+    match lump {
+        Ok(lump) => Ok(lump),
+        Err(err) => Err(format!(
+            "Error parsing '{}' lump {}: {:?}",
+            entry.entry_type, entry.name, err
+        )),
+    }
+}
+
+fn read_header(wad_file: &mut VfsReader) -> Result<header::Header, String> {
     let mut header_buf = [0u8; HEADER_BYTES];
     if let Err(err) = wad_file.read_exact(&mut header_buf) {
         return Err(format!("Error reading WAD header: {:?}", err));
@@ -145,27 +358,13 @@ fn read_header(wad_file: &mut File) -> Result<header::Header, String> {
 }
 
 fn read_directory(
-    wad_file: &mut File,
+    wad_file: &mut VfsReader,
     header: &header::Header,
     whitelist: &Option<Vec<String>>,
 ) -> Result<Vec<Entry>, String> {
     let entries: Vec<Result<Option<Entry>, String>> = (0..header.num_entries)
         .map(|i| {
-            let offset = header.dir_offset;
-            let offset = offset + i * ENTRY_BYTES as u32;
-            if let Err(err) = wad_file.seek(std::io::SeekFrom::Start(offset as u64)) {
-                return Err(format!("Error seeking to directory: {:?}", err));
-            }
-
-            let mut entry_buf = [0u8; ENTRY_BYTES];
-            if let Err(err) = wad_file.read_exact(&mut entry_buf) {
-                return Err(format!("Error reading entry {}: {:?}", i, err));
-            }
-
-            let entry = match entry::parser(&entry_buf) {
-                Ok((_i, entry)) => entry,
-                Err(err) => return Err(format!("Error parsing entry: {:?}", err)),
-            };
+            let entry = read_entry(wad_file, header, i)?;
 
             match entry.entry_type {
                 'C' | 'D' => (),
@@ -176,6 +375,7 @@ fn read_directory(
                 None => true,
                 Some(whitelist) => whitelist.iter().any(|texture| texture == &entry.name),
             } {
+                check_compression(&entry)?;
                 Ok(Some(entry))
             } else {
                 Ok(None)
@@ -201,7 +401,25 @@ fn read_directory(
     Ok(entries)
 }
 
-fn read_mip_textures(wad_file: &mut File, directory: &[Entry]) -> Result<Vec<MipTexture>, String> {
+fn read_entry(wad_file: &mut VfsReader, header: &header::Header, i: u32) -> Result<Entry, String> {
+    let offset = header.dir_offset;
+    let offset = offset + i * ENTRY_BYTES as u32;
+    if let Err(err) = wad_file.seek(std::io::SeekFrom::Start(offset as u64)) {
+        return Err(format!("Error seeking to directory: {:?}", err));
+    }
+
+    let mut entry_buf = [0u8; ENTRY_BYTES];
+    if let Err(err) = wad_file.read_exact(&mut entry_buf) {
+        return Err(format!("Error reading entry {}: {:?}", i, err));
+    }
+
+    match entry::parser(&entry_buf) {
+        Ok((_i, entry)) => Ok(entry),
+        Err(err) => Err(format!("Error parsing entry: {:?}", err)),
+    }
+}
+
+fn read_mip_textures(wad_file: &mut VfsReader, directory: &[Entry]) -> Result<Vec<MipTexture>, String> {
     let mip_textures: Vec<Result<MipTexture, String>> = directory
         .iter()
         .map(move |entry| {
@@ -242,88 +460,71 @@ fn read_mip_textures(wad_file: &mut File, directory: &[Entry]) -> Result<Vec<Mip
 }
 
 fn read_mip_data(
-    wad_file: &str,
+    wad: &VfsFile,
     wad_type: WadType,
     directory: &[Entry],
     mip_textures: &[MipTexture],
-    mip_levels: usize,
+    levels: &[usize],
+    diagnostics: &Diagnostics,
 ) -> Result<Vec<(MipDataIndexed, Option<Palette>)>, String> {
-    assert!(mip_levels >= 1 && mip_levels <= 4);
-
     let mip_data: Vec<Result<(MipDataIndexed, Option<Palette>), String>> = directory
         .par_iter()
         .zip(mip_textures.par_iter())
         .map(|(entry, miptex)| {
-            println!("Reading mipdata for entry: {:?}", entry);
-
-            let mut wad_file = match File::open(wad_file) {
-                Ok(wad_file) => wad_file,
-                Err(err) => return Err(format!("Error opening WAD file: {:?}", err)),
-            };
-
-            if let Err(err) = wad_file.seek(std::io::SeekFrom::Start(
-                (entry.offset + miptex.offset1) as u64,
-            )) {
-                return Err(format!("Error seeking to miptex: {:?}", err));
-            }
-
-            let size0 = (miptex.width * miptex.height) as usize;
-            let size1 = size0 / 4;
-            let size2 = size1 / 4;
-            let size3 = size2 / 4;
-
-            let sizes = [size0, size1, size2, size3];
-            let sizes = Vec::from(&sizes[..mip_levels]);
-            let total_size = sizes.iter().sum();
-
-            let mut mipdata_buf = vec![0u8; total_size];
-
-            if let Err(err) = wad_file.read_exact(&mut mipdata_buf) {
-                return Err(format!(
-                    "Error reading mipdata for texture {:?}: {:?}",
-                    miptex, err
-                ));
-            }
+            diagnostics.trace(&format!("Reading mipdata for entry: {:?}", entry));
+
+            let mut wad_file = wad.reader()?;
+
+            // Each level sits wherever its offset says, relative to the start of the lump
+            let offsets = [
+                miptex.offset1,
+                miptex.offset2,
+                miptex.offset4,
+                miptex.offset8,
+            ];
+            let sizes: Vec<usize> = (0..4)
+                .map(|level| ((miptex.width >> level) * (miptex.height >> level)) as usize)
+                .collect();
+
+            let mut mips: [Option<MipLevel>; 4] = [None, None, None, None];
+
+            for &level in levels {
+                let offset = offsets[level] as usize;
+                let size = sizes[level];
+
+                if offset + size > entry.dsize as usize {
+                    return Err(format!(
+                        "Mip level {} of texture {} lies outside its {} byte lump",
+                        level, miptex.name, entry.dsize
+                    ));
+                }
 
-            let mip0_buf = &mipdata_buf[..size0];
+                if let Err(err) = wad_file.seek(std::io::SeekFrom::Start(
+                    (entry.offset as usize + offset) as u64,
+                )) {
+                    return Err(format!("Error seeking to mip level {}: {:?}", level, err));
+                }
 
-            let (_, mip0) = match mip_level::parser(size0)(mip0_buf) {
-                Ok(mip_level) => mip_level,
-                Err(err) => return Err(format!("Error parsing mip level 0: {:?}", err)),
-            };
+                let mut mip_buf = vec![0u8; size];
+                if let Err(err) = wad_file.read_exact(&mut mip_buf) {
+                    return Err(format!(
+                        "Error reading mip level {} for texture {:?}: {:?}",
+                        level, miptex, err
+                    ));
+                }
 
-            let mip1 = if mip_levels > 1 {
-                let mip1_buf = &mipdata_buf[size0..size1];
-                let (_, mip1) = match mip_level::parser(size1)(mip1_buf) {
+                let (_, mip) = match mip_level::parser(size)(&mip_buf) {
                     Ok(mip_level) => mip_level,
-                    Err(err) => return Err(format!("Error parsing mip level 1: {:?}", err)),
+                    Err(err) => {
+                        return Err(format!("Error parsing mip level {}: {:?}", level, err))
+                    }
                 };
-                Some(mip1)
-            } else {
-                None
-            };
 
-            let mip2 = if mip_levels > 2 {
-                let mip2_buf = &mipdata_buf[size0 + size1..size2];
-                let (_, mip2) = match mip_level::parser(size2)(mip2_buf) {
-                    Ok(mip_level) => mip_level,
-                    Err(err) => return Err(format!("Error parsing mip level 2: {:?}", err)),
-                };
-                Some(mip2)
-            } else {
-                None
-            };
+                mips[level] = Some(mip);
+            }
 
-            let mip3 = if mip_levels > 3 {
-                let mip3_buf = &mipdata_buf[size0 + size1 + size2..size3];
-                let (_, mip3) = match mip_level::parser(size3)(mip3_buf) {
-                    Ok(mip_level) => mip_level,
-                    Err(err) => return Err(format!("Error parsing mip level 3: {:?}", err)),
-                };
-                Some(mip3)
-            } else {
-                None
-            };
+            let [mip0, mip1, mip2, mip3] = mips;
+            let size3 = sizes[3];
 
             let mipdata = MipDataIndexed {
                 mip0,
@@ -380,3 +581,209 @@ fn read_mip_data(
 
     Ok(mip_data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every byte of mip level n is base + n. "first" stores its levels contiguously, "second"
+    // stores them smallest first with 4 padding bytes ahead of each level.
+    fn fixture(wad: &str) -> String {
+        format!("{}/src/wad/test_data/{}", env!("CARGO_MANIFEST_DIR"), wad)
+    }
+
+    fn check_levels(texture: &TextureIndexed, base: u8, levels: &[usize]) {
+        let mip_data = &texture.mip_data;
+        let mips = [
+            &mip_data.mip0,
+            &mip_data.mip1,
+            &mip_data.mip2,
+            &mip_data.mip3,
+        ];
+
+        for (level, mip) in mips.iter().enumerate() {
+            let size = ((texture.mip_texture.width >> level)
+                * (texture.mip_texture.height >> level)) as usize;
+
+            match mip {
+                Some(MipLevel(mip)) => assert!(
+                    levels.contains(&level)
+                        && mip.len() == size
+                        && mip.iter().all(|idx| *idx == base + level as u8),
+                    "Mip level {} of {}: {:?}",
+                    level,
+                    texture.mip_texture.name,
+                    mip
+                ),
+                None => assert!(
+                    !levels.contains(&level),
+                    "Missing mip level {} of {}",
+                    level,
+                    texture.mip_texture.name
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn wad2_mip_levels() {
+        let textures = read_textures(&fixture("wad2.wad"), None, 4).unwrap();
+
+        assert!(textures.len() == 2, "Texture count {}", textures.len());
+        check_levels(&textures[0], 10, &[0, 1, 2, 3]);
+        check_levels(&textures[1], 20, &[0, 1, 2, 3]);
+        assert!(textures.iter().all(|texture| texture.palette.is_none()));
+    }
+
+    #[test]
+    fn wad3_mip_levels() {
+        let textures = read_textures(&fixture("wad3.wad"), None, 4).unwrap();
+
+        check_levels(&textures[0], 30, &[0, 1, 2, 3]);
+        check_levels(&textures[1], 40, &[0, 1, 2, 3]);
+
+        for texture in &textures {
+            let Palette(palette) = texture.palette.expect("WAD3 texture palette");
+            let Color(r, g, b) = palette[10];
+            assert!((r, g, b) == (10, 245, 5), "Palette entry {:?}", (r, g, b));
+        }
+    }
+
+    #[test]
+    fn individual_mip_levels() {
+        for (wad, bases) in &[("wad2.wad", [10, 20]), ("wad3.wad", [30, 40])] {
+            let textures =
+                read_texture_mips(&fixture(wad), None, &[1, 3], &Diagnostics::default()).unwrap();
+
+            check_levels(&textures[0], bases[0], &[1, 3]);
+            check_levels(&textures[1], bases[1], &[1, 3]);
+        }
+
+        let whitelist = Some(vec!["second".to_string()]);
+        let textures = read_texture_mips(
+            &fixture("wad2.wad"),
+            whitelist,
+            &[2],
+            &Diagnostics::default(),
+        )
+        .unwrap();
+        assert!(textures.len() == 1, "Texture count {}", textures.len());
+        check_levels(&textures[0], 20, &[2]);
+
+        assert!(
+            read_texture_mips(&fixture("wad2.wad"), None, &[4], &Diagnostics::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn wad2_lumps() {
+        let whitelist = Some(vec!["palette".into(), "num_0".into(), "label".into()]);
+        let lumps = read_lumps(&fixture("gfx.wad"), whitelist).unwrap();
+        assert!(lumps.len() == 3, "Lump count {}", lumps.len());
+
+        match &lumps[0].1 {
+            Lump::Palette(Palette(palette)) => {
+                let Color(r, g, b) = palette[10];
+                assert!((r, g, b) == (10, 10, 10), "Palette entry {:?}", (r, g, b));
+            }
+            lump => panic!("Expected palette, got {:?}", lump),
+        }
+
+        match &lumps[1].1 {
+            Lump::Pic(pic) => assert!(
+                pic.width == 8 && pic.height == 4 && pic.data.0[5] == 5 && pic.palette.is_none(),
+                "Pic {:?}",
+                pic
+            ),
+            lump => panic!("Expected pic, got {:?}", lump),
+        }
+
+        match &lumps[2].1 {
+            Lump::Unknown(data) => assert!(data == b"abc", "Unknown lump {:?}", data),
+            lump => panic!("Expected unknown lump, got {:?}", lump),
+        }
+    }
+
+    #[test]
+    fn compressed_lumps() {
+        let err = read_lumps(&fixture("gfx.wad"), None).unwrap_err();
+        assert!(err.contains("packed is compressed"), "Error {:?}", err);
+
+        let err = read_textures(&fixture("gfx.wad"), None, 1).err().unwrap();
+        assert!(err.contains("packed is compressed"), "Error {:?}", err);
+    }
+
+    #[test]
+    fn bad_magic() {
+        let wad_file =
+            std::env::temp_dir().join(format!("quarchitect-magic-{}.wad", std::process::id()));
+        std::fs::write(&wad_file, b"PACK\0\0\0\0\0\0\0\0").unwrap();
+        let wad_file = wad_file.to_str().unwrap();
+
+        let texture_err = read_textures(wad_file, None, 1).err().unwrap();
+        let lump_err = read_lumps(wad_file, None).unwrap_err();
+        std::fs::remove_file(wad_file).ok();
+
+        for err in &[texture_err, lump_err] {
+            assert!(err.contains("Unexpected WAD magic"), "Error {:?}", err);
+        }
+    }
+
+    #[test]
+    fn wad3_lumps() {
+        let lumps = read_lumps(&fixture("hud.wad"), None).unwrap();
+
+        match &lumps[0].1 {
+            Lump::Pic(pic) => {
+                let Palette(palette) = pic.palette.expect("WAD3 pic palette");
+                let Color(r, g, b) = palette[1];
+                assert!(
+                    pic.data.0[4] == 1 && (r, g, b) == (0, 255, 0),
+                    "Pic {:?}",
+                    pic
+                );
+            }
+            lump => panic!("Expected pic, got {:?}", lump),
+        }
+
+        match &lumps[1].1 {
+            Lump::Font(font) => assert!(
+                font.row_count == 1
+                    && font.row_height == 2
+                    && font.data.0.len() == 512
+                    && font.chars[65].offset == 10
+                    && font.chars[65].width == 6,
+                "Font {:?}",
+                font.chars[65]
+            ),
+            lump => panic!("Expected font, got {:?}", lump),
+        }
+
+        match &lumps[2].1 {
+            Lump::MipTexture(mip_texture) => assert!(
+                mip_texture.name == "{blood" && mip_texture.width == 16,
+                "Mip texture {:?}",
+                mip_texture
+            ),
+            lump => panic!("Expected mip texture, got {:?}", lump),
+        }
+    }
+
+    #[test]
+    fn decal() {
+        let whitelist = Some(vec!["{blood".to_string()]);
+        let mut textures = read_textures(&fixture("hud.wad"), whitelist, 4).unwrap();
+        let decal = textures.remove(0).into_decal().unwrap();
+
+        let mip0 = decal.mip_data.mip0.unwrap();
+        assert!(mip0[3] == [120, 0, 0, 48], "Decal pixel {:?}", mip0[3]);
+
+        let mip3 = decal.mip_data.mip3.unwrap();
+        assert!(mip3[1] == [120, 0, 0, 16], "Decal pixel {:?}", mip3[1]);
+
+        let textures = read_textures(&fixture("wad2.wad"), None, 1).unwrap();
+        assert!(textures
+            .into_iter()
+            .all(|texture| texture.into_decal().is_err()));
+    }
+}
@@ -1,10 +1,19 @@
-use nom::IResult;
+use nom::{number::complete::le_u16, IResult};
 
 use super::color::{self, Color};
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone)]
 pub struct Palette(pub [Color; 256]);
 
+impl Palette {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0
+            .iter()
+            .flat_map(|Color(r, g, b)| vec![*r, *g, *b])
+            .collect()
+    }
+}
+
 pub fn parser(i: &[u8]) -> IResult<&[u8], Palette> {
     let (i, o) = nom::multi::count(color::parser, 256)(i)?;
     let mut color_arr = [Color(0, 0, 0); 256];
@@ -12,10 +21,19 @@ pub fn parser(i: &[u8]) -> IResult<&[u8], Palette> {
     Ok((i, Palette(color_arr)))
 }
 
+// WAD3 lumps prefix their palette with its colour count, which may be under 256
+pub fn counted_parser(i: &[u8]) -> IResult<&[u8], Palette> {
+    let (i, colors) = le_u16(i)?;
+    let (i, o) = nom::multi::count(color::parser, colors.min(256) as usize)(i)?;
+    let mut color_arr = [Color(0, 0, 0); 256];
+    color_arr[..o.len()].copy_from_slice(&o);
+    Ok((i, Palette(color_arr)))
+}
+
 pub fn read_palette(palette_file: &str) -> Result<Palette, String> {
-    let palette_buf = match std::fs::read(palette_file) {
+    let palette_buf = match crate::vfs::open(palette_file).and_then(|file| file.read()) {
         Ok(palette_buf) => palette_buf,
-        Err(err) => return Err(format!("Failed to load palette: {:?}", err)),
+        Err(err) => return Err(format!("Failed to load palette: {}", err)),
     };
 
     let (_, palette) = match parser(&palette_buf) {
@@ -0,0 +1,38 @@
+use nom::{number::complete::le_u32, IResult};
+
+use super::mip_level::{self, MipLevel};
+use super::palette::{self, Palette};
+
+// Status bar and menu image, stored as 'B' or 'E' lumps
+#[derive(Debug)]
+pub struct Pic {
+    pub width: u32,
+    pub height: u32,
+    pub data: MipLevel,
+    // WAD3 pics carry their own palette, WAD2 pics use the Quake palette
+    pub palette: Option<Palette>,
+}
+
+pub fn parser<'a>(with_palette: bool) -> impl Fn(&[u8]) -> IResult<&[u8], Pic> + 'a {
+    move |i: &[u8]| {
+        let (i, (width, height)) = nom::sequence::tuple((le_u32, le_u32))(i)?;
+        let (i, data) = mip_level::parser((width * height) as usize)(i)?;
+
+        let (i, palette) = if with_palette {
+            let (i, palette) = palette::counted_parser(i)?;
+            (i, Some(palette))
+        } else {
+            (i, None)
+        };
+
+        Ok((
+            i,
+            Pic {
+                width,
+                height,
+                data,
+                palette,
+            },
+        ))
+    }
+}
@@ -0,0 +1,521 @@
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::color::Color;
+use super::entry::Entry;
+use super::header::Header;
+use super::mip_texture::MipTexture;
+use super::palette::Palette;
+use super::{HEADER_BYTES, MIPTEX_BYTES};
+
+// Pixels below this alpha are written as index 255, which Quake draws as transparent on '{' textures
+const ALPHA_THRESHOLD: u8 = 128;
+pub(super) const TRANSPARENT_INDEX: u8 = 255;
+// Quake palette indices from here up are fullbright and glow regardless of lighting
+pub(super) const FULLBRIGHT_START: usize = 224;
+// Half-Life convention for the colour behind transparent pixels
+const WAD3_TRANSPARENT_COLOR: Color = Color(0, 0, 255);
+
+pub enum WadFormat {
+    // Every texture quantized to one shared palette, normally Quake's palette.lmp
+    WAD2(Box<Palette>),
+    // Every texture quantized to its own 256-colour palette stored alongside it
+    WAD3,
+}
+
+// Source image for a miptex lump, one [r, g, b, a] per pixel in row order
+pub struct Image {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    pub fn new(name: String, width: u32, height: u32, pixels: Vec<[u8; 4]>) -> Image {
+        Image {
+            name,
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn from_rgb(name: String, width: u32, height: u32, data: &[u8]) -> Result<Image, String> {
+        let expected = image_bytes(&name, width, height, 3)?;
+        if data.len() != expected {
+            return Err(format!(
+                "RGB data for {} is {} bytes, expected {}",
+                name,
+                data.len(),
+                expected
+            ));
+        }
+
+        let pixels = data
+            .chunks_exact(3)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect();
+
+        Ok(Image::new(name, width, height, pixels))
+    }
+
+    pub fn from_rgba(name: String, width: u32, height: u32, data: &[u8]) -> Result<Image, String> {
+        let expected = image_bytes(&name, width, height, 4)?;
+        if data.len() != expected {
+            return Err(format!(
+                "RGBA data for {} is {} bytes, expected {}",
+                name,
+                data.len(),
+                expected
+            ));
+        }
+
+        let pixels = data
+            .chunks_exact(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect();
+
+        Ok(Image::new(name, width, height, pixels))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.len() > 15 || !self.name.is_ascii() {
+            return Err(format!(
+                "Invalid texture name {:?}, expected 1 to 15 ASCII characters",
+                self.name
+            ));
+        }
+
+        // Quake's renderer and compilers assume every mip level has whole 2x2 blocks
+        if self.width == 0 || self.height == 0 || self.width % 16 != 0 || self.height % 16 != 0 {
+            return Err(format!(
+                "Texture {} is {}x{}, dimensions must be non-zero multiples of 16",
+                self.name, self.width, self.height
+            ));
+        }
+
+        let expected = image_bytes(&self.name, self.width, self.height, 1)?;
+        if self.pixels.len() != expected {
+            return Err(format!(
+                "Texture {} has {} pixels, expected {}",
+                self.name,
+                self.pixels.len(),
+                expected
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Size of a width by height image with this many bytes per pixel, unless it overflows
+fn image_bytes(name: &str, width: u32, height: u32, channels: usize) -> Result<usize, String> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or_else(|| format!("Texture {} is too large at {}x{}", name, width, height))
+}
+
+pub fn write_wad(wad_file: &str, images: &[Image], format: &WadFormat) -> Result<(), String> {
+    let buf = to_wad(images, format)?;
+
+    match std::fs::write(wad_file, buf) {
+        Ok(()) => Ok(()),
+        Err(err) => Err(format!("Error writing WAD file {:?}: {:?}", wad_file, err)),
+    }
+}
+
+pub fn to_wad(images: &[Image], format: &WadFormat) -> Result<Vec<u8>, String> {
+    // The reader lowercases names, so names differing only in case would collide
+    let mut names = HashSet::new();
+    for image in images {
+        if !names.insert(image.name.to_lowercase()) {
+            return Err(format!("Duplicate texture name {:?}", image.name));
+        }
+    }
+
+    let lumps: Vec<Vec<u8>> = images
+        .par_iter()
+        .map(|image| miptex_lump(image, format))
+        .collect::<Result<_, String>>()?;
+
+    let (magic, entry_type) = match format {
+        WadFormat::WAD2(_) => (['W', 'A', 'D', '2'], 'D'),
+        WadFormat::WAD3 => (['W', 'A', 'D', '3'], 'C'),
+    };
+
+    let dir_offset = HEADER_BYTES + lumps.iter().map(Vec::len).sum::<usize>();
+
+    let header = Header {
+        magic,
+        num_entries: images.len() as u32,
+        dir_offset: dir_offset as u32,
+    };
+
+    let mut buf = header.to_bytes();
+    let mut directory = Vec::with_capacity(images.len());
+
+    for (image, lump) in images.iter().zip(lumps) {
+        directory.push(Entry {
+            offset: buf.len() as u32,
+            dsize: lump.len() as u32,
+            size: lump.len() as u32,
+            entry_type,
+            cmprs: 0,
+            name: image.name.clone(),
+        });
+        buf.extend(lump);
+    }
+
+    for entry in directory {
+        buf.extend(entry.to_bytes());
+    }
+
+    Ok(buf)
+}
+
+fn miptex_lump(image: &Image, format: &WadFormat) -> Result<Vec<u8>, String> {
+    image.validate()?;
+
+    let has_transparency = image.pixels.iter().any(is_transparent);
+
+    // Opaque pixels only ever map into the first `candidates` palette entries
+    let (palette, candidates) = match format {
+        WadFormat::WAD2(palette) => (**palette, FULLBRIGHT_START),
+        WadFormat::WAD3 => {
+            let max_colors = if has_transparency { 255 } else { 256 };
+            let colors = median_cut(&image.pixels, max_colors);
+
+            let mut palette = Palette([Color(0, 0, 0); 256]);
+            palette.0[..colors.len()].copy_from_slice(&colors);
+            if has_transparency {
+                palette.0[TRANSPARENT_INDEX as usize] = WAD3_TRANSPARENT_COLOR;
+            }
+
+            (palette, colors.len())
+        }
+    };
+
+    let mut cache = HashMap::new();
+    let mut width = image.width as usize;
+    let mut height = image.height as usize;
+    let mut level = image.pixels.clone();
+    let mut mip_levels = Vec::with_capacity(4);
+
+    for i in 0..4 {
+        if i > 0 {
+            level = downsample(&level, width, height);
+            width /= 2;
+            height /= 2;
+        }
+
+        let indices: Vec<u8> = level
+            .iter()
+            .map(|pixel| {
+                if is_transparent(pixel) {
+                    return TRANSPARENT_INDEX;
+                }
+
+                let rgb = [pixel[0], pixel[1], pixel[2]];
+                *cache
+                    .entry(rgb)
+                    .or_insert_with(|| nearest(&palette, candidates, rgb))
+            })
+            .collect();
+
+        mip_levels.push(indices);
+    }
+
+    let offset1 = MIPTEX_BYTES as u32;
+    let offset2 = offset1 + mip_levels[0].len() as u32;
+    let offset4 = offset2 + mip_levels[1].len() as u32;
+    let offset8 = offset4 + mip_levels[2].len() as u32;
+
+    let mip_texture = MipTexture {
+        name: image.name.clone(),
+        width: image.width,
+        height: image.height,
+        offset1,
+        offset2,
+        offset4,
+        offset8,
+    };
+
+    let mut buf = mip_texture.to_bytes();
+    for mip_level in mip_levels {
+        buf.extend(mip_level);
+    }
+
+    if let WadFormat::WAD3 = format {
+        buf.extend_from_slice(&256u16.to_le_bytes());
+        buf.extend(palette.to_bytes());
+        // Pad the lump back to a 4-byte boundary
+        buf.extend_from_slice(&[0u8; 2]);
+    }
+
+    Ok(buf)
+}
+
+fn is_transparent(pixel: &[u8; 4]) -> bool {
+    pixel[3] < ALPHA_THRESHOLD
+}
+
+// Box filter each 2x2 block, averaging only its opaque pixels
+fn downsample(pixels: &[[u8; 4]], width: usize, height: usize) -> Vec<[u8; 4]> {
+    let half_width = width / 2;
+    let half_height = height / 2;
+
+    (0..half_height)
+        .flat_map(|y| {
+            (0..half_width).map(move |x| {
+                let top = 2 * y * width + 2 * x;
+                let bottom = top + width;
+                let block = [
+                    pixels[top],
+                    pixels[top + 1],
+                    pixels[bottom],
+                    pixels[bottom + 1],
+                ];
+
+                let opaque: Vec<&[u8; 4]> = block
+                    .iter()
+                    .filter(|pixel| !is_transparent(pixel))
+                    .collect();
+
+                // A block stays transparent unless at least half of it is opaque
+                if opaque.len() < 2 {
+                    return [0, 0, 0, 0];
+                }
+
+                let count = opaque.len() as u32;
+                let mut sum = [0u32; 3];
+                for pixel in &opaque {
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += pixel[channel] as u32;
+                    }
+                }
+
+                [
+                    ((sum[0] + count / 2) / count) as u8,
+                    ((sum[1] + count / 2) / count) as u8,
+                    ((sum[2] + count / 2) / count) as u8,
+                    255,
+                ]
+            })
+        })
+        .collect()
+}
+
+// Repeatedly split the box with the widest channel range at its median, then average each box
+fn median_cut(pixels: &[[u8; 4]], max_colors: usize) -> Vec<Color> {
+    let opaque: Vec<[u8; 3]> = pixels
+        .iter()
+        .filter(|pixel| !is_transparent(pixel))
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+
+    if opaque.is_empty() {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![opaque];
+
+    while boxes.len() < max_colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, colors)| (i, widest_channel(colors)))
+            .filter(|(_, (_, range))| *range > 0)
+            .max_by_key(|(_, (_, range))| *range);
+
+        let (i, (channel, _)) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        let mut lower = boxes.swap_remove(i);
+        lower.sort_unstable_by_key(|color| color[channel]);
+        let upper = lower.split_off(lower.len() / 2);
+
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let count = colors.len() as u64;
+            let mut sum = [0u64; 3];
+            for color in colors {
+                for (channel, total) in sum.iter_mut().enumerate() {
+                    *total += color[channel] as u64;
+                }
+            }
+
+            Color(
+                ((sum[0] + count / 2) / count) as u8,
+                ((sum[1] + count / 2) / count) as u8,
+                ((sum[2] + count / 2) / count) as u8,
+            )
+        })
+        .collect()
+}
+
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = colors.iter().map(|color| color[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|color| color[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn nearest(Palette(palette): &Palette, candidates: usize, [r, g, b]: [u8; 3]) -> u8 {
+    palette[..candidates]
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, Color(pr, pg, pb))| {
+            let dr = *pr as i32 - r as i32;
+            let dg = *pg as i32 - g as i32;
+            let db = *pb as i32 - b as i32;
+            dr * dr + dg * dg + db * db
+        })
+        .map(|(i, _)| i as u8)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker(name: &str, transparent: bool) -> Image {
+        let pixels = (0..32 * 32)
+            .map(|i| {
+                let (x, y) = (i % 32, i / 32);
+                match ((x / 8) + (y / 8)) % 2 {
+                    0 => [200, 40, 40, 255],
+                    _ if transparent => [0, 0, 0, 0],
+                    _ => [40, 40, 200, 255],
+                }
+            })
+            .collect();
+
+        Image::new(name.into(), 32, 32, pixels)
+    }
+
+    fn read_back(name: &str, buf: &[u8], mip_levels: usize) -> Vec<super::super::TextureIndexed> {
+        let wad_file =
+            std::env::temp_dir().join(format!("quarchitect-{}-{}.wad", name, std::process::id()));
+        std::fs::write(&wad_file, buf).unwrap();
+
+        let textures = super::super::read_textures(wad_file.to_str().unwrap(), None, mip_levels);
+        std::fs::remove_file(&wad_file).ok();
+        textures.unwrap()
+    }
+
+    #[test]
+    fn wad2_round_trip() {
+        let mut palette = Palette([Color(0, 0, 0); 256]);
+        palette.0[10] = Color(200, 40, 40);
+        palette.0[20] = Color(40, 40, 200);
+        // Fullbright entries are never chosen, even on an exact match
+        palette.0[230] = Color(200, 40, 40);
+
+        let images = vec![checker("brick", false), checker("{grate", true)];
+        let buf = to_wad(&images, &WadFormat::WAD2(Box::new(palette))).unwrap();
+
+        let textures = read_back("wad2", &buf, 1);
+        assert!(textures.len() == 2, "Texture count {}", textures.len());
+
+        let brick = &textures[0];
+        assert!(
+            brick.mip_texture.name == "brick"
+                && brick.mip_texture.width == 32
+                && brick.mip_texture.offset8 == 40 + 32 * 32 + 16 * 16 + 8 * 8,
+            "Miptex {:?}",
+            brick.mip_texture
+        );
+
+        let mip0 = &brick.mip_data.mip0.as_ref().unwrap().0;
+        assert!(
+            mip0[0] == 10 && mip0[8] == 20,
+            "Brick indices {:?}",
+            &mip0[..16]
+        );
+
+        let grate = &textures[1].mip_data.mip0.as_ref().unwrap().0;
+        assert!(
+            grate[0] == 10 && grate[8] == TRANSPARENT_INDEX,
+            "Grate indices {:?}",
+            &grate[..16]
+        );
+        assert!(
+            textures[1].palette.is_none(),
+            "WAD2 texture carried a palette"
+        );
+    }
+
+    #[test]
+    fn wad3_round_trip() {
+        let images = vec![checker("{grate", true)];
+        let buf = to_wad(&images, &WadFormat::WAD3).unwrap();
+
+        let textures = read_back("wad3", &buf, 1);
+        let Palette(palette) = textures[0].palette.expect("WAD3 texture palette");
+        let mip0 = &textures[0].mip_data.mip0.as_ref().unwrap().0;
+
+        let Color(r, g, b) = palette[mip0[0] as usize];
+        assert!((r, g, b) == (200, 40, 40), "Opaque colour {:?}", (r, g, b));
+        assert!(
+            mip0[8] == TRANSPARENT_INDEX,
+            "Transparent index {}",
+            mip0[8]
+        );
+
+        let Color(r, g, b) = palette[TRANSPARENT_INDEX as usize];
+        assert!(
+            (r, g, b) == (0, 0, 255),
+            "Transparent colour {:?}",
+            (r, g, b)
+        );
+    }
+
+    #[test]
+    fn mip_levels() {
+        let buf = to_wad(&[checker("brick", false)], &WadFormat::WAD3).unwrap();
+        let textures = read_back("mips", &buf, 4);
+
+        // 8x8 checker squares survive as 1x1 squares in the smallest mip
+        let mip3 = &textures[0].mip_data.mip3.as_ref().unwrap().0;
+        assert!(
+            mip3[0] != mip3[1] && mip3[0] == mip3[2] && mip3[0] == mip3[5],
+            "Mip 3 {:?}",
+            mip3
+        );
+    }
+
+    #[test]
+    fn invalid_images() {
+        let odd_size = Image::new("odd".into(), 24, 16, vec![[0, 0, 0, 255]; 24 * 16]);
+        assert!(to_wad(&[odd_size], &WadFormat::WAD3).is_err());
+
+        let long_name = Image::new("a_very_long_texture".into(), 16, 16, vec![[0; 4]; 256]);
+        assert!(to_wad(&[long_name], &WadFormat::WAD3).is_err());
+
+        let duplicates = vec![checker("brick", false), checker("BRICK", false)];
+        assert!(to_wad(&duplicates, &WadFormat::WAD3).is_err());
+
+        let short_data = Image::from_rgb("short".into(), 16, 16, &[0; 16 * 16 * 3 - 1]);
+        assert!(short_data.is_err());
+
+        let huge = Image::from_rgba("huge".into(), u32::MAX, u32::MAX, &[]).err();
+        assert!(
+            huge.as_deref() == Some("Texture huge is too large at 4294967295x4294967295"),
+            "Error {:?}",
+            huge
+        );
+    }
+}
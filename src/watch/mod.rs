@@ -0,0 +1,334 @@
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::scene_tree::SceneTreeNode;
+use crate::Config;
+
+#[derive(Debug, Clone)]
+pub struct WatchSettings {
+    pub files: Vec<PathBuf>,
+    pub poll_interval: Duration,
+    pub debounce: Duration,
+    pub socket: Option<SocketAddr>,
+}
+
+impl WatchSettings {
+    pub fn new(
+        files: Vec<PathBuf>,
+        poll_interval: Duration,
+        debounce: Duration,
+        socket: Option<SocketAddr>,
+    ) -> WatchSettings {
+        WatchSettings {
+            files,
+            poll_interval,
+            debounce,
+            socket,
+        }
+    }
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        // TrenchBroom writes the map and its backup in quick succession
+        let files = Vec::new();
+        let poll_interval = Duration::from_millis(100);
+        let debounce = Duration::from_millis(300);
+        let socket = None;
+
+        WatchSettings {
+            files,
+            poll_interval,
+            debounce,
+            socket,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WatchControl {
+    Continue,
+    Stop,
+}
+
+type FileState = Option<(SystemTime, u64)>;
+
+fn file_state(file: &PathBuf) -> FileState {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// A client that stops reading is dropped after this long, rather than stalling the watch loop
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Pushes each build to loopback clients as a line of JSON
+struct SocketTarget {
+    listener: TcpListener,
+    clients: Vec<TcpStream>,
+}
+
+impl SocketTarget {
+    fn bind(addr: SocketAddr) -> io::Result<SocketTarget> {
+        if !addr.ip().is_loopback() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Watch socket must be bound to a loopback address",
+            ));
+        }
+
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(SocketTarget {
+            listener,
+            clients: Vec::new(),
+        })
+    }
+
+    fn accept(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(false).is_ok()
+                && stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)).is_ok()
+            {
+                self.clients.push(stream);
+            }
+        }
+    }
+
+    fn push(&mut self, result: &Result<Vec<SceneTreeNode>, Box<dyn Error>>) {
+        self.accept();
+
+        let message = match result {
+            Ok(scene_tree) => serde_json::json!({ "scene_tree": scene_tree }),
+            Err(err) => serde_json::json!({ "error": err.to_string() }),
+        };
+        let message = format!("{}\n", message);
+
+        self.clients
+            .retain_mut(|client| client.write_all(message.as_bytes()).is_ok());
+    }
+}
+
+// Builds once, then rebuilds whenever the map, any extra file, or any WAD or texture image the
+// last build read settles after a change. The config is recreated for every build so that WADs
+// and game data are reloaded.
+pub fn run<F, C>(
+    settings: &WatchSettings,
+    mut make_config: F,
+    mut callback: C,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut() -> Result<Config, Box<dyn Error>>,
+    C: FnMut(Result<Vec<SceneTreeNode>, Box<dyn Error>>) -> WatchControl,
+{
+    let mut socket = match settings.socket {
+        Some(addr) => Some(SocketTarget::bind(addr)?),
+        None => None,
+    };
+
+    let config = make_config()?;
+    let mut watched_files = vec![PathBuf::from(&config.map_file)];
+    watched_files.extend(settings.files.iter().cloned());
+
+    let mut files = watched_files.clone();
+    let mut states: Vec<FileState> = files.iter().map(file_state).collect();
+    let mut config = Some(config);
+
+    loop {
+        let config = match config.take() {
+            Some(config) => Ok(config),
+            None => make_config(),
+        };
+
+        let result = config.and_then(crate::run_with_report);
+
+        // Files the build read may differ from the last one, e.g. after the wad key changes.
+        // States already known are kept, so a change made during the build still counts.
+        if let Ok((_scene_tree, report)) = &result {
+            let mut new_files = watched_files.clone();
+            for file in &report.source_files {
+                if !new_files.contains(file) {
+                    new_files.push(file.clone());
+                }
+            }
+
+            states = new_files
+                .iter()
+                .map(|file| match files.iter().position(|known| known == file) {
+                    Some(i) => states[i],
+                    None => file_state(file),
+                })
+                .collect();
+            files = new_files;
+        }
+
+        let result = result.map(|(scene_tree, _report)| scene_tree);
+
+        if let Some(socket) = &mut socket {
+            socket.push(&result);
+        }
+
+        if callback(result) == WatchControl::Stop {
+            return Ok(());
+        }
+
+        // Wait for a change, then for the files to stop changing
+        let mut last_change: Option<Instant> = None;
+        loop {
+            thread::sleep(settings.poll_interval);
+
+            if let Some(socket) = &mut socket {
+                socket.accept();
+            }
+
+            let new_states: Vec<FileState> = files.iter().map(file_state).collect();
+            if new_states != states {
+                states = new_states;
+                last_change = Some(Instant::now());
+                continue;
+            }
+
+            if let Some(last_change) = last_change {
+                if last_change.elapsed() >= settings.debounce {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game_data, TextureBlacklist, TextureInfo};
+    use std::collections::HashMap;
+
+    #[test]
+    fn rebuild_on_change() {
+        let map_file =
+            std::env::temp_dir().join(format!("quarchitect-watch-{}.map", std::process::id()));
+        fs::write(&map_file, "{\n\"classname\" \"worldspawn\"\n}\n").unwrap();
+
+        let settings = WatchSettings::new(
+            vec![],
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            None,
+        );
+
+        let map_path = map_file.to_string_lossy().to_string();
+        let mut entity_counts = Vec::new();
+
+        run(
+            &settings,
+            || {
+                Ok(Config::new(
+                    &map_path,
+                    TextureInfo(HashMap::new()),
+                    TextureBlacklist::default(),
+                    game_data::forge::GameData::default(),
+                    game_data::GameData::default(),
+                ))
+            },
+            |result| {
+                entity_counts.push(result.unwrap().len());
+                if entity_counts.len() == 1 {
+                    fs::write(
+                        &map_file,
+                        "{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"info_null\"\n\"origin\" \"0 0 0\"\n}\n",
+                    )
+                    .unwrap();
+                    WatchControl::Continue
+                } else {
+                    WatchControl::Stop
+                }
+            },
+        )
+        .unwrap();
+
+        fs::remove_file(&map_file).unwrap();
+
+        assert!(
+            entity_counts == vec![1, 2],
+            "Watch entity counts {:?}",
+            entity_counts
+        );
+    }
+
+    #[test]
+    fn rebuild_on_wad_change() {
+        let directory =
+            std::env::temp_dir().join(format!("quarchitect-watch-wad-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let map_file = directory.join("test.map");
+        let wad_file = directory.join("textures.wad");
+        fs::write(
+            &map_file,
+            "{\n\"classname\" \"worldspawn\"\n\"wad\" \"textures.wad\"\n}\n",
+        )
+        .unwrap();
+        fs::copy(
+            format!("{}/src/wad/test_data/wad3.wad", env!("CARGO_MANIFEST_DIR")),
+            &wad_file,
+        )
+        .unwrap();
+
+        let settings = WatchSettings::new(
+            vec![],
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+            None,
+        );
+
+        // Touch the map if the WAD change is missed, so a failure can't hang
+        let fallback_map_file = map_file.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(10));
+            fs::write(&fallback_map_file, "{\n\"classname\" \"worldspawn\"\n}\n").ok();
+        });
+
+        let map_path = map_file.to_string_lossy().to_string();
+        let start = Instant::now();
+        let mut builds = 0;
+
+        run(
+            &settings,
+            || {
+                Ok(Config::new(
+                    &map_path,
+                    TextureInfo(HashMap::new()),
+                    TextureBlacklist::default(),
+                    game_data::forge::GameData::default(),
+                    game_data::GameData::default(),
+                )
+                .with_wad_search_paths(vec![]))
+            },
+            |result| {
+                result.unwrap();
+                builds += 1;
+                if builds == 1 {
+                    let mut wad = fs::OpenOptions::new().append(true).open(&wad_file).unwrap();
+                    wad.write_all(&[0; 16]).unwrap();
+                    WatchControl::Continue
+                } else {
+                    WatchControl::Stop
+                }
+            },
+        )
+        .unwrap();
+
+        fs::remove_dir_all(&directory).ok();
+
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "WAD change missed, rebuilt after {:?}",
+            start.elapsed()
+        );
+    }
+}